@@ -0,0 +1,363 @@
+//! passmgr-server
+//!
+//! A self-hostable, zero-knowledge sync server for rust-passmgr.
+//! Stores one opaque encrypted vault per user, tagged with a revision
+//! number, and never sees the master password or the vault key.
+//!
+//! HTTP API (all requests need `Authorization: Bearer <token>`):
+//! - `GET /v1/vault` — returns the stored vault with an `X-Revision` header,
+//!   or `404` if nothing was pushed yet.
+//! - `PUT /v1/vault` — stores a new vault. The `X-Base-Revision` header must
+//!   name the current revision (`0` for the first push); otherwise the write
+//!   is rejected with `409 Conflict`. Returns the new `X-Revision`.
+//!
+//! TLS is not handled here; put the server behind a reverse proxy.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Largest vault accepted by `PUT /v1/vault` (32 MiB).
+const MAX_VAULT_SIZE: usize = 32 * 1024 * 1024;
+
+/// 🔐 Zero-knowledge sync server for rust-passmgr.
+#[derive(Parser)]
+#[command(name = "passmgr-server", version, about = "Self-hostable sync server for rust-passmgr")]
+struct Cli {
+    /// Directory holding users and vaults.
+    #[arg(short, long, default_value = "passmgr-data", global = true)]
+    data: PathBuf,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Serves the sync API.
+    Serve {
+        /// Address to listen on.
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        listen: String,
+    },
+    /// Creates a user (or replaces their token) and prints the new token.
+    AddUser {
+        /// User name (letters, digits, `-`, `_` and `.`).
+        name: String,
+    },
+    /// Deletes a user and their stored vault.
+    RemoveUser {
+        /// User name.
+        name: String,
+    },
+}
+
+/// A stored vault together with its revision number.
+#[derive(Serialize, Deserialize)]
+struct StoredVault {
+    revision: u64,
+    /// The vault exactly as uploaded; never parsed by the server.
+    blob: String,
+}
+
+/// Server state kept on disk under the data directory.
+struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    fn users_path(&self) -> PathBuf {
+        self.dir.join("users.json")
+    }
+
+    fn vault_path(&self, user: &str) -> PathBuf {
+        self.dir.join("vaults").join(format!("{user}.json"))
+    }
+
+    /// Returns the map of user names to hex SHA-256 hashes of their tokens.
+    fn users(&self) -> Result<BTreeMap<String, String>> {
+        let path = self.users_path();
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let s = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&s).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save_users(&self, users: &BTreeMap<String, String>) -> Result<()> {
+        write_atomic(&self.users_path(), serde_json::to_string_pretty(users)?.as_bytes())
+    }
+
+    /// Finds the user a bearer token belongs to.
+    fn authenticate(&self, token: &str) -> Result<Option<String>> {
+        let hash = token_hash(token);
+        Ok(self
+            .users()?
+            .into_iter()
+            .find(|(_, h)| *h == hash)
+            .map(|(name, _)| name))
+    }
+
+    fn load(&self, user: &str) -> Result<Option<StoredVault>> {
+        let path = self.vault_path(user);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let s = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Some(serde_json::from_str(&s)?))
+    }
+
+    fn save(&self, user: &str, vault: &StoredVault) -> Result<()> {
+        write_atomic(&self.vault_path(user), serde_json::to_string(vault)?.as_bytes())
+    }
+}
+
+/// Writes a file via a temporary file and rename, so readers never see partial data.
+///
+/// Files are created with mode `0600` and directories with `0700`.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(&tmp)
+        .and_then(|mut f| f.write_all(data))
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn valid_user_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn text(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(format!("{message}\n")).with_status_code(status)
+}
+
+/// Handles a single API request.
+fn handle(store: &Store, request: &mut Request) -> Result<Response<std::io::Cursor<Vec<u8>>>> {
+    if request.url() != "/v1/vault" {
+        return Ok(text(404, "not found"));
+    }
+
+    let token = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(str::to_string);
+    let Some(user) = token.map(|t| store.authenticate(&t)).transpose()?.flatten() else {
+        return Ok(text(401, "invalid or missing token"));
+    };
+
+    match request.method() {
+        Method::Get => match store.load(&user)? {
+            Some(vault) => Ok(Response::from_string(vault.blob)
+                .with_header(header("Content-Type", "application/json"))
+                .with_header(header("X-Revision", &vault.revision.to_string()))),
+            None => Ok(text(404, "no vault stored yet")),
+        },
+        Method::Put => {
+            let base: Option<u64> = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("X-Base-Revision"))
+                .and_then(|h| h.value.as_str().parse().ok());
+            let Some(base) = base else {
+                return Ok(text(400, "missing or invalid X-Base-Revision header"));
+            };
+
+            let mut blob = String::new();
+            let read = request
+                .as_reader()
+                .take(MAX_VAULT_SIZE as u64 + 1)
+                .read_to_string(&mut blob);
+            if read.is_err() {
+                return Ok(text(400, "vault must be UTF-8 JSON"));
+            }
+            if blob.len() > MAX_VAULT_SIZE {
+                return Ok(text(413, "vault too large"));
+            }
+
+            let current = store.load(&user)?.map_or(0, |v| v.revision);
+            if base != current {
+                return Ok(text(409, "base revision is out of date")
+                    .with_header(header("X-Revision", &current.to_string())));
+            }
+
+            let revision = current + 1;
+            store.save(&user, &StoredVault { revision, blob })?;
+            Ok(text(200, "stored").with_header(header("X-Revision", &revision.to_string())))
+        }
+        _ => Ok(text(405, "method not allowed")),
+    }
+}
+
+fn serve(store: &Store, listen: &str) -> Result<()> {
+    let server = Server::http(listen).map_err(|e| anyhow!("cannot listen on {listen}: {e}"))?;
+    println!("🔐 passmgr-server listening on http://{listen}");
+
+    // Requests are handled one at a time, so revision checks cannot race.
+    for mut request in server.incoming_requests() {
+        let response = handle(store, &mut request).unwrap_or_else(|e| {
+            eprintln!("❌ {e:#}");
+            text(500, "internal error")
+        });
+        if let Err(e) = request.respond(response) {
+            eprintln!("⚠️  Failed to send response: {e}");
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let store = Store { dir: cli.data };
+
+    match cli.command {
+        Commands::Serve { listen } => serve(&store, &listen)?,
+        Commands::AddUser { name } => {
+            if !valid_user_name(&name) {
+                return Err(anyhow!("invalid user name: {name}"));
+            }
+            let mut bytes = [0u8; 32];
+            OsRng.fill_bytes(&mut bytes);
+            let token = hex::encode(bytes);
+
+            let mut users = store.users()?;
+            users.insert(name.clone(), token_hash(&token));
+            store.save_users(&users)?;
+            println!("✅ User '{name}' created. Token (shown only once):");
+            println!("{token}");
+        }
+        Commands::RemoveUser { name } => {
+            let mut users = store.users()?;
+            if users.remove(&name).is_none() {
+                println!("⚠️  User '{name}' not found.");
+                return Ok(());
+            }
+            store.save_users(&users)?;
+            let vault = store.vault_path(&name);
+            if vault.exists() {
+                fs::remove_file(&vault)
+                    .with_context(|| format!("Failed to remove {}", vault.display()))?;
+            }
+            println!("🗑️  Removed user '{name}'.");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tiny_http::TestRequest;
+
+    /// Creates a store in `dir` with the user `alice`, whose token is `secret`.
+    fn store(dir: &TempDir) -> Store {
+        let store = Store { dir: dir.path().to_path_buf() };
+        store.save_users(&BTreeMap::from([("alice".to_string(), token_hash("secret"))])).unwrap();
+        store
+    }
+
+    /// Sends a request to [`handle`] and returns the status code, the
+    /// `X-Revision` header and the body of the response.
+    fn send(
+        store: &Store,
+        method: Method,
+        token: Option<&str>,
+        base: Option<&str>,
+        body: &'static str,
+    ) -> (u16, Option<String>, String) {
+        let mut request = TestRequest::new().with_method(method).with_path("/v1/vault").with_body(body);
+        if let Some(token) = token {
+            request = request.with_header(header("Authorization", &format!("Bearer {token}")));
+        }
+        if let Some(base) = base {
+            request = request.with_header(header("X-Base-Revision", base));
+        }
+        let response = handle(store, &mut request.into()).unwrap();
+        let revision = response
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("X-Revision"))
+            .map(|h| h.value.to_string());
+        let status = response.status_code().0;
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        (status, revision, body)
+    }
+
+    /// Requests without a known token are refused and change nothing.
+    #[test]
+    fn rejects_missing_and_wrong_tokens() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+
+        assert_eq!(send(&store, Method::Get, None, None, "").0, 401);
+        assert_eq!(send(&store, Method::Get, Some("guess"), None, "").0, 401);
+        assert_eq!(send(&store, Method::Put, Some("guess"), Some("0"), "{}").0, 401);
+        assert!(store.load("alice").unwrap().is_none());
+    }
+
+    /// Every accepted push bumps the revision, which reads return.
+    #[test]
+    fn bumps_the_revision_on_each_push() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+
+        assert_eq!(send(&store, Method::Get, Some("secret"), None, "").0, 404);
+        assert_eq!(send(&store, Method::Put, Some("secret"), None, "{}").0, 400);
+        let (status, revision, _) = send(&store, Method::Put, Some("secret"), Some("0"), "one");
+        assert_eq!((status, revision.as_deref()), (200, Some("1")));
+        let (status, revision, _) = send(&store, Method::Put, Some("secret"), Some("1"), "two");
+        assert_eq!((status, revision.as_deref()), (200, Some("2")));
+        assert_eq!(send(&store, Method::Get, Some("secret"), None, ""), (200, Some("2".into()), "two".into()));
+    }
+
+    /// A push based on an older revision is refused with the current one,
+    /// and the stored vault stays as it was.
+    #[test]
+    fn rejects_pushes_based_on_a_stale_revision() {
+        let dir = TempDir::new().unwrap();
+        let store = store(&dir);
+        send(&store, Method::Put, Some("secret"), Some("0"), "one");
+        send(&store, Method::Put, Some("secret"), Some("1"), "two");
+
+        let (status, revision, _) = send(&store, Method::Put, Some("secret"), Some("1"), "stale");
+        assert_eq!((status, revision.as_deref()), (409, Some("2")));
+        let (status, revision, _) = send(&store, Method::Put, Some("secret"), Some("0"), "stale");
+        assert_eq!((status, revision.as_deref()), (409, Some("2")));
+        assert_eq!(send(&store, Method::Get, Some("secret"), None, "").2, "two");
+    }
+}
@@ -0,0 +1,119 @@
+//! Sidecar store for large attachments.
+//!
+//! Attachments larger than the vault's inline limit (see
+//! [`crate::model::Settings`]) are not kept in the vault itself but in a
+//! directory next to it, one encrypted file per attachment:
+//!
+//! ```text
+//! vault.json
+//! vault.json.blobs/
+//! └── 3f2b...-....blob          single-file layout
+//!
+//! vault/
+//! └── .blobs/
+//!     └── 3f2b...-....blob      directory layout (committed with the entries)
+//! ```
+//!
+//! Blob files are named by a random id rather than by a hash of their
+//! contents, so the store does not reveal which files it holds. They are
+//! encrypted with the vault key in the same `[ nonce | ciphertext ]` format
+//! as the vault.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use uuid::Uuid;
+use crate::{
+    crypto::{decrypt_bytes_with_key, encrypt_bytes_with_key, KEY_LEN},
+    perms,
+    storage::Layout,
+};
+
+/// Extension of blob files.
+const BLOB_EXT: &str = "blob";
+
+/// Returns the blob directory of the vault at `path`.
+pub fn dir(path: &Path, layout: Layout) -> PathBuf {
+    match layout {
+        Layout::File => {
+            let mut name = path.file_name().unwrap_or_default().to_os_string();
+            name.push(".blobs");
+            path.with_file_name(name)
+        }
+        Layout::Directory => path.join(".blobs"),
+    }
+}
+
+/// Returns the path of the blob `id`.
+fn blob_path(path: &Path, layout: Layout, id: &Uuid) -> PathBuf {
+    dir(path, layout).join(format!("{id}.{BLOB_EXT}"))
+}
+
+/// Encrypts `data` and stores it as the blob `id`.
+pub fn write(path: &Path, layout: Layout, key: &[u8; KEY_LEN], id: &Uuid, data: &[u8]) -> Result<()> {
+    perms::create_dir_all(&dir(path, layout))?;
+    perms::write_file(&blob_path(path, layout, id), &encrypt_bytes_with_key(data, key)?)
+}
+
+/// Reads and decrypts the blob `id`.
+pub fn read(path: &Path, layout: Layout, key: &[u8; KEY_LEN], id: &Uuid) -> Result<Vec<u8>> {
+    let file = blob_path(path, layout, id);
+    let blob = match fs::read(&file) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!(
+                "the attachment's blob is not on this machine ({}); only git sync and `merge` carry blobs between machines",
+                file.display()
+            ));
+        }
+        blob => blob.with_context(|| format!("Failed to read attachment blob: {}", file.display()))?,
+    };
+    decrypt_bytes_with_key(&blob, key)
+        .with_context(|| format!("Attachment blob is damaged: {}", file.display()))
+}
+
+/// Returns `true` if the blob `id` is in the store.
+pub fn exists(path: &Path, layout: Layout, id: &Uuid) -> bool {
+    blob_path(path, layout, id).is_file()
+}
+
+/// Returns how many blobs the store holds.
+pub fn count(path: &Path, layout: Layout) -> usize {
+    fs::read_dir(dir(path, layout)).map_or(0, |items| {
+        items
+            .filter_map(|item| item.ok())
+            .filter(|item| item.path().extension().is_some_and(|ext| ext == BLOB_EXT))
+            .count()
+    })
+}
+
+/// Deletes the blob `id`; a blob that is already gone is not an error.
+pub fn remove(path: &Path, layout: Layout, id: &Uuid) -> Result<()> {
+    let file = blob_path(path, layout, id);
+    match fs::remove_file(&file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove attachment blob: {}", file.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Copies every blob of one vault to another, e.g. when converting layouts.
+///
+/// Does nothing if the source vault has no blobs.
+pub fn copy_all(from: &Path, from_layout: Layout, to: &Path, to_layout: Layout) -> Result<()> {
+    let source = dir(from, from_layout);
+    if !source.is_dir() {
+        return Ok(());
+    }
+    let target = dir(to, to_layout);
+    perms::create_dir_all(&target)?;
+    for item in fs::read_dir(&source)
+        .with_context(|| format!("Failed to read blob directory: {}", source.display()))?
+    {
+        let file = item?.path();
+        if file.extension().is_some_and(|ext| ext == BLOB_EXT) {
+            perms::copy_file(&file, &target.join(file.file_name().unwrap_or_default()))?;
+        }
+    }
+    Ok(())
+}
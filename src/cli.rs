@@ -1,10 +1,23 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand, CommandFactory};
+use clap::{Args, Parser, Subcommand, CommandFactory};
 use std::path::PathBuf;
+use uuid::Uuid;
 
 use crate::commands::{
     handle_init, handle_add, handle_list, handle_get, handle_remove, handle_update,
+    handle_log, handle_sync, handle_checkout, handle_merge, handle_convert, handle_verify,
+    handle_remote_login, handle_remote_push, handle_remote_pull, handle_remote_sync,
+    handle_field_set, handle_field_get, handle_field_remove, handle_export,
+    handle_history, handle_history_restore, handle_mv, handle_attach, handle_attachments,
+    handle_extract, handle_detach, handle_config, handle_expiry, handle_due,
+    handle_trash_list, handle_trash_restore, handle_trash_purge, handle_refs, handle_edit,
+    handle_rename, handle_cp, handle_search, handle_generate, handle_policy_list,
+    handle_policy_set, handle_policy_remove, handle_policy_assign, handle_rotate,
 };
+use crate::merge::Strategy;
+use crate::model::{EntryType, FieldKind};
+use crate::perms;
+use crate::storage::Layout;
 
 /// 🔐 Minimal password manager written in Rust.
 ///
@@ -20,22 +33,94 @@ use crate::commands::{
 
 Subcommands:
   init      Create a new vault (vault.json)
-  add       Add a new entry (key, login, password, note)
-  list      Show all saved keys
+  add       Add a new entry (login, note, card, identity, SSH key, API token, Wi-Fi)
+  list      Show all saved keys (or one folder, optionally as a tree)
+  search    Fuzzy-search entries by key, login, URL, tag or notes
   get       Display a specific entry
-  remove    Delete an entry from the vault
+  generate  Generate a random password or diceware passphrase
+  policy    Manage password policies and assign them to entries
+  edit      Change an entry's login, password, notes or other values
+  rotate    Generate a new password and change it via a hook
+  remove    Move an entry (or with -r, a folder) to the trash
+  trash     List, restore or purge removed entries
+  mv        Rename an entry or move entries and folders
+  rename    Give an entry or folder a new key
+  cp        Copy an entry or folder
+  field     Set, get or remove an entry's custom fields
+  export    Write entries as unencrypted JSON
+  history   List or restore an entry's earlier passwords
+  attach    Attach a file to an entry (attachments, extract, detach)
+  config    Show or change the vault settings
+  expiry    Set when an entry expires and how often it must be rotated
+  due       List expired entries and passwords due for rotation
+  refs      Show an entry's references to and from other entries
+  log       Show the git history of the vault
+  sync      Pull, merge and push the vault via git
+  checkout  Restore the vault from a git revision
+  merge     Merge a diverged copy of the vault
+  convert   Switch between the single-file and directory-per-entry layouts
+  verify    Check the vault for damage and restore it from a backup
+  remote    Sync the vault with a passmgr-server (login, push, pull, sync)
   help      Show help information
 
 Examples:
   rust-passmgr init
   rust-passmgr add --file vault.json example.com user123
+  rust-passmgr add github.com alice --url https://github.com --tag work
+  rust-passmgr add github.com alice --force
+  rust-passmgr add bank.com alice --generate=16,no-symbols
+  rust-passmgr generate --length 32 --no-ambiguous
+  rust-passmgr generate --words 6 --capitalize --digits 1
+  rust-passmgr policy set bank '16,symbols=!#$%'
+  rust-passmgr policy assign mybank.com bank
+  rust-passmgr edit mybank.com --generate
+  rust-passmgr rotate db/prod --hook ./alter-user.sh --save-hooks
+  rust-passmgr rotate db/prod
+  rust-passmgr add --type card visa
+  rust-passmgr add --type ssh-key servers/prod --key-file ~/.ssh/id_ed25519
+  rust-passmgr list --tag work
   rust-passmgr list
+  rust-passmgr list work/aws --tree
+  rust-passmgr search github
+  rust-passmgr search login:alice tag:prod
   rust-passmgr get example.com
+  rust-passmgr get --id 0b6f3c1e-2f4a-4c7e-9d1a-5e8b7c6d4f21
+  rust-passmgr edit example.com --prompt-password
+  rust-passmgr edit example.com
+  rust-passmgr field set bank.com PIN --type hidden
+  rust-passmgr field get bank.com PIN | xclip -selection clipboard
+  rust-passmgr export -o backup.json
+  rust-passmgr history example.com
+  rust-passmgr history restore example.com 1
+  rust-passmgr attach github.com recovery-codes.pdf
+  rust-passmgr extract github.com recovery-codes.pdf -o ~/codes.pdf
+  rust-passmgr config attachment-max-size 50M
+  rust-passmgr add ci/deploy-token --type api-token --rotate-every 90d
+  rust-passmgr expiry example.com --at 2027-06-30
+  rust-passmgr due --within 30d || notify-send "Passwords are due"
+  rust-passmgr refs corp/sso
+  rust-passmgr add jira alice -p '{REF:P@T:corp/sso}'
   rust-passmgr remove example.com
+  rust-passmgr mv work/aws archive/
+  rust-passmgr rename github github.com/work
+  rust-passmgr cp github.com/work github.com/personal
+  rust-passmgr remove -r archive/aws
+  rust-passmgr trash restore example.com
+  rust-passmgr trash purge --older-than 30d
+  rust-passmgr init --git
+  rust-passmgr sync --remote origin
+  rust-passmgr merge "vault (conflicted copy).json"
+  rust-passmgr convert --file vault.json vault/
+  rust-passmgr --strict-perms list
+  rust-passmgr verify --restore
 "#,
     disable_help_subcommand = true
 )]
 pub struct Cli {
+    /// Refuse to open vaults readable by other users, owned by someone else,
+    /// or stored in a world-writable directory (default: only warn).
+    #[arg(long, global = true)]
+    pub strict_perms: bool,
     /// Subcommand to execute.
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -49,41 +134,298 @@ pub enum Commands {
         /// Path to the vault file (default: vault.json)
         #[arg(short, long, default_value = "vault.json")]
         file: PathBuf,
+        /// Storage layout: one file, or a directory with one file per entry.
+        #[arg(long, value_enum, default_value = "file")]
+        layout: Layout,
+        /// Track the vault in git and auto-commit every save.
+        #[arg(long)]
+        git: bool,
+        /// Include entry names in auto-commit messages (requires --git).
+        #[arg(long, requires = "git")]
+        commit_names: bool,
     },
     /// Adds a new entry to the vault.
-    Add {
-        /// Path to the vault file (default: vault.json)
+    Add(AddArgs),
+    /// Lists all keys currently stored in the vault.
+    List {
         #[arg(short, long, default_value = "vault.json")]
         file: PathBuf,
-        /// Unique key name for the entry.
-        key: String,
-        /// Login or username for the entry.
-        login: String,
-        /// Optional password; will be prompted if omitted.
-        #[arg(short, long)]
-        password: Option<String>,
-        /// Optional notes or description.
-        #[arg(short, long)]
-        notes: Option<String>,
+        /// Only list this folder (e.g. `work/aws`) and its subfolders.
+        folder: Option<String>,
+        /// Only list entries with this tag (repeatable; all must match).
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+        /// Show folders as a tree.
+        #[arg(long)]
+        tree: bool,
     },
-    /// Lists all keys currently stored in the vault.
-    List {
+    /// Finds entries by fuzzy-matching their keys, logins, URLs and tags.
+    Search {
         #[arg(short, long, default_value = "vault.json")]
         file: PathBuf,
+        /// Words to look for; `key:`, `login:`, `url:`, `tag:` or `notes:`
+        /// limits a word to that value (e.g. `login:alice tag:prod`).
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Also look in the notes.
+        #[arg(long)]
+        notes: bool,
+    },
+    /// Generates a random password or passphrase.
+    Generate(GenerateArgs),
+    /// Lists, defines and assigns password generation policies.
+    Policy {
+        #[command(subcommand)]
+        action: PolicyAction,
     },
     /// Displays a specific entry by key.
     Get {
         #[arg(short, long, default_value = "vault.json")]
         file: PathBuf,
         /// The key name of the entry to display.
-        key: String,
+        #[arg(required_unless_present = "id", conflicts_with = "id")]
+        key: Option<String>,
+        /// Look the entry up by its stable id instead (shown by `get`).
+        #[arg(long)]
+        id: Option<Uuid>,
+        /// Reveal hidden custom fields, card numbers and private keys.
+        #[arg(long)]
+        show: bool,
     },
-    /// Removes an entry from the vault by key.
+    /// Changes an existing entry (interactively without options).
+    Edit(EditArgs),
+    /// Generates a new password for an entry and runs its hooks to change it.
+    ///
+    /// The hooks get the old and the new password on stdin, one per line.
+    /// The new password is only kept if the rotate hook exits with 0.
+    Rotate(RotateArgs),
+    /// Moves an entry to the trash by key.
     Remove {
         #[arg(short, long, default_value = "vault.json")]
         file: PathBuf,
         /// The key name of the entry to delete.
         key: String,
+        /// Remove the folder `key` with all entries and subfolders in it.
+        #[arg(short, long)]
+        recursive: bool,
+        /// Delete for good instead of moving to the trash.
+        #[arg(long)]
+        permanent: bool,
+    },
+    /// Lists, restores or purges removed entries.
+    Trash {
+        #[command(subcommand)]
+        action: TrashAction,
+    },
+    /// Renames an entry, or moves an entry or folder into another folder.
+    Mv {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Entry key or folder to move.
+        from: String,
+        /// New key, or a folder to move into (existing, or ending in `/`).
+        to: String,
+        /// Move entries in the way to the trash instead of refusing.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Gives an entry or folder a new key.
+    Rename {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Entry key or folder to rename.
+        old: String,
+        /// The new key (never a folder to move into).
+        new: String,
+        /// Move entries in the way to the trash instead of refusing.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Copies an entry or folder, with its history and attachments.
+    Cp {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Entry key or folder to copy.
+        from: String,
+        /// Key of the copy, or a folder to copy into (existing, or ending in `/`).
+        to: String,
+        /// Move entries in the way to the trash instead of refusing.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Sets, prints or removes an entry's custom fields.
+    Field {
+        #[command(subcommand)]
+        action: FieldAction,
+    },
+    /// Lists an entry's earlier passwords, or restores one of them.
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    History {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        #[arg(required = true)]
+        key: Option<String>,
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+    },
+    /// Attaches a file to an entry, stored encrypted with the vault.
+    Attach {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// The file to attach.
+        path: PathBuf,
+        /// Name to store it under (default: the file's name).
+        #[arg(short, long)]
+        name: Option<String>,
+        /// MIME type (default: guessed from the name).
+        #[arg(long)]
+        mime: Option<String>,
+        /// Replace an attachment with the same name.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Lists an entry's attachments.
+    Attachments {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+    },
+    /// Writes an attachment out to a file.
+    Extract {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Name of the attachment.
+        name: String,
+        /// File to write, or `-` for standard output (default: the attachment's name).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Removes an attachment from an entry.
+    Detach {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Name of the attachment.
+        name: String,
+    },
+    /// Shows or changes the vault settings (such as attachment size limits).
+    Config {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Setting to show or change (default: list all).
+        name: Option<String>,
+        /// New value for the setting.
+        value: Option<String>,
+    },
+    /// Shows or sets when an entry expires and how often its password must be rotated.
+    Expiry {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Expiry: a date (YYYY-MM-DD) or days from now (e.g. 90d).
+        #[arg(long, value_name = "WHEN", conflicts_with = "never")]
+        at: Option<String>,
+        /// Remove the expiry.
+        #[arg(long)]
+        never: bool,
+        /// Rotate the password after this many days since its last change (e.g. 90d, 12w).
+        #[arg(long, value_name = "DAYS", conflicts_with = "no_rotation")]
+        rotate_every: Option<String>,
+        /// Remove the rotation interval.
+        #[arg(long)]
+        no_rotation: bool,
+    },
+    /// Lists expired entries and passwords due for rotation.
+    ///
+    /// Exits with 0 if nothing is due, 2 if entries are due within the
+    /// window and 3 if any is already expired or overdue.
+    Due {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Also list entries due within this many days (e.g. 14d, 4w).
+        #[arg(short, long, value_name = "DAYS", default_value = "14d")]
+        within: String,
+    },
+    /// Shows an entry's references to other entries and the entries that reference it.
+    Refs {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+    },
+    /// Writes entries as unencrypted JSON.
+    Export {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Entries or folders to export (default: all).
+        keys: Vec<String>,
+        /// File to write (default: standard output).
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Shows the git history of the vault file.
+    Log {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Maximum number of commits to show.
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+    /// Pulls, merges and pushes the vault via git.
+    Sync {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Name or URL of the git remote to sync with.
+        #[arg(short, long, default_value = "origin")]
+        remote: String,
+        /// How to resolve entries changed on both sides.
+        #[arg(short, long, value_enum, default_value = "interactive")]
+        strategy: Strategy,
+    },
+    /// Restores the vault file from a git revision.
+    Checkout {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Revision to restore (commit hash, tag, `HEAD~2`, ...).
+        rev: String,
+    },
+    /// Merges a diverged copy of the vault into it, entry by entry.
+    Merge {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The other copy (e.g. a sync tool's "conflicted copy").
+        other: PathBuf,
+        /// How to resolve entries changed on both sides.
+        #[arg(short, long, value_enum, default_value = "interactive")]
+        strategy: Strategy,
+    },
+    /// Copies the vault into the other storage layout (file <-> directory).
+    Convert {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Where to write the converted vault (e.g. a directory such as `vault/`).
+        target: PathBuf,
+    },
+    /// Checks the vault for damage and points at the newest intact backup.
+    Verify {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Replace a damaged vault with its newest intact backup.
+        #[arg(long)]
+        restore: bool,
+    },
+    /// Syncs the vault with a self-hosted passmgr-server.
+    Remote {
+        #[command(subcommand)]
+        action: RemoteAction,
     },
     /// Displays help for the entire program or a specific subcommand.
     Help {
@@ -94,21 +436,413 @@ pub enum Commands {
     Update,
 }
 
+/// Actions of the `remote` subcommand.
+#[derive(Subcommand)]
+pub enum RemoteAction {
+    /// Links the vault to an account on a passmgr-server.
+    Login {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Base URL of the server (e.g. https://passmgr.example.com).
+        url: String,
+        /// Access token; will be prompted if omitted.
+        #[arg(short, long)]
+        token: Option<String>,
+    },
+    /// Uploads the local vault to the server.
+    Push {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+    },
+    /// Replaces the local vault with the server copy.
+    Pull {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+    },
+    /// Pushes, pulls or merges so both copies match.
+    Sync {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// How to resolve entries changed on both sides.
+        #[arg(short, long, value_enum, default_value = "interactive")]
+        strategy: Strategy,
+    },
+}
+
+/// Arguments of the `add` subcommand.
+#[derive(Args)]
+pub struct AddArgs {
+    /// Path to the vault file (default: vault.json)
+    #[arg(short, long, default_value = "vault.json")]
+    pub file: PathBuf,
+    /// Type of entry; its details are prompted for.
+    #[arg(long = "type", value_enum, default_value = "login")]
+    pub kind: EntryType,
+    /// Unique key name for the entry.
+    pub key: String,
+    /// Login or username (prompted for if a login entry has none; the key
+    /// id of an API token, the identity on WPA-Enterprise networks).
+    pub login: Option<String>,
+    /// Optional password; will be prompted if omitted.
+    #[arg(short, long)]
+    pub password: Option<String>,
+    /// Optional notes or description.
+    #[arg(short, long)]
+    pub notes: Option<String>,
+    /// Site the account is used on (repeatable).
+    #[arg(short, long = "url")]
+    pub urls: Vec<String>,
+    /// Tag to label the entry with (repeatable).
+    #[arg(short, long = "tag")]
+    pub tags: Vec<String>,
+    /// SSH key entries: the private key file (its `.pub` file is read too).
+    #[arg(long, value_name = "PATH")]
+    pub key_file: Option<PathBuf>,
+    /// When the entry expires: a date (YYYY-MM-DD) or days from now (e.g. 90d).
+    #[arg(long, value_name = "WHEN")]
+    pub expires: Option<String>,
+    /// Rotate the password after this many days (e.g. 90d, 12w).
+    #[arg(long, value_name = "DAYS")]
+    pub rotate_every: Option<String>,
+    /// Replace an existing entry with the same key (otherwise the
+    /// differences are shown and the command exits with status 4).
+    #[arg(long)]
+    pub force: bool,
+    /// Generate the password instead of asking for it, optionally following
+    /// a policy such as `--generate=16,no-symbols` (see `generate`).
+    #[arg(
+        long,
+        value_name = "POLICY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        conflicts_with = "password"
+    )]
+    pub generate: Option<String>,
+}
+
+/// Arguments of the `generate` subcommand.
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Vault holding user-defined policies (only read for `--policy`).
+    #[arg(short, long, default_value = "vault.json")]
+    pub file: PathBuf,
+    /// Follow a named policy (see `policy list`) or policy spec.
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = [
+            "length", "words", "no_lower", "no_upper", "no_digits", "no_symbols", "min_lower",
+            "min_upper", "min_digits", "min_symbols", "symbols", "no_ambiguous",
+        ]
+    )]
+    pub policy: Option<String>,
+    /// Number of characters (default: 20).
+    #[arg(short, long)]
+    pub length: Option<u32>,
+    /// Generate a passphrase of this many words instead (diceware).
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        conflicts_with_all = [
+            "length", "no_lower", "no_upper", "no_digits", "no_symbols", "min_lower",
+            "min_upper", "min_digits", "min_symbols", "symbols", "no_ambiguous",
+        ]
+    )]
+    pub words: Option<u32>,
+    /// A wordlist to draw words from as well (repeatable): one word per
+    /// line, or the EFF format of dice rolls and word.
+    #[arg(long = "wordlist", value_name = "PATH", requires = "words")]
+    pub wordlists: Vec<PathBuf>,
+    /// Put this between the words.
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+    /// Start every word with a capital letter.
+    #[arg(long, requires = "words")]
+    pub capitalize: bool,
+    /// Add this many random digits, each to a random word.
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "words")]
+    pub digits: u32,
+    /// Leave out lowercase letters.
+    #[arg(long, conflicts_with = "min_lower")]
+    pub no_lower: bool,
+    /// Leave out uppercase letters.
+    #[arg(long, conflicts_with = "min_upper")]
+    pub no_upper: bool,
+    /// Leave out digits.
+    #[arg(long, conflicts_with = "min_digits")]
+    pub no_digits: bool,
+    /// Leave out symbols.
+    #[arg(long, conflicts_with_all = ["min_symbols", "symbols"])]
+    pub no_symbols: bool,
+    /// At least this many lowercase letters (default: 1).
+    #[arg(long, value_name = "N")]
+    pub min_lower: Option<u32>,
+    /// At least this many uppercase letters (default: 1).
+    #[arg(long, value_name = "N")]
+    pub min_upper: Option<u32>,
+    /// At least this many digits (default: 1).
+    #[arg(long, value_name = "N")]
+    pub min_digits: Option<u32>,
+    /// At least this many symbols (default: 1).
+    #[arg(long, value_name = "N")]
+    pub min_symbols: Option<u32>,
+    /// The symbols to use, e.g. `'!#$%'` (default: !@#$%^&*()-_=+[]{}<>?/~;:.).
+    #[arg(long, value_name = "SET")]
+    pub symbols: Option<String>,
+    /// Leave out look-alike characters (0 O o 1 l I | ` ' ").
+    #[arg(long)]
+    pub no_ambiguous: bool,
+}
+
+/// Arguments of the `edit` subcommand.
+#[derive(Args)]
+pub struct EditArgs {
+    /// Path to the vault file (default: vault.json)
+    #[arg(short, long, default_value = "vault.json")]
+    pub file: PathBuf,
+    /// Key of the entry to change.
+    pub key: String,
+    /// New login or username.
+    #[arg(long)]
+    pub login: Option<String>,
+    /// New password (the old one is kept in the history).
+    #[arg(short, long, conflicts_with_all = ["prompt_password", "generate"])]
+    pub password: Option<String>,
+    /// Ask for the new password without echoing it.
+    #[arg(long, conflicts_with = "generate")]
+    pub prompt_password: bool,
+    /// Generate a new password following the entry's policy.
+    #[arg(short, long)]
+    pub generate: bool,
+    /// New notes, replacing the old ones.
+    #[arg(short, long, conflicts_with = "clear_notes")]
+    pub notes: Option<String>,
+    /// Remove the notes.
+    #[arg(long)]
+    pub clear_notes: bool,
+}
+
+/// Arguments of the `rotate` subcommand.
+#[derive(Args)]
+pub struct RotateArgs {
+    /// Path to the vault file (default: vault.json)
+    #[arg(short, long, default_value = "vault.json")]
+    pub file: PathBuf,
+    /// Key of the entry to rotate.
+    pub key: String,
+    /// Shell command that changes the password (e.g. runs `ALTER USER`),
+    /// instead of the stored one.
+    #[arg(long, value_name = "CMD")]
+    pub hook: Option<String>,
+    /// Shell command to run once the new password is saved, instead of the
+    /// stored one.
+    #[arg(long, value_name = "CMD")]
+    pub post_hook: Option<String>,
+    /// Store exactly the given hooks with the entry for later rotations
+    /// (without any, removes the stored ones).
+    #[arg(long)]
+    pub save_hooks: bool,
+    /// Do not run any hook.
+    #[arg(long, conflicts_with_all = ["hook", "post_hook", "save_hooks"])]
+    pub no_hooks: bool,
+}
+
+/// Actions of the `field` subcommand.
+#[derive(Subcommand)]
+pub enum FieldAction {
+    /// Adds a custom field to an entry or changes it.
+    Set {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Name of the field (e.g. `PIN`).
+        name: String,
+        /// The value; will be prompted if omitted.
+        value: Option<String>,
+        /// Type of the field (default: the current type, or text).
+        #[arg(short = 't', long = "type", value_enum)]
+        kind: Option<FieldKind>,
+    },
+    /// Prints only the value of a field (TOTP fields: the current code).
+    Get {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Name of the field.
+        name: String,
+        /// Print the stored value of a TOTP field instead of the code.
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Removes a field from an entry.
+    Remove {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Name of the field.
+        name: String,
+    },
+}
+
+/// Actions of the `policy` subcommand.
+#[derive(Subcommand)]
+pub enum PolicyAction {
+    /// Lists the built-in policies and those defined in the vault.
+    List {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+    },
+    /// Defines or replaces a policy in the vault.
+    Set {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Name of the policy (e.g. `bank`).
+        name: String,
+        /// A policy spec such as `16,no-symbols` or `length=16,symbols=!#$%`,
+        /// or the name of a policy to copy.
+        spec: String,
+    },
+    /// Removes a policy from the vault.
+    Remove {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Name of the policy.
+        name: String,
+    },
+    /// Makes new passwords of an entry follow a policy.
+    Assign {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Key of the entry.
+        key: String,
+        /// Policy name or spec.
+        #[arg(required_unless_present = "clear")]
+        policy: Option<String>,
+        /// Go back to the default policy.
+        #[arg(long, conflicts_with = "policy")]
+        clear: bool,
+    },
+}
+
+/// Actions of the `trash` subcommand.
+#[derive(Subcommand)]
+pub enum TrashAction {
+    /// Lists the removed entries, most recent first.
+    List {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+    },
+    /// Puts a removed entry (or folder of entries) back.
+    Restore {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Key of the removed entry, or a folder.
+        key: String,
+        /// Restore a single entry under this key instead.
+        #[arg(long = "as", value_name = "KEY")]
+        to: Option<String>,
+    },
+    /// Deletes removed entries for good.
+    Purge {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// Only purge entries removed more than this long ago (e.g. 30d).
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<String>,
+    },
+}
+
+/// Actions of the `history` subcommand.
+#[derive(Subcommand)]
+pub enum HistoryAction {
+    /// Makes an earlier password the current one again.
+    Restore {
+        #[arg(short, long, default_value = "vault.json")]
+        file: PathBuf,
+        /// The key name of the entry.
+        key: String,
+        /// Number of the password, as listed by `history` (1 = most recent).
+        n: usize,
+    },
+}
+
 /// Entry point for the CLI.
 ///
 /// Parses arguments, matches the selected subcommand,
 /// and invokes the corresponding handler from [`crate::commands`].
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
+    perms::set_strict(cli.strict_perms);
 
     match cli.command {
-        Some(Commands::Init { file }) => handle_init(file)?,
-        Some(Commands::Add { file, key, login, password, notes }) => {
-            handle_add(file, key, login, password, notes)?
+        Some(Commands::Init { file, layout, git, commit_names }) => {
+            handle_init(file, layout, git, commit_names)?
+        }
+        Some(Commands::Add(args)) => handle_add(args)?,
+        Some(Commands::List { file, folder, tags, tree }) => handle_list(file, folder, tags, tree)?,
+        Some(Commands::Search { file, query, notes }) => handle_search(file, query, notes)?,
+        Some(Commands::Get { file, key, id, show }) => handle_get(file, key, id, show)?,
+        Some(Commands::Generate(args)) => handle_generate(args)?,
+        Some(Commands::Policy { action }) => match action {
+            PolicyAction::List { file } => handle_policy_list(file)?,
+            PolicyAction::Set { file, name, spec } => handle_policy_set(file, name, spec)?,
+            PolicyAction::Remove { file, name } => handle_policy_remove(file, name)?,
+            PolicyAction::Assign { file, key, policy, clear: _ } => handle_policy_assign(file, key, policy)?,
+        },
+        Some(Commands::Edit(args)) => handle_edit(args)?,
+        Some(Commands::Rotate(args)) => handle_rotate(args)?,
+        Some(Commands::Remove { file, key, recursive, permanent }) => {
+            handle_remove(file, key, recursive, permanent)?
+        }
+        Some(Commands::Trash { action }) => match action {
+            TrashAction::List { file } => handle_trash_list(file)?,
+            TrashAction::Restore { file, key, to } => handle_trash_restore(file, key, to)?,
+            TrashAction::Purge { file, older_than } => handle_trash_purge(file, older_than)?,
+        },
+        Some(Commands::Mv { file, from, to, force }) => handle_mv(file, from, to, force)?,
+        Some(Commands::Rename { file, old, new, force }) => handle_rename(file, old, new, force)?,
+        Some(Commands::Cp { file, from, to, force }) => handle_cp(file, from, to, force)?,
+        Some(Commands::Field { action }) => match action {
+            FieldAction::Set { file, key, name, value, kind } => {
+                handle_field_set(file, key, name, value, kind)?
+            }
+            FieldAction::Get { file, key, name, raw } => handle_field_get(file, key, name, raw)?,
+            FieldAction::Remove { file, key, name } => handle_field_remove(file, key, name)?,
+        },
+        Some(Commands::History { file, key, action }) => match action {
+            Some(HistoryAction::Restore { file, key, n }) => handle_history_restore(file, key, n)?,
+            None => handle_history(file, key.expect("clap requires a key without a subcommand"))?,
+        },
+        Some(Commands::Attach { file, key, path, name, mime, force }) => {
+            handle_attach(file, key, path, name, mime, force)?
+        }
+        Some(Commands::Attachments { file, key }) => handle_attachments(file, key)?,
+        Some(Commands::Extract { file, key, name, output }) => handle_extract(file, key, name, output)?,
+        Some(Commands::Detach { file, key, name }) => handle_detach(file, key, name)?,
+        Some(Commands::Config { file, name, value }) => handle_config(file, name, value)?,
+        Some(Commands::Expiry { file, key, at, never, rotate_every, no_rotation }) => {
+            handle_expiry(file, key, at, never, rotate_every, no_rotation)?
         }
-        Some(Commands::List { file }) => handle_list(file)?,
-        Some(Commands::Get { file, key }) => handle_get(file, key)?,
-        Some(Commands::Remove { file, key }) => handle_remove(file, key)?,
+        Some(Commands::Due { file, within }) => handle_due(file, within)?,
+        Some(Commands::Refs { file, key }) => handle_refs(file, key)?,
+        Some(Commands::Export { file, keys, output }) => handle_export(file, keys, output)?,
+        Some(Commands::Log { file, limit }) => handle_log(file, limit)?,
+        Some(Commands::Sync { file, remote, strategy }) => handle_sync(file, remote, strategy)?,
+        Some(Commands::Checkout { file, rev }) => handle_checkout(file, rev)?,
+        Some(Commands::Merge { file, other, strategy }) => handle_merge(file, other, strategy)?,
+        Some(Commands::Convert { file, target }) => handle_convert(file, target)?,
+        Some(Commands::Verify { file, restore }) => handle_verify(file, restore)?,
+        Some(Commands::Remote { action }) => match action {
+            RemoteAction::Login { file, url, token } => handle_remote_login(file, url, token)?,
+            RemoteAction::Push { file } => handle_remote_push(file)?,
+            RemoteAction::Pull { file } => handle_remote_pull(file)?,
+            RemoteAction::Sync { file, strategy } => handle_remote_sync(file, strategy)?,
+        },
         Some(Commands::Help { command }) => show_help(command)?,
         Some(Commands::Update) => handle_update()?,
         None => {
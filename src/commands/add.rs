@@ -1,49 +1,201 @@
-use std::path::PathBuf;
-use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
-use rpassword;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
 use crate::{
-    crypto::{decrypt_vault, encrypt_vault},
-    model::{Entry, FileFormat},
-    storage::{load_fileformat, save_fileformat},
+    cli::AddArgs,
+    generate::{lookup, strength},
+    model::{parse_days, Card, Entry, EntryKind, EntryType, Identity, SshKey, Wifi, WifiSecurity},
+    refs,
+    sshkey::PrivateKey,
+    storage::open_vault,
+    vcs::Change,
 };
+use super::{format_date, parse_expiry, prompt_line, prompt_new_password, prompt_optional, EXIT_CONFLICT};
 
 /// Handles the `add` subcommand.
 ///
 /// Decrypts the existing vault, prompts the user (if necessary)
 /// for password and notes, inserts a new entry, and then re-encrypts and saves
 /// the updated vault to disk.
-pub fn handle_add(
-    file: PathBuf,
-    key: String,
-    login: String,
-    password: Option<String>,
-    notes: Option<String>,
-) -> Result<()> {
-    // Check if the vault file exists before proceeding
-    if !file.exists() {
-        println!("❌ File {:?} not found. Please run 'init' first.", file);
-        return Ok(());
+///
+/// `urls` and `tags` are stored with the entry; creation and modification
+/// times are set to now.
+///
+/// # Entry types
+/// With `--type`, the details of that type are prompted for and checked
+/// before anything is saved: card numbers must pass the Luhn check and
+/// expiry dates must be valid months, SSH keys are read from `--key-file`
+/// (plus its `.pub` file) and parsed, and Wi-Fi passphrases must suit the
+/// network's security type.
+///
+/// The login, password and notes may reference other entries' values
+/// (`{REF:P@I:<id>}`, see [`crate::refs`]).
+///
+/// `expires` and `rotate_every` set the entry's expiry and rotation
+/// interval (see `due`).
+///
+/// Passwords typed in at the prompt are asked for twice. With `generate`,
+/// a random password is used instead and not shown. It follows the policy
+/// named by `generate` (a policy name or spec, see [`crate::generate`]),
+/// which is then stored with the entry, or the existing entry's policy, or
+/// else the default policy.
+///
+/// # Existing keys
+/// If the key already exists, nothing is saved unless `force` is set;
+/// instead the differences the values given on the command line would make
+/// are shown, without prompting for anything, and the process exits with
+/// [`EXIT_CONFLICT`]. With `force`, the entry is replaced but keeps its id,
+/// creation time, password history, custom fields, attachments, expiry,
+/// rotation interval and rotate hooks, as well as its URLs and tags unless
+/// `urls` or `tags` are given, and the old password is added to the history.
+pub fn handle_add(args: AddArgs) -> Result<()> {
+    let AddArgs { file, kind, key, login, password, notes, urls, tags, key_file, expires, rotate_every, force, generate } = args;
+    let expires_at = expires.as_deref().map(parse_expiry).transpose()?;
+    let rotate_every = rotate_every.as_deref().map(parse_days).transpose()?;
+
+    if generate.is_some() && !matches!(kind, EntryType::Login | EntryType::ApiToken | EntryType::Wifi) {
+        return Err(anyhow!("--generate only works for logins, API tokens and Wi-Fi networks"));
     }
 
-    // Load the existing file format (contains salt and encrypted blob)
-    let ff = load_fileformat(&file)?.expect("Error reading file");
-    let salt = general_purpose::STANDARD.decode(&ff.salt)?;
-    let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+    // Load the vault (file, directory or remote), which must already exist
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
 
     // Ask user for the master password (hidden input)
     let master = rpassword::prompt_password("Master password: ")?;
-    let mut vault = decrypt_vault(&blob, &master, &salt)?;
+    let mut open = locked.unlock(&master)?;
+
+    // A generated password takes the place of one given or typed in; an
+    // existing entry's policy always applies
+    let assigned = open.vault.entries.get(&key).and_then(|e| e.policy.clone());
+    let named = match generate.as_deref() {
+        Some("") | None => None,
+        Some(name) => Some(lookup(&open.vault, name)?),
+    };
+    let policy = match (generate.is_some(), &assigned, &named) {
+        (false, _, _) => None,
+        (true, Some(assigned), Some(named)) if assigned != named => {
+            return Err(anyhow!(
+                "'{key}' follows the policy {assigned}; change it with 'policy assign' first"
+            ));
+        }
+        (true, _, _) => Some(assigned.clone().or(named.clone()).unwrap_or_default()),
+    };
+    let password = match &policy {
+        Some(policy) => Some(policy.generate()?),
+        None => password,
+    };
+
+    // Without --force, show what the given values would change before
+    // asking for anything else
+    if let (Some(old), false) = (open.vault.entries.get(&key), force) {
+        println!("⚠️  Entry '{}' already exists; it is only replaced with --force.", key);
+        let mut given = old.clone();
+        if let Some(login) = &login {
+            given.login = login.clone();
+        }
+        if let Some(password) = &password {
+            given.set_password(password.clone());
+        }
+        if let Some(notes) = &notes {
+            given.notes = Some(notes.clone());
+        }
+        if !urls.is_empty() {
+            given.urls = urls.clone();
+        }
+        if !tags.is_empty() {
+            given.tags = tags.iter().cloned().collect();
+        }
+        given.expires_at = expires_at.or(given.expires_at);
+        given.rotate_every = rotate_every.or(given.rotate_every);
+        given.policy = assigned.or(named);
 
-    // Determine the password for the new entry
-    let pass = match password {
-        Some(p) => p,
-        None => rpassword::prompt_password("Password for new entry: ")?,
+        let mut changes = diff(old, &given);
+        let old_kind = EntryType::of(&old.kind);
+        if old_kind != kind {
+            let name = |t: EntryType| t.to_possible_value().map_or(String::new(), |v| v.get_name().to_string());
+            changes.insert(0, format!("type: {} → {}", name(old_kind), name(kind)));
+        }
+        let given_any = login.is_some()
+            || password.is_some()
+            || notes.is_some()
+            || !urls.is_empty()
+            || !tags.is_empty()
+            || expires_at.is_some()
+            || rotate_every.is_some();
+        if changes.is_empty() && given_any {
+            println!("Entry '{}' already has these values; nothing was changed.", key);
+            return Ok(());
+        }
+        if !changes.is_empty() {
+            println!("Replacing it would change:");
+            for line in changes {
+                println!("  {}", line);
+            }
+        }
+        println!("❌ Nothing was saved. Use --force to replace the entry.");
+        std::process::exit(EXIT_CONFLICT);
+    }
+
+    // Collect the type-specific details, the login and the password
+    let (kind, login, pass) = match kind {
+        EntryType::Login => {
+            let login = match login {
+                Some(l) => l,
+                None => prompt_line("Login: ")?,
+            };
+            let pass = match password {
+                Some(p) => p,
+                None => prompt_new_password("Password for new entry: ", false)?,
+            };
+            (EntryKind::Login, login, pass)
+        }
+        EntryType::Note => (EntryKind::SecureNote, String::new(), no_password(password, "secure notes")?),
+        EntryType::Card => (EntryKind::Card(prompt_card()?), String::new(), no_password(password, "cards")?),
+        EntryType::Identity => {
+            let kind = EntryKind::Identity(prompt_identity()?);
+            (kind, String::new(), no_password(password, "identities")?)
+        }
+        EntryType::SshKey => {
+            let (ssh, encrypted) = read_ssh_key(key_file.as_deref())?;
+            let pass = match password {
+                Some(p) => p,
+                None if encrypted => prompt_new_password("Key passphrase: ", false)?,
+                None => String::new(),
+            };
+            (EntryKind::SshKey(ssh), login.unwrap_or_default(), pass)
+        }
+        EntryType::ApiToken => {
+            let pass = match password {
+                Some(p) => p,
+                None => prompt_new_password("Token: ", false)?,
+            };
+            (EntryKind::ApiToken, login.unwrap_or_default(), pass)
+        }
+        EntryType::Wifi => {
+            let wifi = prompt_wifi()?;
+            let pass = match password {
+                Some(p) => p,
+                None if wifi.security == WifiSecurity::Open => String::new(),
+                None => prompt_new_password("Wi-Fi password: ", false)?,
+            };
+            (EntryKind::Wifi(wifi), login.unwrap_or_default(), pass)
+        }
     };
+    kind.validate(&pass)?;
+    if let EntryKind::Card(card) = &kind {
+        if card.is_expired(chrono::Local::now().date_naive()) {
+            println!("⚠️  This card expired in {}.", card.expiry);
+        }
+    }
 
     // Determine the notes for the new entry
     let notes = match notes {
         Some(n) => Some(n),
+        None if matches!(kind, EntryKind::SecureNote) => prompt_note()?,
         None => {
             println!("Add a note? (press Enter to skip):");
             let mut input = String::new();
@@ -57,28 +209,216 @@ pub fn handle_add(
         }
     };
 
-    // Insert the new entry into the vault’s entries map
-    vault.entries.insert(
-        key.clone(),
-        Entry {
-            login,
-            password: pass,
-            notes,
-        },
-    );
-
-    // Re-encrypt the vault and prepare a new FileFormat
-    let new_blob = encrypt_vault(&vault, &master, &salt)?;
-    let new_ff = FileFormat {
-        version: ff.version,
-        salt: ff.salt,
-        blob: general_purpose::STANDARD.encode(&new_blob),
+    // Build the new entry, carrying over what an existing one keeps
+    let mut entry = match open.vault.entries.get(&key) {
+        Some(old) => replace_entry(old, login, pass, notes),
+        None => Entry::new(login, pass, notes),
     };
+    entry.kind = kind;
+    if !urls.is_empty() {
+        entry.urls = urls;
+    }
+    if !tags.is_empty() {
+        entry.tags = tags.into_iter().collect();
+    }
+    entry.expires_at = expires_at.or(entry.expires_at);
+    entry.rotate_every = rotate_every.or(entry.rotate_every);
+    entry.policy = assigned.or(named);
+    open.vault.entries.insert(key.clone(), entry);
 
-    // Save the updated file format back to disk
-    save_fileformat(&file, &new_ff)?;
+    // Refuse references to other entries that lead nowhere or in a cycle
+    refs::resolve_entry(&open.vault, &key)?;
+
+    // Re-encrypt and save the updated vault
+    open.save(&Change::entry("add", &key))?;
 
     // Notify user
     println!("✅ Entry added: {}", key);
+    if let Some(policy) = policy {
+        let bits = policy.entropy_bits();
+        println!("🎲 Generated a {}-character password (~{:.0} bits, {}); 'get' shows it.", policy.length, bits, strength(bits));
+    }
     Ok(())
 }
+
+/// Builds the replacement of the existing entry `old`, carrying over what
+/// `add --force` keeps: the id, creation time, password history, URLs,
+/// tags, custom fields, attachments, expiry, rotation interval and rotate
+/// hooks. The old password goes to the history.
+fn replace_entry(old: &Entry, login: String, password: String, notes: Option<String>) -> Entry {
+    let mut entry = Entry::new(login, old.password.clone(), notes);
+    entry.id = old.id;
+    entry.created_at = old.created_at;
+    entry.history = old.history.clone();
+    entry.urls = old.urls.clone();
+    entry.tags = old.tags.clone();
+    entry.fields = old.fields.clone();
+    entry.attachments = old.attachments.clone();
+    entry.expires_at = old.expires_at;
+    entry.rotate_every = old.rotate_every;
+    entry.hooks = old.hooks.clone();
+    entry.set_password(password);
+    entry
+}
+
+/// Describes the differences between an existing entry and its replacement,
+/// one line per value. Passwords and type-specific secrets are not shown.
+fn diff(old: &Entry, new: &Entry) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut value = |name: &str, old: String, new: String| {
+        if old != new {
+            lines.push(format!("{name}: {old} → {new}"));
+        }
+    };
+    let or_none = |s: Option<&str>| s.map_or_else(|| "(none)".to_string(), |s| format!("{s:?}"));
+    let list = |items: Vec<&String>| {
+        if items.is_empty() {
+            "(none)".to_string()
+        } else {
+            items.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+        }
+    };
+
+    value("type", old.kind.name().to_string(), new.kind.name().to_string());
+    value("login", format!("{:?}", old.login), format!("{:?}", new.login));
+    value("notes", or_none(old.notes.as_deref()), or_none(new.notes.as_deref()));
+    value("URLs", list(old.urls.iter().collect()), list(new.urls.iter().collect()));
+    value("tags", list(old.tags.iter().collect()), list(new.tags.iter().collect()));
+    value(
+        "expires",
+        old.expires_at.map_or("never".to_string(), |t| format_date(&t)),
+        new.expires_at.map_or("never".to_string(), |t| format_date(&t)),
+    );
+    value(
+        "rotate every",
+        old.rotate_every.map_or("never".to_string(), |d| format!("{d} days")),
+        new.rotate_every.map_or("never".to_string(), |d| format!("{d} days")),
+    );
+    value(
+        "policy",
+        old.policy.as_ref().map_or("default".to_string(), |p| p.to_string()),
+        new.policy.as_ref().map_or("default".to_string(), |p| p.to_string()),
+    );
+    if old.password != new.password {
+        lines.push("password: changed (the old one goes to the history)".to_string());
+    }
+    if old.kind.name() == new.kind.name() && old.kind != new.kind {
+        lines.push(format!("{} details: changed", new.kind.name()));
+    }
+    lines
+}
+
+/// Rejects `--password` for entry types that have no password.
+fn no_password(password: Option<String>, kind: &str) -> Result<String> {
+    match password {
+        Some(_) => Err(anyhow!("{kind} have no password; use 'field set' for PINs and similar")),
+        None => Ok(String::new()),
+    }
+}
+
+/// Asks for the details of a payment card.
+fn prompt_card() -> Result<Card> {
+    let holder = prompt_optional("Cardholder (optional): ")?;
+    let number = Card::normalize_number(&rpassword::prompt_password("Card number: ")?);
+    let expiry = Card::normalize_expiry(&prompt_line("Expiry (MM/YY): ")?)?;
+    let cvv = rpassword::prompt_password("Security code (press Enter to skip): ")?;
+    Ok(Card {
+        holder,
+        number,
+        expiry,
+        cvv: (!cvv.is_empty()).then_some(cvv),
+    })
+}
+
+/// Asks for personal details; every one of them is optional.
+fn prompt_identity() -> Result<Identity> {
+    println!("Press Enter to skip any of these:");
+    Ok(Identity {
+        full_name: prompt_optional("Full name: ")?,
+        email: prompt_optional("Email: ")?,
+        phone: prompt_optional("Phone: ")?,
+        address: prompt_optional("Address: ")?,
+        birth_date: prompt_optional("Date of birth (YYYY-MM-DD): ")?,
+    })
+}
+
+/// Asks for the name and security type of a Wi-Fi network.
+fn prompt_wifi() -> Result<Wifi> {
+    let ssid = prompt_line("SSID: ")?;
+    let security = match prompt_optional("Security (open, wep, wpa, wpa2, wpa3, enterprise) [wpa2]: ")? {
+        Some(s) => WifiSecurity::from_str(s.trim(), true).map_err(|_| anyhow!("unknown security type {s:?}"))?,
+        None => WifiSecurity::default(),
+    };
+    let hidden = prompt_line("Hidden network? [y/N]: ")?.trim().eq_ignore_ascii_case("y");
+    Ok(Wifi { ssid, security, hidden })
+}
+
+/// Reads a multi-line secure note, ended by an empty line.
+fn prompt_note() -> Result<Option<String>> {
+    println!("Enter the note (finish with an empty line):");
+    let mut lines = Vec::new();
+    loop {
+        let line = prompt_line("")?;
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    Ok((!lines.is_empty()).then(|| lines.join("\n")))
+}
+
+/// Reads an SSH private key file and its `.pub` file, if there is one.
+///
+/// Returns the key and whether it is protected by a passphrase. Without a
+/// public key file, the public key is taken from the private key when its
+/// format records it.
+fn read_ssh_key(path: Option<&Path>) -> Result<(SshKey, bool)> {
+    let path = match path {
+        Some(p) => p.to_path_buf(),
+        None => PathBuf::from(prompt_line("Private key file: ")?),
+    };
+    let private_key = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read key file: {}", path.display()))?;
+    let parsed = PrivateKey::parse(&private_key)?;
+
+    let mut pub_path = path.into_os_string();
+    pub_path.push(".pub");
+    let public_key = match fs::read_to_string(&pub_path) {
+        Ok(line) => Some(line.trim().to_string()),
+        Err(_) => parsed.public.as_ref().map(|p| p.to_string()),
+    };
+
+    Ok((SshKey { private_key, public_key }, parsed.encrypted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Field, FieldKind};
+
+    /// Replacing an entry keeps its fields and history; only real changes are listed.
+    #[test]
+    fn force_replace_keeps_carried_over_parts() {
+        let mut old = Entry::new("alice".into(), "old".into(), None);
+        old.fields.push(Field { name: "PIN".into(), value: "1234".into(), kind: FieldKind::Hidden });
+        old.rotate_every = Some(90);
+        old.urls = vec!["https://example.com".into()];
+        old.tags.insert("work".into());
+
+        let new = replace_entry(&old, "alice".into(), "new".into(), Some("note".into()));
+        assert_eq!(new.id, old.id);
+        assert_eq!(new.fields, old.fields);
+        assert_eq!(new.rotate_every, Some(90));
+        assert_eq!((&new.urls, &new.tags), (&old.urls, &old.tags));
+        assert_eq!(new.password, "new");
+        assert_eq!(new.history[0].password, "old");
+        assert_eq!(
+            diff(&old, &new),
+            ["notes: (none) → \"note\"", "password: changed (the old one goes to the history)"]
+        );
+
+        let same = replace_entry(&old, "alice".into(), "old".into(), None);
+        assert!(diff(&old, &same).is_empty());
+        assert!(same.history.is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::{
+    model::{format_size, Attachment, AttachmentData},
+    perms,
+    storage::{open_vault, OpenVault},
+    vcs::Change,
+};
+use super::format_time;
+
+/// Handles the `attach` subcommand.
+///
+/// Adds the file at `path` to the entry `key`, under `name` (default: the
+/// file's name) and with `mime` as its type (default: guessed from the name).
+///
+/// # Behavior
+/// - Files up to the vault's `attachment-inline-limit` are stored inside
+///   the vault; larger ones are encrypted into the sidecar blob store
+///   (see [`crate::blobs`]), which remote vaults and vaults synced with
+///   `passmgr-server` do not have.
+/// - Files above `attachment-max-size` are refused (both limits are set
+///   with `config`).
+/// - An attachment with the same name is only replaced with `force`.
+/// - The entry's modification time is updated.
+///
+/// # Errors
+/// Returns an error if the file cannot be read or is too large, or if
+/// decryption or saving fails.
+pub fn handle_attach(
+    file: PathBuf,
+    key: String,
+    path: PathBuf,
+    name: Option<String>,
+    mime: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let name = match name {
+        Some(n) => n,
+        None => path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow!("{:?} has no file name; pass one with --name", path))?,
+    };
+    let size = fs::metadata(&path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?
+        .len();
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+    let settings = open.vault.settings.clone();
+    if size > settings.attachment_max_size {
+        return Err(anyhow!(
+            "{} is {}, more than the attachment-max-size of {} (change it with 'config attachment-max-size <size>')",
+            path.display(),
+            format_size(size),
+            format_size(settings.attachment_max_size)
+        ));
+    }
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    let old = entry.attachment(&name).cloned();
+    if old.is_some() && !force {
+        println!("⚠️  {} already has an attachment called '{}'. Use --force to replace it.", key, name);
+        return Ok(());
+    }
+
+    let contents = fs::read(&path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    let attachment = store_attachment(&mut open, &key, name, mime, &contents)?;
+
+    let place = match attachment.data {
+        AttachmentData::Inline(_) => "in the vault",
+        AttachmentData::Blob(_) => "in the blob store",
+    };
+    println!("📎 Attached {} ({}) to {}, stored {}.", attachment.name, format_size(attachment.size), key, place);
+    Ok(())
+}
+
+/// Handles the `attachments` subcommand.
+///
+/// Lists the attachments of an entry with their type, size, hash and the
+/// time they were added.
+///
+/// # Errors
+/// Returns an error if reading or decryption fails.
+pub fn handle_attachments(file: PathBuf, key: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+
+    println!("📎 Attachments of {}:", key);
+    if entry.attachments.is_empty() {
+        println!("(none)");
+    }
+    let width = entry.attachments.iter().map(|a| a.name.chars().count()).max().unwrap_or(0);
+    for a in &entry.attachments {
+        let added = a.added_at.map(|t| format!("  added {}", format_time(&t))).unwrap_or_default();
+        let place = match a.data {
+            AttachmentData::Inline(_) => "",
+            AttachmentData::Blob(_) => "  (blob store)",
+        };
+        println!(
+            "  {:<width$}  {:>10}  {}  sha256:{}{}{}",
+            a.name,
+            format_size(a.size),
+            a.mime,
+            a.sha256,
+            added,
+            place
+        );
+    }
+    Ok(())
+}
+
+/// Handles the `extract` subcommand.
+///
+/// Writes the attachment `name` of the entry `key` to `output`, or to a
+/// file called `name` in the current directory. `-` writes to standard
+/// output.
+///
+/// # Behavior
+/// - Output files are created readable by the owner only and never
+///   overwritten, even if one appears while the password is typed.
+/// - The contents are checked against the recorded size and SHA-256
+///   before anything is written.
+///
+/// # Errors
+/// Returns an error if the attachment's blob is missing or damaged, or if
+/// decryption or writing fails.
+pub fn handle_extract(file: PathBuf, key: String, name: String, output: Option<PathBuf>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    let output = output.unwrap_or_else(|| PathBuf::from(Path::new(&name).file_name().unwrap_or_default()));
+    let to_stdout = output == Path::new("-");
+    if !to_stdout && output.exists() {
+        println!("⚠️  {:?} already exists. Not overwriting.", output);
+        return Ok(());
+    }
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    let Some(attachment) = entry.attachment(&name) else {
+        println!("⚠️  {} has no attachment called '{}'.", key, name);
+        return Ok(());
+    };
+
+    let contents = attachment_contents(&open, attachment)?;
+
+    if to_stdout {
+        std::io::stdout().write_all(&contents)?;
+    } else {
+        perms::write_new_file(&output, &contents)?;
+        println!("📤 Extracted {} ({}) to {:?}.", name, format_size(attachment.size), output);
+    }
+    Ok(())
+}
+
+/// Handles the `detach` subcommand.
+///
+/// Removes the attachment `name` from the entry `key`, deleting its blob
+/// from the sidecar blob store if it has one.
+///
+/// # Errors
+/// Returns an error if decryption or saving fails.
+pub fn handle_detach(file: PathBuf, key: String, name: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    if entry.attachment(&name).is_none() {
+        println!("⚠️  {} has no attachment called '{}'.", key, name);
+        return Ok(());
+    }
+    remove_attachment(&mut open, &key, &name)?;
+
+    println!("🗑️  Removed attachment {} from {}.", name, key);
+    Ok(())
+}
+
+/// Stores `contents` as the attachment `name` of the entry `key`, inside
+/// the vault or in the blob store depending on its size, and saves the
+/// vault. An attachment with the same name is replaced.
+///
+/// A blob written for the attachment is removed again if saving fails; the
+/// blob of the attachment it replaces is removed once saving succeeded.
+fn store_attachment(
+    open: &mut OpenVault,
+    key: &str,
+    name: String,
+    mime: Option<String>,
+    contents: &[u8],
+) -> Result<Attachment> {
+    let entry = open.vault.entries.get(key).ok_or_else(|| anyhow!("there is no entry '{key}'"))?;
+    let old = entry.attachment(&name).cloned();
+
+    let data = if contents.len() as u64 <= open.vault.settings.attachment_inline_limit {
+        AttachmentData::Inline(general_purpose::STANDARD.encode(contents))
+    } else {
+        let id = Uuid::new_v4();
+        open.write_blob(&id, contents)?;
+        AttachmentData::Blob(id)
+    };
+    let attachment = Attachment {
+        mime: mime.unwrap_or_else(|| Attachment::guess_mime(&name).to_string()),
+        size: contents.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(contents)),
+        added_at: Some(Utc::now()),
+        name,
+        data,
+    };
+
+    let entry = open.vault.entries.get_mut(key).expect("entry was found above");
+    match entry.attachments.iter_mut().find(|a| a.name == attachment.name) {
+        Some(slot) => *slot = attachment.clone(),
+        None => entry.attachments.push(attachment.clone()),
+    }
+    entry.touch();
+
+    if let Err(e) = open.save(&Change::entry("attach file", key)) {
+        if let AttachmentData::Blob(id) = &attachment.data {
+            let _ = open.remove_blob(id);
+        }
+        return Err(e);
+    }
+    if let Some(AttachmentData::Blob(id)) = old.map(|a| a.data) {
+        open.remove_blob(&id)?;
+    }
+    Ok(attachment)
+}
+
+/// Returns the contents of `attachment`, checked against its recorded size
+/// and SHA-256.
+fn attachment_contents(open: &OpenVault, attachment: &Attachment) -> Result<Vec<u8>> {
+    let name = &attachment.name;
+    let contents = match &attachment.data {
+        AttachmentData::Inline(data) => general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| anyhow!("attachment '{name}' is damaged ({e})"))?,
+        AttachmentData::Blob(id) => open.read_blob(id)?,
+    };
+    if contents.len() as u64 != attachment.size
+        || format!("{:x}", Sha256::digest(&contents)) != attachment.sha256
+    {
+        return Err(anyhow!("attachment '{name}' does not match its recorded size and SHA-256"));
+    }
+    Ok(contents)
+}
+
+/// Removes the attachment `name` from the entry `key` and saves the vault,
+/// then deletes the attachment's blob if it has one.
+fn remove_attachment(open: &mut OpenVault, key: &str, name: &str) -> Result<Attachment> {
+    let entry = open.vault.entries.get_mut(key).ok_or_else(|| anyhow!("there is no entry '{key}'"))?;
+    let pos = entry
+        .attachments
+        .iter()
+        .position(|a| a.name == name)
+        .ok_or_else(|| anyhow!("{key} has no attachment called '{name}'"))?;
+    let removed = entry.attachments.remove(pos);
+    entry.touch();
+    open.save(&Change::entry("detach file", key))?;
+    if let AttachmentData::Blob(id) = &removed.data {
+        open.remove_blob(id)?;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::{
+        blobs,
+        model::Entry,
+        remote::server::ServerLink,
+        storage::{Layout, OpenVault},
+    };
+
+    /// Creates a vault with one entry and an inline limit of 8 bytes.
+    fn scratch_vault(dir: &TempDir) -> OpenVault {
+        let mut open = OpenVault::create(&dir.path().join("vault.json"), Layout::File, "master").unwrap();
+        open.vault.settings.attachment_inline_limit = 8;
+        open.vault.entries.insert("gh".into(), Entry::new("alice".into(), "pw".into(), None));
+        open.save(&Change::vault("init vault")).unwrap();
+        open
+    }
+
+    /// Returns the blob files of the vault in `dir`.
+    fn blob_files(dir: &TempDir) -> Vec<PathBuf> {
+        let store = blobs::dir(&dir.path().join("vault.json"), Layout::File);
+        fs::read_dir(store).map_or(Vec::new(), |d| d.map(|f| f.unwrap().path()).collect())
+    }
+
+    /// Small files stay in the vault, large ones go to the blob store, and
+    /// both read back intact.
+    #[test]
+    fn stores_attachments_by_size() {
+        let dir = TempDir::new().unwrap();
+        let mut open = scratch_vault(&dir);
+
+        let small = store_attachment(&mut open, "gh", "codes.txt".into(), None, b"1234").unwrap();
+        assert!(matches!(small.data, AttachmentData::Inline(_)));
+        assert_eq!(small.mime, "text/plain");
+        let large = store_attachment(&mut open, "gh", "key.pem".into(), None, b"0123456789").unwrap();
+        assert!(matches!(large.data, AttachmentData::Blob(_)));
+        assert_eq!(blob_files(&dir).len(), 1);
+
+        let open = open_vault(&dir.path().join("vault.json")).unwrap().unwrap().unlock("master").unwrap();
+        let entry = &open.vault.entries["gh"];
+        assert_eq!(attachment_contents(&open, entry.attachment("codes.txt").unwrap()).unwrap(), b"1234");
+        assert_eq!(attachment_contents(&open, entry.attachment("key.pem").unwrap()).unwrap(), b"0123456789");
+    }
+
+    /// Replacing or detaching a blob attachment deletes its old blob.
+    #[test]
+    fn removes_replaced_and_detached_blobs() {
+        let dir = TempDir::new().unwrap();
+        let mut open = scratch_vault(&dir);
+        store_attachment(&mut open, "gh", "key.pem".into(), None, b"0123456789").unwrap();
+        let first = blob_files(&dir);
+        store_attachment(&mut open, "gh", "key.pem".into(), None, b"9876543210").unwrap();
+        let second = blob_files(&dir);
+        assert_eq!(second.len(), 1);
+        assert_ne!(first, second);
+
+        let removed = remove_attachment(&mut open, "gh", "key.pem").unwrap();
+        assert_eq!(removed.size, 10);
+        assert!(blob_files(&dir).is_empty());
+        assert!(open.vault.entries["gh"].attachments.is_empty());
+        assert!(remove_attachment(&mut open, "gh", "key.pem").is_err());
+    }
+
+    /// Contents that do not match the recorded hash, or a blob that is not
+    /// on this machine, are reported instead of extracted.
+    #[test]
+    fn checks_attachment_contents() {
+        let dir = TempDir::new().unwrap();
+        let mut open = scratch_vault(&dir);
+        let mut small = store_attachment(&mut open, "gh", "a.txt".into(), None, b"1234").unwrap();
+        small.sha256 = "0".repeat(64);
+        assert!(attachment_contents(&open, &small).unwrap_err().to_string().contains("SHA-256"));
+
+        let large = store_attachment(&mut open, "gh", "b.bin".into(), None, b"0123456789").unwrap();
+        for file in blob_files(&dir) {
+            fs::remove_file(file).unwrap();
+        }
+        let err = attachment_contents(&open, &large).unwrap_err();
+        assert!(format!("{err:#}").contains("not on this machine"), "{err:#}");
+    }
+
+    /// Vaults synced with passmgr-server only take attachments that fit
+    /// into the vault, since the server never sees the blob store.
+    #[test]
+    fn refuses_blobs_for_server_synced_vaults() {
+        let dir = TempDir::new().unwrap();
+        let mut open = scratch_vault(&dir);
+        let link = ServerLink { url: "http://localhost".into(), token: "t".into(), revision: 0, base: None };
+        link.save(&dir.path().join("vault.json")).unwrap();
+
+        assert!(store_attachment(&mut open, "gh", "a.txt".into(), None, b"1234").is_ok());
+        let err = store_attachment(&mut open, "gh", "b.bin".into(), None, b"0123456789").unwrap_err();
+        assert!(err.to_string().contains("passmgr-server"), "{err}");
+        assert!(blob_files(&dir).is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use crate::{
+    model::FileFormat,
+    storage::save_fileformat,
+    vcs::{self, Change},
+};
+
+/// Handles the `checkout` subcommand.
+///
+/// Restores the vault file to the version recorded at a git revision.
+///
+/// # Behavior
+/// - If the vault is not tracked in git, prints a hint and exits gracefully.
+/// - Reads the vault as it was at `rev` and writes it back as the current vault,
+///   so the restore itself becomes a new commit and no history is lost.
+///
+/// # Errors
+/// Returns an error if the revision does not exist or does not contain a valid vault.
+pub fn handle_checkout(file: PathBuf, rev: String) -> Result<()> {
+    if !vcs::is_tracked_repo(&file) {
+        println!("⚠️  {:?} is not tracked in git. Run 'init --git' or 'git init' first.", file);
+        return Ok(());
+    }
+
+    let contents = vcs::show(&file, &rev)?;
+    let ff: FileFormat = serde_json::from_str(&contents)
+        .with_context(|| format!("Revision {rev} does not contain a valid vault"))?;
+
+    let message = format!("checkout {rev}");
+    save_fileformat(&file, &ff, &Change::vault(&message))?;
+    println!("⏪ Vault restored to {}.", rev);
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::{model::Settings, storage::open_vault, vcs::Change};
+
+/// Handles the `config` subcommand.
+///
+/// Shows or changes the vault-wide settings, which are stored encrypted
+/// in the vault and so travel with it.
+///
+/// # Behavior
+/// - Without `name`, lists every setting with its value.
+/// - With `name` only, prints that setting.
+/// - With `name` and `value`, changes it; sizes accept `K`, `M` and `G`
+///   suffixes (e.g. `64K`, `10M`).
+///
+/// # Errors
+/// Returns an error for unknown settings or invalid values, or if
+/// decryption or saving fails.
+pub fn handle_config(file: PathBuf, name: Option<String>, value: Option<String>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    match (name, value) {
+        (None, _) => {
+            for name in Settings::NAMES {
+                println!("{} = {}", name, open.vault.settings.get(name)?);
+            }
+        }
+        (Some(name), None) => println!("{}", open.vault.settings.get(&name)?),
+        (Some(name), Some(value)) => {
+            open.vault.settings.set(&name, &value)?;
+            open.save(&Change::vault("change settings"))?;
+            println!("⚙️  {} = {}", name, open.vault.settings.get(&name)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::{
+    storage::{open_vault, vault_exists},
+    vcs::Change,
+};
+
+/// Handles the `convert` subcommand.
+///
+/// Copies the vault into the other storage layout: a single `vault.json`
+/// file becomes a directory with one encrypted file per entry, and a
+/// directory vault becomes a single file.
+///
+/// # Behavior
+/// - Refuses to overwrite an existing vault at `target`.
+/// - Prompts for the master password; the converted vault keeps the same
+///   salt, so the password does not change.
+/// - The original vault is left in place.
+///
+/// # Errors
+/// Returns an error if decryption fails, an entry key cannot be used as a
+/// file name, or writing the target fails.
+pub fn handle_convert(file: PathBuf, target: PathBuf) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    if vault_exists(&target)? {
+        println!("⚠️  {:?} already exists. Not overwriting.", target);
+        return Ok(());
+    }
+
+    let from = locked.layout();
+    let to = from.other();
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let vault = locked.unlock(&master)?;
+    vault.save_as(&target, to, &Change::vault("convert vault"))?;
+
+    println!("✅ Converted {:?} ({}) to {:?} ({}).", file, from, target, to);
+    println!("The original vault was left in place; remove it once you have checked the copy.");
+    Ok(())
+}
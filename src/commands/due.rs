@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use crate::{model::parse_days, storage::open_vault};
+use super::describe_due;
+
+/// Exit status of `due` when entries are due within the window but none is overdue.
+const EXIT_DUE_SOON: i32 = 2;
+
+/// Exit status of `due` when an entry has expired or is overdue for rotation.
+const EXIT_OVERDUE: i32 = 3;
+
+/// Handles the `due` subcommand.
+///
+/// Lists the entries that have expired or whose password is due for
+/// rotation, plus those that will be within `within` (e.g. `14d`), soonest
+/// first.
+///
+/// # Exit status
+/// - `0` — nothing is due within the window.
+/// - `1` — an error occurred.
+/// - [`EXIT_DUE_SOON`] (`2`) — some entries are due within the window.
+/// - [`EXIT_OVERDUE`] (`3`) — some entries are already expired or overdue.
+///
+/// # Errors
+/// Returns an error if `within` is not a number of days, or if reading or
+/// decryption fails.
+pub fn handle_due(file: PathBuf, within: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    let window = parse_days(&within)?;
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let now = Utc::now();
+    let horizon = now + Duration::days(window.into());
+    let mut due = Vec::new();
+    for (key, entry) in &open.vault.entries {
+        let expiry = entry.expires_at.map(|t| (t, true));
+        let rotation = entry.rotation_due_at().map(|t| (t, false));
+        if let Some((t, expires)) = expiry.into_iter().chain(rotation).min() {
+            if t <= horizon {
+                due.push((t, expires, key));
+            }
+        }
+    }
+    due.sort();
+
+    if due.is_empty() {
+        println!("✅ Nothing is due in the next {} days.", window);
+        return Ok(());
+    }
+    let width = due.iter().map(|(_, _, k)| k.chars().count()).max().unwrap_or(0);
+    let overdue = due.iter().filter(|(t, _, _)| *t <= now).count();
+    println!("⏰ {} due, {} of them overdue:", due.len(), overdue);
+    for (t, expires, key) in &due {
+        let (mark, what) = match (*t <= now, expires) {
+            (true, true) => ("❌", "expired"),
+            (true, false) => ("❌", "rotation due"),
+            (false, true) => ("⚠️ ", "expires"),
+            (false, false) => ("⚠️ ", "rotation due"),
+        };
+        println!("  {} {:<width$}  {} {}", mark, key, what, describe_due(t));
+    }
+
+    std::process::exit(if overdue > 0 { EXIT_OVERDUE } else { EXIT_DUE_SOON });
+}
@@ -0,0 +1,280 @@
+use anyhow::{anyhow, Result};
+use crate::{
+    cli::EditArgs,
+    model::Entry,
+    refs,
+    storage::open_vault,
+    vcs::Change,
+};
+use super::{prompt_line, prompt_new_password};
+
+/// Handles the `edit` subcommand.
+///
+/// Changes single values of an existing entry and leaves the rest alone.
+///
+/// # Behavior
+/// - `--login`, `--password` and `--notes` set those values,
+///   `--prompt-password` asks for the new password (twice) without echo,
+///   `--generate` generates one following the entry's policy (see
+///   [`crate::model::Entry::policy`]), and `--clear-notes` removes the notes.
+/// - Without any of these, walks through the login, password, notes, URLs,
+///   tags and custom fields, showing each current value; pressing Enter
+///   keeps it.
+/// - A new password goes through the password history, and any change
+///   updates the modification time. If nothing changed, nothing is saved.
+/// - Type-specific details (card numbers, SSH keys, ...) are not edited
+///   here; re-add the entry with `add --type` to change them.
+///
+/// # Errors
+/// Returns an error if a value is invalid (e.g. a custom field that does not
+/// fit its type or a broken reference), the passwords do not match, or
+/// decryption or saving fails.
+pub fn handle_edit(args: EditArgs) -> Result<()> {
+    let EditArgs { file, key, login, password, prompt_password, generate, notes, clear_notes } = args;
+
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    let mut edited = entry.clone();
+
+    let interactive =
+        login.is_none() && password.is_none() && !prompt_password && !generate && notes.is_none() && !clear_notes;
+    if interactive {
+        edit_interactively(&mut edited)?;
+    } else {
+        let password = match password {
+            None if prompt_password => Some(prompt_new_password("New password: ", false)?),
+            password => password,
+        };
+        apply_flags(&mut edited, login, password, generate, notes, clear_notes)?;
+    }
+
+    let changes = changed_parts(entry, &edited);
+    if changes.is_empty() {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+    edited.touch();
+    open.vault.entries.insert(key.clone(), edited);
+    refs::resolve_entry(&open.vault, &key)?;
+    open.save(&Change::entry("edit", &key))?;
+
+    println!("✏️  Updated {}: {}.", key, changes.join(", "));
+    if generate {
+        println!("🎲 Generated a new password; 'get' shows it.");
+    }
+    Ok(())
+}
+
+/// Applies the values given as flags to `entry`.
+///
+/// `password` is the new password, if one was given or typed; `generate`
+/// generates one following the entry's policy instead.
+fn apply_flags(
+    entry: &mut Entry,
+    login: Option<String>,
+    password: Option<String>,
+    generate: bool,
+    notes: Option<String>,
+    clear_notes: bool,
+) -> Result<()> {
+    if generate && !entry.kind.has_generated_password() {
+        return Err(anyhow!("{} entries have no generated password", entry.kind.name()));
+    }
+    if let Some(login) = login {
+        entry.login = login;
+    }
+    let password = match password {
+        None if generate => Some(entry.policy.clone().unwrap_or_default().generate()?),
+        password => password,
+    };
+    if let Some(password) = password {
+        entry.set_password(password);
+    }
+    if let Some(notes) = notes {
+        entry.notes = Some(notes);
+    }
+    if clear_notes {
+        entry.notes = None;
+    }
+    Ok(())
+}
+
+/// Walks through the editable values of `entry`, keeping those left empty.
+fn edit_interactively(entry: &mut Entry) -> Result<()> {
+    println!("Press Enter to keep a value.");
+    if let Some(login) = keep_or(&format!("Login [{}]: ", entry.login))? {
+        entry.login = login;
+    }
+
+    let password = prompt_new_password("New password (Enter to keep): ", true)?;
+    if !password.is_empty() {
+        entry.set_password(password);
+    }
+
+    let current = entry.notes.as_deref().unwrap_or("");
+    match keep_or(&format!("Notes [{}] ('-' to clear): ", current))?.as_deref() {
+        Some("-") => entry.notes = None,
+        Some(notes) => entry.notes = Some(notes.to_string()),
+        None => {}
+    }
+
+    let label = format!("URLs [{}] (comma separated, '-' to clear): ", entry.urls.join(", "));
+    if let Some(urls) = keep_or(&label)? {
+        entry.urls = split_list(&urls);
+    }
+    let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+    let label = format!("Tags [{}] (comma separated, '-' to clear): ", tags.join(", "));
+    if let Some(tags) = keep_or(&label)? {
+        entry.tags = split_list(&tags).into_iter().collect();
+    }
+
+    for field in entry.fields.iter_mut() {
+        let value = if field.kind.is_secret() {
+            let value = rpassword::prompt_password(format!("{} [hidden]: ", field.name))?;
+            (!value.is_empty()).then_some(value)
+        } else {
+            keep_or(&format!("{} [{}]: ", field.name, field.value))?
+        };
+        if let Some(value) = value {
+            if refs::parse(&value)?.is_empty() {
+                field.kind.validate(&value)?;
+            }
+            field.value = value;
+        }
+    }
+    Ok(())
+}
+
+/// Prompts with `label`; an empty answer keeps the current value (`None`).
+fn keep_or(label: &str) -> Result<Option<String>> {
+    let input = prompt_line(label)?;
+    Ok((!input.trim().is_empty()).then(|| input.trim().to_string()))
+}
+
+/// Splits a comma-separated answer; `-` stands for an empty list.
+fn split_list(input: &str) -> Vec<String> {
+    if input == "-" {
+        return Vec::new();
+    }
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Names the parts of an entry that differ between `old` and `new`.
+fn changed_parts(old: &Entry, new: &Entry) -> Vec<&'static str> {
+    let mut parts = Vec::new();
+    if old.login != new.login {
+        parts.push("login");
+    }
+    if old.password != new.password {
+        parts.push("password");
+    }
+    if old.notes != new.notes {
+        parts.push("notes");
+    }
+    if old.urls != new.urls {
+        parts.push("URLs");
+    }
+    if old.tags != new.tags {
+        parts.push("tags");
+    }
+    if old.fields != new.fields {
+        parts.push("custom fields");
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use crate::{
+        cli::{Cli, Commands},
+        model::{EntryKind, Field, FieldKind},
+    };
+
+    /// Parses an `edit` command line.
+    fn parse(args: &[&str]) -> Result<EditArgs, clap::Error> {
+        let cli = Cli::try_parse_from(["rust-passmgr", "edit", "mail"].iter().chain(args))?;
+        match cli.command {
+            Some(Commands::Edit(args)) => Ok(args),
+            _ => unreachable!("parsed an edit command"),
+        }
+    }
+
+    /// Lists are split on commas, with `-` clearing them.
+    #[test]
+    fn splits_lists() {
+        assert_eq!(split_list(" a.com, ,b.com ,"), ["a.com", "b.com"]);
+        assert!(split_list("-").is_empty());
+        assert!(split_list("").is_empty());
+    }
+
+    /// Only the parts that differ are named, in a fixed order.
+    #[test]
+    fn names_changed_parts() {
+        let old = Entry::new("alice".into(), "pw".into(), None);
+        assert!(changed_parts(&old, &old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.tags.insert("work".into());
+        new.notes = Some("note".into());
+        new.fields.push(Field { name: "PIN".into(), value: "1234".into(), kind: FieldKind::Hidden });
+        new.set_password("new".into());
+        assert_eq!(changed_parts(&old, &new), ["password", "notes", "tags", "custom fields"]);
+    }
+
+    /// Flags set single values; the others are left alone.
+    #[test]
+    fn applies_flags() {
+        let old = Entry::new("alice".into(), "pw".into(), Some("note".into()));
+
+        let mut edited = old.clone();
+        apply_flags(&mut edited, Some("bob".into()), Some("new".into()), false, None, false).unwrap();
+        assert_eq!(changed_parts(&old, &edited), ["login", "password"]);
+        assert_eq!(edited.history[0].password, "pw");
+
+        let mut edited = old.clone();
+        apply_flags(&mut edited, None, None, false, None, true).unwrap();
+        assert_eq!(edited.notes, None);
+
+        let mut edited = old.clone();
+        apply_flags(&mut edited, None, None, true, None, false).unwrap();
+        assert_eq!(changed_parts(&old, &edited), ["password"]);
+    }
+
+    /// Only entries with a generated password can get a new one generated.
+    #[test]
+    fn refuses_to_generate_without_a_generated_password() {
+        let mut note = Entry::new(String::new(), String::new(), Some("text".into()));
+        note.kind = EntryKind::SecureNote;
+        let err = apply_flags(&mut note.clone(), None, None, true, None, false).unwrap_err();
+        assert!(err.to_string().contains("no generated password"));
+        apply_flags(&mut note, None, None, false, Some("other".into()), false).unwrap();
+        assert_eq!(note.notes.as_deref(), Some("other"));
+    }
+
+    /// Flags that contradict each other are rejected by the parser.
+    #[test]
+    fn rejects_conflicting_flags() {
+        assert!(parse(&["--notes", "x", "--clear-notes"]).is_err());
+        assert!(parse(&["--password", "x", "--generate"]).is_err());
+        assert!(parse(&["--prompt-password", "--generate"]).is_err());
+        let args = parse(&["--generate", "--clear-notes"]).unwrap();
+        assert!(args.generate && args.clear_notes);
+    }
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::{model::parse_days, storage::open_vault, vcs::Change};
+use super::{describe_due, parse_expiry};
+
+/// Handles the `expiry` subcommand.
+///
+/// Shows or changes when an entry expires and how often its password must
+/// be rotated.
+///
+/// # Behavior
+/// - `at` sets the expiry, as a date (`YYYY-MM-DD`) or a number of days
+///   from now (`90d`); `never` removes it.
+/// - `rotate_every` sets the rotation interval (`90d`, `12w`, ...), counted
+///   from the last password change; `no_rotation` removes it.
+/// - Without any of these, prints the current settings and due dates.
+///
+/// # Errors
+/// Returns an error for invalid dates or intervals, or if decryption or
+/// saving fails.
+pub fn handle_expiry(
+    file: PathBuf,
+    key: String,
+    at: Option<String>,
+    never: bool,
+    rotate_every: Option<String>,
+    no_rotation: bool,
+) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    let expires_at = at.as_deref().map(parse_expiry).transpose()?;
+    let rotate_every = rotate_every.as_deref().map(parse_days).transpose()?;
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+
+    let changing = expires_at.is_some() || never || rotate_every.is_some() || no_rotation;
+    if changing {
+        if expires_at.is_some() || never {
+            entry.expires_at = expires_at;
+        }
+        if rotate_every.is_some() || no_rotation {
+            entry.rotate_every = rotate_every;
+        }
+        entry.touch();
+        open.save(&Change::entry("set expiry", &key))?;
+    }
+
+    let entry = &open.vault.entries[&key];
+    println!("⏳ {}:", key);
+    match &entry.expires_at {
+        Some(t) => println!("Expires: {}", describe_due(t)),
+        None => println!("Expires: never"),
+    }
+    match (entry.rotate_every, entry.rotation_due_at()) {
+        (Some(days), Some(due)) => println!("Rotate every: {} days, next rotation due {}", days, describe_due(&due)),
+        _ => println!("Rotate every: -"),
+    }
+    Ok(())
+}
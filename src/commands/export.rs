@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::Result;
+use serde::Serialize;
+use crate::{model::Entry, perms, refs, storage::open_vault};
+
+/// The exported JSON document.
+#[derive(Serialize)]
+struct Export<'a> {
+    entries: BTreeMap<&'a String, Entry>,
+}
+
+/// Handles the `export` subcommand.
+///
+/// Writes entries as unencrypted JSON, in the same shape as the decrypted
+/// vault (`{"entries": {...}}`), including every custom field with its name,
+/// type and value.
+///
+/// # Behavior
+/// - Exports the entries named in `keys`, or all entries if none are given.
+///   A key naming a folder exports the folder with all its subfolders.
+/// - Writes to `output` (created readable by the owner only, never
+///   overwritten) or to standard output.
+/// - Hidden fields and TOTP secrets are exported in plaintext.
+/// - References to other entries' values are exported resolved.
+///
+/// # Errors
+/// Returns an error if decryption fails, a reference cannot be resolved or
+/// writing the output fails.
+pub fn handle_export(file: PathBuf, keys: Vec<String>, output: Option<PathBuf>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    if let Some(path) = &output {
+        if path.exists() {
+            println!("⚠️  {:?} already exists. Not overwriting.", path);
+            return Ok(());
+        }
+    }
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let mut entries = BTreeMap::new();
+    if keys.is_empty() {
+        entries.extend(open.vault.entries.iter());
+    }
+    for key in &keys {
+        let mut found: Vec<String> = open.vault.folder_keys(key);
+        if open.vault.entries.contains_key(key) {
+            found.push(key.clone());
+        }
+        if found.is_empty() {
+            println!("⚠️  Entry '{}' not found.", key);
+            return Ok(());
+        }
+        entries.extend(found.iter().filter_map(|k| open.vault.entries.get_key_value(k)));
+    }
+
+    let entries = entries
+        .into_keys()
+        .map(|k| Ok((k, refs::resolve_entry(&open.vault, k)?)))
+        .collect::<Result<BTreeMap<&String, Entry>>>()?;
+    let count = entries.len();
+    let json = serde_json::to_string_pretty(&Export { entries })? + "\n";
+    match output {
+        Some(path) => {
+            perms::write_file(&path, json.as_bytes())?;
+            println!("📤 Exported {} entr{} to {:?}.", count, if count == 1 { "y" } else { "ies" }, path);
+            println!("⚠️  The export is not encrypted; delete it once you no longer need it.");
+        }
+        None => print!("{}", json),
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::{
+    model::{Field, FieldKind},
+    refs,
+    storage::open_vault,
+    totp::Totp,
+    vcs::Change,
+};
+use super::prompt_line;
+
+/// Handles the `field set` subcommand.
+///
+/// Adds a custom field to an entry, or changes an existing one in place.
+///
+/// # Behavior
+/// - The type defaults to the field's current type, or `text` for a new field.
+/// - If `value` is omitted it is prompted for; hidden and TOTP values are
+///   read without echo.
+/// - The value is checked against the type (URL, email address, `YYYY-MM-DD`
+///   date, base32 secret or `otpauth://` URI) before anything is saved.
+/// - The value may reference another entry's value (`{REF:P@I:<id>}`, see
+///   [`crate::refs`]); references that lead nowhere or in a cycle are refused.
+/// - New fields are appended, so fields keep the order they were added in.
+///
+/// # Errors
+/// Returns an error if the value is invalid for the type, or if decryption
+/// or saving fails.
+pub fn handle_field_set(
+    file: PathBuf,
+    key: String,
+    name: String,
+    value: Option<String>,
+    kind: Option<FieldKind>,
+) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+
+    let kind = kind
+        .or_else(|| entry.field(&name).map(|f| f.kind))
+        .unwrap_or_default();
+    let value = match value {
+        Some(v) => v,
+        None if kind.is_secret() => rpassword::prompt_password(format!("Value for '{}': ", name))?,
+        None => prompt_line(&format!("Value for '{}': ", name))?,
+    };
+    if refs::parse(&value)?.is_empty() {
+        kind.validate(&value)?;
+    }
+
+    let added = match entry.fields.iter_mut().find(|f| f.name == name) {
+        Some(field) => {
+            field.kind = kind;
+            field.value = value;
+            false
+        }
+        None => {
+            entry.fields.push(Field { name: name.clone(), kind, value });
+            true
+        }
+    };
+    entry.touch();
+    refs::resolve_entry(&open.vault, &key)?;
+    open.save(&Change::entry("set field", &key))?;
+
+    if added {
+        println!("✅ Field '{}' ({}) added to {}.", name, kind, key);
+    } else {
+        println!("✅ Field '{}' ({}) updated in {}.", name, kind, key);
+    }
+    Ok(())
+}
+
+/// Handles the `field get` subcommand.
+///
+/// Prints the value of a single field and nothing else, so it can be piped
+/// into a clipboard tool (`| xclip -selection clipboard`, `| pbcopy`) or a
+/// script. Hidden values are printed in full.
+///
+/// # Behavior
+/// - References to other entries' values are resolved.
+/// - TOTP fields print the current code; with `raw`, the stored secret.
+/// - Records the time of use like `get` does.
+///
+/// # Errors
+/// Returns an error if decryption fails, a reference cannot be resolved or
+/// the stored TOTP secret is invalid.
+pub fn handle_field_get(file: PathBuf, key: String, name: String, raw: bool) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    if !open.vault.entries.contains_key(&key) {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    }
+    let entry = refs::resolve_entry(&open.vault, &key)?;
+    let Some(field) = entry.field(&name) else {
+        println!("⚠️  Entry '{}' has no field '{}'.", key, name);
+        return Ok(());
+    };
+
+    if field.kind == FieldKind::Totp && !raw {
+        println!("{}", Totp::parse(&field.value)?.now().0);
+    } else {
+        println!("{}", field.value);
+    }
+
+    if let Err(e) = open.record_use(&key) {
+        eprintln!("⚠️  Could not record the time of use: {e}");
+    }
+    Ok(())
+}
+
+/// Handles the `field remove` subcommand.
+///
+/// Deletes a custom field from an entry; the other fields keep their order.
+///
+/// # Errors
+/// Returns an error if decryption or saving fails.
+pub fn handle_field_remove(file: PathBuf, key: String, name: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    let before = entry.fields.len();
+    entry.fields.retain(|f| f.name != name);
+    if entry.fields.len() == before {
+        println!("⚠️  Entry '{}' has no field '{}'.", key, name);
+        return Ok(());
+    }
+
+    entry.touch();
+    open.save(&Change::entry("remove field", &key))?;
+    println!("🗑️  Field '{}' removed from {}.", name, key);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use crate::{
+    cli::GenerateArgs,
+    generate::{load_wordlists, lookup, strength, Passphrase, Policy},
+    model::Vault,
+    storage::open_vault,
+};
+
+/// Handles the `generate` subcommand.
+///
+/// Prints a random password drawn from the operating system's secure random
+/// number generator, following the length, character classes, minimum
+/// counts and symbol set given (see [`crate::generate`]).
+///
+/// With `policy`, follows that policy instead: a built-in one, a spec, or
+/// one defined in the vault (which is only opened in that case).
+///
+/// With `words`, prints a diceware passphrase instead, drawn from the EFF
+/// large wordlist and any extra wordlists (see
+/// [`crate::generate::load_wordlists`]).
+///
+/// The password is the only thing written to standard output, so it can be
+/// piped; the entropy estimate goes to standard error.
+///
+/// # Errors
+/// Returns an error if the options leave no way to build a password, e.g.
+/// minimum counts longer than the password, if no wordlist can be read, or
+/// if the policy is unknown.
+pub fn handle_generate(args: GenerateArgs) -> Result<()> {
+    if let Some(words) = args.words {
+        let wordlist = load_wordlists(&args.wordlists)?;
+        let options = Passphrase {
+            words,
+            separator: args.separator.clone(),
+            capitalize: args.capitalize,
+            digits: args.digits,
+        };
+        let passphrase = options.generate(&wordlist)?;
+        let bits = options.entropy_bits(wordlist.len());
+
+        println!("{}", passphrase);
+        eprintln!("🎲 ~{:.0} bits of entropy ({}; {} words to choose from)", bits, strength(bits), wordlist.len());
+        return Ok(());
+    }
+
+    let policy = match &args.policy {
+        Some(name) => match lookup(&Vault::default(), name) {
+            Ok(policy) => policy,
+            Err(e) => {
+                let Some(locked) = open_vault(&args.file)? else {
+                    return Err(e);
+                };
+                let master = rpassword::prompt_password("Master password: ")?;
+                lookup(&locked.unlock(&master)?.vault, name)?
+            }
+        },
+        None => policy_from_args(&args),
+    };
+    let password = policy.generate()?;
+    let bits = policy.entropy_bits();
+
+    println!("{}", password);
+    eprintln!("🎲 ~{:.0} bits of entropy ({})", bits, strength(bits));
+    Ok(())
+}
+
+/// Builds the policy described by the command-line options.
+fn policy_from_args(args: &GenerateArgs) -> Policy {
+    let mut policy = Policy::default();
+    if let Some(length) = args.length {
+        policy.length = length;
+    }
+    for (class, off, min) in [
+        (&mut policy.lowercase, args.no_lower, args.min_lower),
+        (&mut policy.uppercase, args.no_upper, args.min_upper),
+        (&mut policy.digits, args.no_digits, args.min_digits),
+        (&mut policy.symbols, args.no_symbols, args.min_symbols),
+    ] {
+        if off {
+            *class = None;
+        } else if let Some(min) = min {
+            *class = Some(min);
+        }
+    }
+    if let Some(symbols) = &args.symbols {
+        policy.symbol_set = symbols.clone();
+    }
+    policy.exclude_ambiguous = args.no_ambiguous;
+    policy
+}
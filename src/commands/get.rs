@@ -1,11 +1,14 @@
 use std::path::PathBuf;
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
-use rpassword;
+use uuid::Uuid;
 use crate::{
-    crypto::decrypt_vault,
-    storage::load_fileformat,
+    model::{format_size, Entry, EntryKind, Field, FieldKind},
+    refs,
+    sshkey::{PrivateKey, PublicKey},
+    storage::{open_vault, OpenVault},
+    totp::Totp,
 };
+use super::{describe_due, format_time};
 
 /// Handles the `get` subcommand.
 ///
@@ -13,46 +16,221 @@ use crate::{
 ///
 /// # Arguments
 /// * `file` — Path to the vault file.
-/// * `key` — The unique identifier of the entry to retrieve.
+/// * `key` — The key of the entry to retrieve.
+/// * `id` — Look the entry up by its stable id instead of its key.
+/// * `show` — Whether to reveal hidden custom fields, card numbers and private keys.
 ///
 /// # Behavior
 /// - If the vault file does not exist, prints an error message and exits gracefully.
 /// - Prompts the user for the master password.
 /// - Decrypts the vault and looks for the requested key.
-/// - Prints the entry if found, or a warning if it doesn’t exist.
+/// - Prints the entry if found, or a warning if it doesn’t exist, laid out
+///   for its type (login, card, SSH key, ...).
+/// - Custom fields are listed in order; hidden ones are masked unless
+///   `show` is set, and TOTP fields show the current code.
+/// - Attachments are listed by name and size; `extract` writes them out.
+/// - References to other entries' values (`{REF:P@I:...}`, see
+///   [`crate::refs`]) are shown resolved.
+/// - Warns when the entry has expired or its password is overdue for rotation.
+/// - Records the time of use in the local usage file (see [`crate::usage`]);
+///   the vault itself is not rewritten, and a failure is only a warning.
 ///
 /// # Errors
 /// Returns an error if file operations, base64 decoding, or decryption fail.
-pub fn handle_get(file: PathBuf, key: String) -> Result<()> {
-    // Ensure the vault file exists
-    if !file.exists() {
+pub fn handle_get(file: PathBuf, key: Option<String>, id: Option<Uuid>, show: bool) -> Result<()> {
+    // Load the vault (file, directory or remote), which must already exist
+    let Some(locked) = open_vault(&file)? else {
         println!("❌ File {:?} not found. Please run 'init' first.", file);
         return Ok(());
-    }
-
-    // Load and decode the file contents
-    let ff = load_fileformat(&file)?.expect("Error reading file");
-    let salt = general_purpose::STANDARD.decode(&ff.salt)?;
-    let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+    };
 
     // Ask for master password (hidden input)
     let master = rpassword::prompt_password("Master password: ")?;
-    let vault = decrypt_vault(&blob, &master, &salt)?;
+    let open = locked.unlock(&master)?;
+
+    // Look up the requested entry, by id if one was given
+    let key = match (key, id) {
+        (_, Some(id)) => match open.vault.find_id(&id) {
+            Some((key, _)) => key.clone(),
+            None => {
+                println!("⚠️  No entry with id {}.", id);
+                return Ok(());
+            }
+        },
+        (Some(key), None) => key,
+        (None, None) => unreachable!("clap requires a key or an id"),
+    };
+    show_entry(&open, &key, show)
+}
+
+/// Prints the entry `key` of an unlocked vault and records its use.
+///
+/// Shared with `search`, which offers to show its only match.
+pub(super) fn show_entry(open: &OpenVault, key: &str, show: bool) -> Result<()> {
+    let Some(stored) = open.vault.entries.get(key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    // Show references to other entries' values as the values themselves
+    let entry = &refs::resolve_entry(&open.vault, key).unwrap_or_else(|e| {
+        println!("⚠️  {e}; showing the stored values.");
+        stored.clone()
+    });
+
+    println!("🔑 Entry: {}", key);
+    println!("ID: {}", entry.id);
+    print_details(entry, show);
+    for url in &entry.urls {
+        println!("URL: {}", url);
+    }
+    for field in &entry.fields {
+        println!("{}: {}", field.name, display_value(field, show));
+    }
+    if !entry.tags.is_empty() {
+        let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+        println!("Tags: {}", tags.join(", "));
+    }
+    if let Some(notes) = &entry.notes {
+        println!("Notes: {}", notes);
+    }
+    for attachment in &entry.attachments {
+        println!("Attachment: {} ({})", attachment.name, format_size(attachment.size));
+    }
+    if let Some(t) = &entry.created_at {
+        println!("Created: {}", format_time(t));
+    }
+    if let Some(t) = &entry.modified_at {
+        println!("Modified: {}", format_time(t));
+    }
+    if let Some(t) = &open.last_used(stored) {
+        println!("Last used: {}", format_time(t));
+    }
+    if let Some(t) = &entry.expires_at {
+        println!("Expires: {}", describe_due(t));
+    }
+    if let Some(policy) = &entry.policy {
+        println!("Password policy: {}", policy);
+    }
+    if let Some(hook) = &entry.hooks.pre {
+        println!("Rotate hook: {}", hook);
+    }
+    if let Some(hook) = &entry.hooks.post {
+        println!("Post-rotate hook: {}", hook);
+    }
+    if let (Some(days), Some(t)) = (entry.rotate_every, entry.rotation_due_at()) {
+        println!("Rotate every: {} days, next rotation due {}", days, describe_due(&t));
+    }
+    let now = chrono::Utc::now();
+    if entry.expires_at.is_some_and(|t| t <= now) {
+        println!("⚠️  This entry has expired; replace the credential and update it with 'expiry'.");
+    } else if entry.rotation_due_at().is_some_and(|t| t <= now) {
+        println!("⚠️  This password is overdue for rotation.");
+    }
+
+    if let Err(e) = open.record_use(key) {
+        eprintln!("⚠️  Could not record the time of use: {e}");
+    }
 
-    // Look up the requested entry
-    match vault.entries.get(&key) {
-        Some(entry) => {
-            println!("🔑 Entry: {}", key);
+    Ok(())
+}
+
+/// Formats a custom field's value for display.
+fn display_value(field: &Field, show: bool) -> String {
+    match field.kind {
+        FieldKind::Hidden if !show => "•".repeat(8),
+        FieldKind::Totp => match Totp::parse(&field.value) {
+            Ok(totp) => {
+                let (code, remaining) = totp.now();
+                format!("{} (valid for {}s)", code, remaining)
+            }
+            Err(e) => format!("(invalid TOTP secret: {e})"),
+        },
+        _ => field.value.clone(),
+    }
+}
+
+/// Prints the login, password and type-specific details of an entry.
+fn print_details(entry: &Entry, show: bool) {
+    if !entry.kind.is_login() {
+        println!("Type: {}", entry.kind.name());
+    }
+    match &entry.kind {
+        EntryKind::Login => {
             println!("Login: {}", entry.login);
             println!("Password: {}", entry.password);
-            if let Some(notes) = &entry.notes {
-                println!("Notes: {}", notes);
+        }
+        EntryKind::SecureNote => {}
+        EntryKind::ApiToken => {
+            if !entry.login.is_empty() {
+                println!("Key ID: {}", entry.login);
+            }
+            println!("Token: {}", entry.password);
+        }
+        EntryKind::Card(card) => {
+            if let Some(holder) = &card.holder {
+                println!("Cardholder: {}", holder);
+            }
+            if show {
+                println!("Number: {}", card.number);
+            } else {
+                println!("Number: {}", card.masked_number());
+            }
+            let expired = card.is_expired(chrono::Local::now().date_naive());
+            println!("Expires: {}{}", card.expiry, if expired { " (expired)" } else { "" });
+            if let Some(cvv) = &card.cvv {
+                println!("Security code: {}", if show { cvv.clone() } else { "•".repeat(cvv.len()) });
+            }
+        }
+        EntryKind::Identity(identity) => {
+            let details = [
+                ("Name", &identity.full_name),
+                ("Email", &identity.email),
+                ("Phone", &identity.phone),
+                ("Address", &identity.address),
+                ("Date of birth", &identity.birth_date),
+            ];
+            for (label, value) in details {
+                if let Some(value) = value {
+                    println!("{}: {}", label, value);
+                }
+            }
+        }
+        EntryKind::SshKey(key) => {
+            let private = PrivateKey::parse(&key.private_key).ok();
+            let public = key
+                .public_key
+                .as_deref()
+                .and_then(|line| PublicKey::parse(line).ok())
+                .or_else(|| private.as_ref().and_then(|p| p.public.clone()));
+            if let Some(public) = public {
+                println!("Public key: {}", public);
+                println!("Fingerprint: {}", public.fingerprint());
+            }
+            if !entry.login.is_empty() {
+                println!("Login: {}", entry.login);
+            }
+            if !entry.password.is_empty() {
+                println!("Passphrase: {}", entry.password);
+            }
+            if show {
+                println!("Private key:\n{}", key.private_key.trim_end());
+            } else {
+                let encrypted = private.is_some_and(|p| p.encrypted);
+                println!(
+                    "Private key: {} (use --show to print it)",
+                    if encrypted { "passphrase-protected" } else { "unprotected" }
+                );
             }
         }
-        None => {
-            println!("⚠️  Entry '{}' not found.", key);
+        EntryKind::Wifi(wifi) => {
+            println!("SSID: {}{}", wifi.ssid, if wifi.hidden { " (hidden)" } else { "" });
+            println!("Security: {}", wifi.security);
+            if !entry.login.is_empty() {
+                println!("Identity: {}", entry.login);
+            }
+            if !entry.password.is_empty() {
+                println!("Password: {}", entry.password);
+            }
         }
     }
-
-    Ok(())
 }
@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use crate::{storage::open_vault, vcs::Change};
+use super::format_time;
+
+/// Handles the `history` subcommand.
+///
+/// Lists the earlier passwords of an entry, newest first and numbered from
+/// 1, with the time each one was replaced.
+///
+/// # Errors
+/// Returns an error if reading or decryption fails.
+pub fn handle_history(file: PathBuf, key: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+
+    println!("🕘 Password history of {}:", key);
+    if entry.history.is_empty() {
+        println!("(empty)");
+    }
+    for (i, old) in entry.history.iter().enumerate() {
+        let when = old
+            .replaced_at
+            .map(|t| format!("replaced {}", format_time(&t)))
+            .unwrap_or_else(|| "replaced at an unknown time".to_string());
+        println!("{:>3}. {}  ({})", i + 1, old.password, when);
+    }
+    Ok(())
+}
+
+/// Handles the `history restore` subcommand.
+///
+/// Makes the `n`-th earlier password (as numbered by `history`) the current
+/// one again. The password it replaces goes into the history, so a restore
+/// can itself be undone. Restoring a password that is the current one
+/// already changes nothing.
+///
+/// # Errors
+/// Returns an error if `n` is out of range, or if decryption or saving fails.
+pub fn handle_history_restore(file: PathBuf, key: String, n: usize) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    if n == 0 || n > entry.history.len() {
+        return Err(anyhow!(
+            "entry '{}' has {} earlier password(s); pick a number from 'history {}'",
+            key,
+            entry.history.len(),
+            key
+        ));
+    }
+
+    if !entry.restore_password(n - 1) {
+        println!("ℹ️  Password #{} of {} is the current one already; nothing to restore.", n, key);
+        return Ok(());
+    }
+    open.save(&Change::entry("restore password", &key))?;
+
+    println!("⏪ Restored password #{} of {}; the replaced one is now #1 in its history.", n, key);
+    Ok(())
+}
@@ -1,11 +1,9 @@
 use std::path::PathBuf;
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
-use rpassword;
 use crate::{
-    crypto::{encrypt_vault, generate_salt},
-    model::{FileFormat, Vault},
-    storage::save_fileformat,
+    generate::{load_wordlists, strength, Passphrase},
+    storage::{vault_exists, Layout, OpenVault},
+    vcs::{self, Change},
 };
 
 /// Handles the `init` subcommand.
@@ -14,21 +12,42 @@ use crate::{
 ///
 /// # Behavior
 /// - If the file already exists, prints a warning and exits without overwriting.
-/// - Prompts the user twice to confirm the master password.
+/// - Prompts the user twice to confirm the master password. If a wordlist
+///   is available (see [`crate::generate::load_wordlists`]), pressing Enter
+///   at the first prompt suggests a diceware passphrase, which then has to
+///   be typed in to confirm it; otherwise a hint says how to get one.
 /// - Generates a random salt and creates an empty vault.
-/// - Encrypts and saves the vault as a JSON file containing the salt and ciphertext.
+/// - Encrypts and saves the vault as a JSON file containing the salt and ciphertext,
+///   or, with [`Layout::Directory`], as a directory with one file per entry.
+/// - With `git`, tracks the vault in a git repository and enables auto-commit
+///   (entry names go into commit messages only if `commit_names` is set).
 ///
 /// # Errors
 /// Returns an error if encryption or file operations fail.
-pub fn handle_init(file: PathBuf) -> Result<()> {
+pub fn handle_init(file: PathBuf, layout: Layout, git: bool, commit_names: bool) -> Result<()> {
     // Prevent overwriting existing vault file
-    if file.exists() {
+    if vault_exists(&file)? {
         println!("⚠️  File {:?} already exists. Not overwriting.", file);
         return Ok(());
     }
 
-    // Prompt user for master password twice
-    let pass1 = rpassword::prompt_password("Enter master password: ")?;
+    // Prompt user for master password twice, offering a passphrase if a wordlist is available
+    let wordlist = load_wordlists(&[]).ok();
+    if wordlist.is_none() {
+        println!("💡 Could not get the EFF large wordlist; install it to get a suggested passphrase here (see 'generate --words').");
+    }
+    let label = match wordlist {
+        Some(_) => "Enter master password (or press Enter for a suggested passphrase): ",
+        None => "Enter master password: ",
+    };
+    let mut pass1 = rpassword::prompt_password(label)?;
+    if let (true, Some(words)) = (pass1.is_empty(), &wordlist) {
+        let options = Passphrase::default();
+        pass1 = options.generate(words)?;
+        let bits = options.entropy_bits(words.len());
+        println!("💡 Suggested master passphrase: {}", pass1);
+        println!("   ~{:.0} bits ({}). Write it down: the vault cannot be opened without it.", bits, strength(bits));
+    }
     let pass2 = rpassword::prompt_password("Confirm password: ")?;
     if pass1 != pass2 {
         println!("Passwords do not match.");
@@ -36,21 +55,18 @@ pub fn handle_init(file: PathBuf) -> Result<()> {
     }
 
     // Generate salt and create an empty vault
-    let salt = generate_salt();
-    let vault = Vault::default();
-
-    // Encrypt empty vault using master password
-    let blob = encrypt_vault(&vault, &pass1, &salt)?;
+    let mut vault = OpenVault::create(&file, layout, &pass1)?;
 
-    // Encode salt and ciphertext to base64 for storage
-    let ff = FileFormat {
-        version: 1,
-        salt: general_purpose::STANDARD.encode(&salt),
-        blob: general_purpose::STANDARD.encode(&blob),
-    };
+    // Enable git tracking first so the initial save becomes the first commit
+    if git {
+        vcs::enable(&file, commit_names)?;
+    }
 
     // Save to disk
-    save_fileformat(&file, &ff)?;
+    vault.save(&Change::vault("init vault"))?;
     println!("✅ Vault created: {:?}", file);
+    if git {
+        println!("📚 Git auto-commit enabled.");
+    }
     Ok(())
 }
@@ -1,11 +1,7 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
-use rpassword;
-use crate::{
-    crypto::decrypt_vault,
-    storage::load_fileformat,
-};
+use crate::{model::Entry, storage::open_vault};
 
 /// Handles the `list` subcommand.
 ///
@@ -14,36 +10,112 @@ use crate::{
 /// # Behavior
 /// - If the vault file does not exist, prints an error message and exits gracefully.
 /// - Prompts the user for the master password.
-/// - Decrypts the vault and lists all stored entries.
-/// - If there are no entries, prints `(empty)`.
+/// - Decrypts the vault and lists all stored entries with their tags.
+/// - With `folder`, only lists the entries in that folder and its subfolders.
+/// - With `tags`, only lists entries carrying every one of them.
+/// - With `tree`, shows folders as a tree instead of a flat list of keys.
+/// - If there are no (matching) entries, prints `(empty)`.
 ///
 /// # Errors
 /// Returns an error if reading, decoding, or decryption fails.
-pub fn handle_list(file: PathBuf) -> Result<()> {
-    // Ensure the vault file exists
-    if !file.exists() {
+pub fn handle_list(file: PathBuf, folder: Option<String>, tags: Vec<String>, tree: bool) -> Result<()> {
+    // Load the vault (file, directory or remote), which must already exist
+    let Some(locked) = open_vault(&file)? else {
         println!("❌ File {:?} not found. Please run 'init' first.", file);
         return Ok(());
-    }
-
-    // Load and decode the file contents
-    let ff = load_fileformat(&file)?.expect("Error reading file");
-    let salt = general_purpose::STANDARD.decode(&ff.salt)?;
-    let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+    };
 
     // Ask for master password (hidden input)
     let master = rpassword::prompt_password("Master password: ")?;
-    let vault = decrypt_vault(&blob, &master, &salt)?;
+    let open = locked.unlock(&master)?;
+    let vault = &open.vault;
+
+    // Keep the entries in the folder that carry every requested tag
+    let folder = folder.as_deref().unwrap_or("").trim_matches('/');
+    let entries: Vec<(&String, &Entry)> = vault
+        .folder_keys(folder)
+        .iter()
+        .filter_map(|k| vault.entries.get_key_value(k))
+        .filter(|(_, e)| tags.iter().all(|t| e.tags.contains(t)))
+        .collect();
 
     // Display the list of saved entries
-    if vault.entries.is_empty() {
+    if entries.is_empty() {
         println!("(empty)");
+    } else if tree {
+        let mut root = Node::default();
+        for (key, entry) in &entries {
+            let relative = if folder.is_empty() { key.as_str() } else { &key[folder.len() + 1..] };
+            root.insert(relative, entry);
+        }
+        if folder.is_empty() {
+            println!("📋 Saved entries:");
+        } else {
+            println!("📁 {}/", folder);
+        }
+        root.print("");
     } else {
         println!("📋 List of saved entries:");
-        for key in vault.entries.keys() {
-            println!("• {}", key);
+        for (key, entry) in entries {
+            println!("• {}{}", key, tag_suffix(entry));
         }
     }
 
     Ok(())
 }
+
+/// A folder in the tree view: its subfolders and the entries directly in it.
+#[derive(Default)]
+struct Node<'a> {
+    folders: BTreeMap<&'a str, Node<'a>>,
+    entries: BTreeMap<&'a str, &'a Entry>,
+}
+
+impl<'a> Node<'a> {
+    /// Adds the entry at the `/`-separated path `key` below this folder.
+    fn insert(&mut self, key: &'a str, entry: &'a Entry) {
+        match key.split_once('/') {
+            Some((folder, rest)) => self.folders.entry(folder).or_default().insert(rest, entry),
+            None => {
+                self.entries.insert(key, entry);
+            }
+        }
+    }
+
+    /// Prints the folder's contents, folders first, each line starting with `indent`.
+    fn print(&self, indent: &str) {
+        let items: Vec<(&str, Item)> = self
+            .folders
+            .iter()
+            .map(|(name, node)| (*name, Item::Folder(node)))
+            .chain(self.entries.iter().map(|(name, entry)| (*name, Item::Entry(entry))))
+            .collect();
+
+        for (i, (name, item)) in items.iter().enumerate() {
+            let last = i + 1 == items.len();
+            let branch = if last { "└── " } else { "├── " };
+            match item {
+                Item::Folder(node) => {
+                    println!("{}{}📁 {}/", indent, branch, name);
+                    node.print(&format!("{}{}", indent, if last { "    " } else { "│   " }));
+                }
+                Item::Entry(entry) => println!("{}{}{}{}", indent, branch, name, tag_suffix(entry)),
+            }
+        }
+    }
+}
+
+/// A line in the tree view.
+enum Item<'n, 'a> {
+    Folder(&'n Node<'a>),
+    Entry(&'a Entry),
+}
+
+/// Formats an entry's tags as ` [a, b]`, or nothing if it has none.
+fn tag_suffix(entry: &Entry) -> String {
+    if entry.tags.is_empty() {
+        return String::new();
+    }
+    let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+    format!(" [{}]", tags.join(", "))
+}
@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::vcs;
+
+/// Handles the `log` subcommand.
+///
+/// Prints the git history of the vault file, newest first.
+///
+/// # Behavior
+/// - If the vault is not tracked in git, prints a hint and exits gracefully.
+/// - Shows at most `limit` commits when a limit is given.
+///
+/// # Errors
+/// Returns an error if `git log` fails.
+pub fn handle_log(file: PathBuf, limit: Option<usize>) -> Result<()> {
+    if !vcs::is_tracked_repo(&file) {
+        println!("⚠️  {:?} is not tracked in git. Run 'init --git' or 'git init' first.", file);
+        return Ok(());
+    }
+
+    let lines = vcs::log(&file, limit)?;
+    if lines.is_empty() {
+        println!("(no history)");
+    } else {
+        println!("📜 History of {:?}:", file);
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+
+    Ok(())
+}
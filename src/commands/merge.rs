@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use crate::{
+    blobs,
+    crypto::{decrypt_vault_with_key, derive_key, encrypt_vault_with_key, KEY_LEN},
+    merge::{self, Side, Strategy},
+    model::{AttachmentData, Entry, FileFormat, Vault},
+    remote,
+    storage::{list_backups, load_fileformat, load_version, save_over, Layout},
+    vcs::{self, Change},
+};
+use super::format_time;
+
+/// Maximum number of git revisions searched for a common ancestor.
+const MAX_HISTORY: usize = 50;
+
+/// Handles the `merge` subcommand.
+///
+/// Merges a diverged copy of the vault (such as a sync tool's
+/// "conflicted copy") into the vault, entry by entry.
+///
+/// # Behavior
+/// - If either file does not exist, prints an error message and exits gracefully.
+/// - Prompts for the master password and decrypts both copies; if the other
+///   copy uses a different password, asks for that one too.
+/// - Looks for a common ancestor among the vault's backups and git history
+///   and performs a three-way merge of the entries.
+/// - Non-conflicting changes are combined; conflicts are resolved with `strategy`.
+/// - Saves the merged vault back to `file` only if something changed.
+///
+/// # Errors
+/// Returns an error if file operations, decoding, or decryption fail.
+pub fn handle_merge(file: PathBuf, other: PathBuf, strategy: Strategy) -> Result<()> {
+    // Load both copies, which must already exist
+    let (Some(loaded), Some(other_ff)) = (load_version(&file)?, load_fileformat(&other)?) else {
+        println!("❌ File {:?} or {:?} not found.", file, other);
+        return Ok(());
+    };
+    let ff = loaded.ff.clone();
+
+    // Ask for master password (hidden input) and decrypt both copies
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut keys = KeyCache::new(master);
+    let ours = keys.decrypt(&ff)?;
+    let other_salt = general_purpose::STANDARD.decode(&other_ff.salt)?;
+    let (theirs, other_key) = match keys.decrypt(&other_ff) {
+        Ok(vault) => (vault, keys.key(&other_salt)?),
+        Err(_) => {
+            let prompt = format!("Master password for {:?}: ", other);
+            let mut other_keys = KeyCache::new(rpassword::prompt_password(prompt)?);
+            (other_keys.decrypt(&other_ff)?, other_keys.key(&other_salt)?)
+        }
+    };
+
+    // Search backups and git history for the version both copies came from
+    let base = find_ancestor(&file, &ff, &other_ff, &theirs, &mut keys);
+    if base.is_none() {
+        println!("ℹ️  No common ancestor found; merging both copies as a union.");
+    }
+
+    let ours_newer = modified(&file) >= modified(&other);
+    let merged = merge_vaults(base.as_ref(), &ours, &theirs, strategy, ours_newer)?;
+    if merged == ours {
+        println!("✅ Nothing to merge; {:?} already contains every change.", file);
+        return Ok(());
+    }
+
+    // Bring over the blobs of attachments taken from the other copy
+    if !remote::is_remote(&file) && !remote::is_remote(&other) {
+        let key = keys.key(&general_purpose::STANDARD.decode(&ff.salt)?)?;
+        let copied = copy_blobs(&merged, (&other, &other_key), (&file, &key))?;
+        if copied > 0 {
+            println!("📎 {} attachment(s) copied from the other copy.", copied);
+        }
+    }
+
+    // Re-encrypt with the vault's own salt and record the other copy as a parent
+    let mut new_ff = keys.encrypt(&merged, &ff)?;
+    new_ff.parents = std::iter::once(other_ff.id().to_string())
+        .chain(other_ff.parents.iter().cloned())
+        .collect();
+    save_over(&file, &new_ff, Some(&loaded), &Change::vault("merge vault"))?;
+    println!("🔀 Merged {:?} into {:?}.", other, file);
+    Ok(())
+}
+
+/// Copies the blobs of `merged`'s attachments that are missing from the
+/// vault at `to` but present in the copy at `from`, re-encrypting them with
+/// the vault's key. Returns how many were copied.
+fn copy_blobs(
+    merged: &Vault,
+    (from, from_key): (&Path, &[u8; KEY_LEN]),
+    (to, to_key): (&Path, &[u8; KEY_LEN]),
+) -> Result<usize> {
+    let (from_layout, to_layout) = (Layout::of(from), Layout::of(to));
+    let entries = merged.entries.values().chain(merged.trash.iter().map(|t| &t.entry));
+    let mut copied = 0;
+    for attachment in entries.flat_map(|e| &e.attachments) {
+        let AttachmentData::Blob(id) = &attachment.data else { continue };
+        if blobs::exists(to, to_layout, id) || !blobs::exists(from, from_layout, id) {
+            continue;
+        }
+        let contents = blobs::read(from, from_layout, from_key, id)?;
+        blobs::write(to, to_layout, to_key, id, &contents)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Merges two vaults and resolves conflicts according to `strategy`.
+///
+/// Under [`Strategy::Newest`], conflicts go to the entry modified last;
+/// `ours_newer` decides when the entries do not record that time.
+/// Prints a short summary of what was combined.
+pub(super) fn merge_vaults(
+    base: Option<&Vault>,
+    ours: &Vault,
+    theirs: &Vault,
+    strategy: Strategy,
+    ours_newer: bool,
+) -> Result<Vault> {
+    let mut outcome = merge::three_way(base, ours, theirs);
+    if outcome.from_theirs > 0 {
+        println!("📥 {} change(s) taken from the other copy.", outcome.from_theirs);
+    }
+
+    let conflicts = std::mem::take(&mut outcome.conflicts);
+    if !conflicts.is_empty() {
+        println!("⚔️  {} conflict(s) to resolve.", conflicts.len());
+    }
+    let mut renamed = std::mem::take(&mut outcome.renamed);
+
+    for conflict in &conflicts {
+        let side = match strategy {
+            Strategy::Ours => Side::Ours,
+            Strategy::Theirs => Side::Theirs,
+            Strategy::Newest => match conflict.newer_side() {
+                Some(side) => side,
+                None if ours_newer => Side::Ours,
+                None => Side::Theirs,
+            },
+            Strategy::Interactive => ask_side(conflict)?,
+        };
+        outcome.resolve(conflict, side);
+    }
+
+    renamed.append(&mut outcome.renamed);
+    for (key, new_key) in renamed {
+        println!("📛 '{}' is taken by a different entry; the other copy's entry was kept as '{}'.", key, new_key);
+    }
+    Ok(outcome.merged)
+}
+
+/// Shows both versions of a conflicting entry and asks which one to keep.
+fn ask_side(conflict: &merge::Conflict) -> Result<Side> {
+    println!();
+    println!("⚔️  Conflict on '{}':", conflict.key);
+    println!("  [o]urs:   {}", describe(conflict.ours.as_ref(), conflict.theirs.as_ref()));
+    println!("  [t]heirs: {}", describe(conflict.theirs.as_ref(), conflict.ours.as_ref()));
+
+    loop {
+        print!("Keep which version? [o/t]: ");
+        std::io::stdout().flush()?;
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+        match input.trim() {
+            "o" | "ours" => return Ok(Side::Ours),
+            "t" | "theirs" => return Ok(Side::Theirs),
+            _ => println!("Please answer 'o' or 't'."),
+        }
+    }
+}
+
+/// Summarizes an entry for conflict display without revealing its password.
+fn describe(entry: Option<&(String, Entry)>, other: Option<&(String, Entry)>) -> String {
+    match entry {
+        None => "(deleted)".to_string(),
+        Some((key, e)) => {
+            let mut s = format!("login: {}", e.login);
+            if other.is_some_and(|(k, _)| k != key) {
+                s = format!("key: {}, {}", key, s);
+            }
+            if other.is_some_and(|(_, o)| o.password != e.password) {
+                s.push_str(", password differs");
+            }
+            if let Some(notes) = &e.notes {
+                s.push_str(&format!(", notes: {}", notes));
+            }
+            if let Some(t) = &e.modified_at {
+                s.push_str(&format!(", modified: {}", format_time(t)));
+            }
+            s
+        }
+    }
+}
+
+/// Returns the modification time of a file in seconds, or 0 if unknown.
+fn modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Finds the common ancestor of the local vault `ours` and the copy `other`.
+///
+/// If the local vault descends from `other`, that copy is the ancestor.
+/// Otherwise the versions listed in `other.parents` are looked up, newest
+/// first, among the local vault's backups and git history.
+fn find_ancestor(
+    file: &Path,
+    ours: &FileFormat,
+    other: &FileFormat,
+    theirs: &Vault,
+    keys: &mut KeyCache,
+) -> Option<Vault> {
+    if ours.parents.iter().any(|id| id == other.id()) {
+        return Some(theirs.clone());
+    }
+
+    let versions = local_versions(file, ours);
+    other
+        .parents
+        .iter()
+        .find_map(|id| versions.get(id))
+        .and_then(|ff| keys.decrypt(ff).ok())
+}
+
+/// Collects the known versions of the local vault, keyed by [`FileFormat::id`].
+///
+/// Includes the current file, its backups and (if tracked) its git history.
+/// Versions that cannot be read or parsed are skipped.
+fn local_versions(file: &Path, current: &FileFormat) -> HashMap<String, FileFormat> {
+    let mut contents: Vec<String> = Vec::new();
+
+    for backup in list_backups(file).unwrap_or_default() {
+        if let Ok(c) = fs::read_to_string(&backup) {
+            contents.push(c);
+        }
+    }
+
+    if vcs::is_tracked_repo(file) {
+        for rev in vcs::revisions(file, MAX_HISTORY).unwrap_or_default() {
+            if let Ok(c) = vcs::show(file, &rev) {
+                contents.push(c);
+            }
+        }
+    }
+
+    contents
+        .iter()
+        .filter_map(|c| serde_json::from_str::<FileFormat>(c).ok())
+        .chain(std::iter::once(current.clone()))
+        .map(|ff| (ff.id().to_string(), ff))
+        .collect()
+}
+
+/// Derives keys once per salt so many copies of a vault can be opened quickly.
+pub(super) struct KeyCache {
+    password: String,
+    keys: HashMap<Vec<u8>, [u8; KEY_LEN]>,
+}
+
+impl KeyCache {
+    pub(super) fn new(password: String) -> Self {
+        Self {
+            password,
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Returns the key for `salt`, deriving it on first use.
+    pub(super) fn key(&mut self, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+        if let Some(key) = self.keys.get(salt) {
+            return Ok(*key);
+        }
+        let key = derive_key(&self.password, salt)?;
+        self.keys.insert(salt.to_vec(), key);
+        Ok(key)
+    }
+
+    /// Decrypts the vault stored in a [`FileFormat`].
+    pub(super) fn decrypt(&mut self, ff: &FileFormat) -> Result<Vault> {
+        let salt = general_purpose::STANDARD.decode(&ff.salt)?;
+        let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+        let key = self.key(&salt)?;
+        decrypt_vault_with_key(&blob, &key)
+    }
+
+    /// Encrypts `vault` into a new [`FileFormat`] that reuses the salt and version of `ff`.
+    pub(super) fn encrypt(&mut self, vault: &Vault, ff: &FileFormat) -> Result<FileFormat> {
+        let salt = general_purpose::STANDARD.decode(&ff.salt)?;
+        let key = self.key(&salt)?;
+        let blob = encrypt_vault_with_key(vault, &key)?;
+        Ok(FileFormat {
+            version: ff.version,
+            salt: ff.salt.clone(),
+            blob: general_purpose::STANDARD.encode(blob),
+            parents: Vec::new(),
+        })
+    }
+}
+
+/// Parses a vault file read from git.
+pub(super) fn parse_fileformat(contents: &str) -> Result<FileFormat> {
+    serde_json::from_str(contents).with_context(|| "Failed to parse vault JSON")
+}
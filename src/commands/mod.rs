@@ -4,8 +4,36 @@
 //! - [`handle_init`] — create a new encrypted vault file.
 //! - [`handle_add`] — add a new entry to the vault.
 //! - [`handle_get`] — retrieve and display a specific entry.
+//! - [`handle_generate`] — generate a random password or passphrase.
+//! - [`handle_policy_list`], [`handle_policy_set`], [`handle_policy_remove`],
+//!   [`handle_policy_assign`] — manage password policies.
 //! - [`handle_list`] — list all saved entries.
-//! - [`handle_remove`] — delete an entry by key.
+//! - [`handle_search`] — fuzzy-search entries by key, login, URL, tag or notes.
+//! - [`handle_edit`] — change single values of an existing entry.
+//! - [`handle_rotate`] — generate a new password and change it via a hook.
+//! - [`handle_remove`] — move an entry or a whole folder to the trash, or delete it.
+//! - [`handle_trash_list`], [`handle_trash_restore`], [`handle_trash_purge`] —
+//!   list, restore and empty the trash.
+//! - [`handle_mv`], [`handle_rename`], [`handle_cp`] — rename, move and copy
+//!   entries and folders.
+//! - [`handle_field_set`], [`handle_field_get`], [`handle_field_remove`] —
+//!   manage an entry's custom fields.
+//! - [`handle_export`] — write entries as unencrypted JSON.
+//! - [`handle_history`], [`handle_history_restore`] — list and restore earlier passwords.
+//! - [`handle_attach`], [`handle_attachments`], [`handle_extract`], [`handle_detach`] —
+//!   manage an entry's attached files.
+//! - [`handle_config`] — show or change the vault settings.
+//! - [`handle_expiry`] — set an entry's expiry and rotation interval.
+//! - [`handle_due`] — list expired entries and passwords due for rotation.
+//! - [`handle_refs`] — show an entry's references to and from other entries.
+//! - [`handle_log`] — show the git history of the vault.
+//! - [`handle_sync`] — pull, merge and push the vault via git.
+//! - [`handle_checkout`] — restore the vault from a git revision.
+//! - [`handle_merge`] — merge a diverged copy of the vault entry by entry.
+//! - [`handle_convert`] — switch between the single-file and directory layouts.
+//! - [`handle_verify`] — check a vault for damage and restore it from a backup.
+//! - [`handle_remote_login`], [`handle_remote_push`], [`handle_remote_pull`],
+//!   [`handle_remote_sync`] — sync the vault with a `passmgr-server`.
 //!
 //! Each function uses [`anyhow::Result`] for error propagation
 //! and relies on cryptographic utilities from [`crate::crypto`].
@@ -14,15 +42,146 @@
 //! and do not modify behavior when errors occur — they fail gracefully.
 
 mod add;
+mod attach;
+mod checkout;
+mod config;
+mod convert;
+mod due;
+mod edit;
+mod expiry;
+mod export;
+mod field;
+mod generate;
 mod get;
+mod history;
 mod init;
 mod list;
+mod log;
+mod merge;
+mod policy;
+mod mv;
+mod refs;
+mod remote;
 mod remove;
+mod rotate;
+mod search;
+mod sync;
+mod trash;
 mod update;
+mod verify;
 
 pub use update::handle_update;
 pub use add::handle_add;
 pub use get::handle_get;
+pub use generate::handle_generate;
+pub use policy::{handle_policy_assign, handle_policy_list, handle_policy_remove, handle_policy_set};
+pub use edit::handle_edit;
+pub use rotate::handle_rotate;
 pub use init::handle_init;
 pub use list::handle_list;
+pub use search::handle_search;
 pub use remove::handle_remove;
+pub use mv::{handle_cp, handle_mv, handle_rename};
+pub use log::handle_log;
+pub use sync::handle_sync;
+pub use checkout::handle_checkout;
+pub use merge::handle_merge;
+pub use convert::handle_convert;
+pub use verify::handle_verify;
+pub use field::{handle_field_get, handle_field_remove, handle_field_set};
+pub use export::handle_export;
+pub use history::{handle_history, handle_history_restore};
+pub use attach::{handle_attach, handle_attachments, handle_detach, handle_extract};
+pub use config::handle_config;
+pub use expiry::handle_expiry;
+pub use due::handle_due;
+pub use refs::handle_refs;
+pub use trash::{handle_trash_list, handle_trash_purge, handle_trash_restore};
+pub use remote::{handle_remote_login, handle_remote_pull, handle_remote_push, handle_remote_sync};
+
+/// Exit status of commands that refuse to overwrite an existing entry
+/// without `--force`, so scripts can tell a conflict from other errors (`1`).
+const EXIT_CONFLICT: i32 = 4;
+
+/// Prints `label` and reads one line from standard input, without the line break.
+fn prompt_line(label: &str) -> anyhow::Result<String> {
+    use std::io::Write;
+    print!("{}", label);
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Like [`prompt_line`], but returns `None` for an empty answer.
+fn prompt_optional(label: &str) -> anyhow::Result<Option<String>> {
+    let input = prompt_line(label)?;
+    Ok((!input.trim().is_empty()).then_some(input))
+}
+
+/// Asks for a new password and has it confirmed.
+///
+/// If `allow_empty` is set, an empty first answer is returned without
+/// confirmation (e.g. to keep the current password).
+///
+/// # Errors
+/// Returns an error if the two answers differ.
+fn prompt_new_password(label: &str, allow_empty: bool) -> anyhow::Result<String> {
+    let password = rpassword::prompt_password(label)?;
+    if password.is_empty() && allow_empty {
+        return Ok(password);
+    }
+    if rpassword::prompt_password("Confirm password: ")? != password {
+        return Err(anyhow::anyhow!("passwords do not match; nothing was changed"));
+    }
+    Ok(password)
+}
+
+/// Formats a timestamp in local time for display.
+fn format_time(t: &chrono::DateTime<chrono::Utc>) -> String {
+    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Formats a timestamp as a local date for display.
+fn format_date(t: &chrono::DateTime<chrono::Utc>) -> String {
+    t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string()
+}
+
+/// Parses an expiry given as a date (`YYYY-MM-DD`, the start of that day in
+/// local time) or as a number of days from now (`90d`, `12w`, `1y`).
+fn parse_expiry(s: &str) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    use chrono::{Local, NaiveDate, Utc};
+    if let Ok(date) = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(Local).earliest())
+            .map(|t| t.with_timezone(&Utc))
+            .ok_or_else(|| anyhow::anyhow!("{s:?} is not a valid local date"));
+    }
+    let days = crate::model::parse_days(s)
+        .map_err(|_| anyhow::anyhow!("{s:?} is neither a date (YYYY-MM-DD) nor a number of days (e.g. 90d)"))?;
+    Ok(Utc::now() + chrono::Duration::days(days.into()))
+}
+
+/// Describes how far `t` is from now, e.g. `in 3 days` or `12 days ago`.
+fn relative_days(t: &chrono::DateTime<chrono::Utc>) -> String {
+    let days = (t.with_timezone(&chrono::Local).date_naive() - chrono::Local::now().date_naive()).num_days();
+    match days {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        d if d > 0 => format!("in {d} days"),
+        d => format!("{} days ago", -d),
+    }
+}
+
+/// Describes a due date as `2026-01-31 (in 3 days)`.
+///
+/// [`crate::model::Entry::rotation_due_at`] returns the Unix epoch when the
+/// last password change is unknown; that is shown as due now.
+fn describe_due(t: &chrono::DateTime<chrono::Utc>) -> String {
+    if *t == chrono::DateTime::UNIX_EPOCH {
+        return "now (the last password change is unknown)".to_string();
+    }
+    format!("{} ({})", format_date(t), relative_days(t))
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use uuid::Uuid;
+use crate::{
+    model::AttachmentData,
+    refs,
+    storage::{open_vault, OpenVault},
+    vcs::Change,
+};
+use super::EXIT_CONFLICT;
+
+/// What [`relocate`] does with the entries.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// `mv`: rename, or move into an existing folder.
+    Move,
+    /// `rename`: the target is always the new name.
+    Rename,
+    /// `cp`: like `mv`, but the originals stay.
+    Copy,
+}
+
+/// Handles the `mv` subcommand.
+///
+/// Renames an entry, or moves an entry or a whole folder to another folder.
+///
+/// # Behavior
+/// - `from` may be an entry key or a folder; a folder moves with all its
+///   subfolders.
+/// - If `to` ends with `/` or is an existing folder, `from` moves into it
+///   under its own name; otherwise it is renamed to `to`.
+/// - Entries keep their ids, timestamps, history and attachments, and
+///   references to them by key (`{REF:P@T:<key>}`) are updated.
+/// - If any new key is already taken by another entry, nothing is moved and
+///   the process exits with [`EXIT_CONFLICT`]; with `force`, the entries in
+///   the way are moved to the trash instead.
+///
+/// # Errors
+/// Returns an error if there is nothing called `from`, a folder would be
+/// moved into itself, or decryption or saving fails.
+pub fn handle_mv(file: PathBuf, from: String, to: String, force: bool) -> Result<()> {
+    relocate(file, from, to, force, Mode::Move)
+}
+
+/// Handles the `rename` subcommand.
+///
+/// Like [`handle_mv`], but `new` is always the new key of `old` (or, for a
+/// folder, its new path), even if a folder called `new` exists.
+///
+/// # Errors
+/// Returns an error if there is nothing called `old`, a folder would be
+/// moved into itself, or decryption or saving fails.
+pub fn handle_rename(file: PathBuf, old: String, new: String, force: bool) -> Result<()> {
+    relocate(file, old, new, force, Mode::Rename)
+}
+
+/// Handles the `cp` subcommand.
+///
+/// Copies an entry or a whole folder, placing the copies like [`handle_mv`]
+/// does. A copy keeps the history, timestamps and attachments of its
+/// original but gets a new id; attachments in the blob store are copied too.
+///
+/// # Errors
+/// Returns an error if there is nothing called `from`, a folder would be
+/// copied into itself, or reading blobs, decryption or saving fails.
+pub fn handle_cp(file: PathBuf, from: String, to: String, force: bool) -> Result<()> {
+    relocate(file, from, to, force, Mode::Copy)
+}
+
+/// Moves, renames or copies `from` to `to`.
+fn relocate(file: PathBuf, from: String, to: String, force: bool, mode: Mode) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let moves = match mode {
+        Mode::Rename => open.vault.rename_targets(&from, &to)?,
+        Mode::Move | Mode::Copy => open.vault.move_targets(&from, &to)?,
+    };
+    let taken: Vec<String> = moves
+        .iter()
+        .map(|(_, new)| new)
+        .filter(|new| {
+            open.vault.entries.contains_key(*new) && (mode == Mode::Copy || !moves.iter().any(|(old, _)| old == *new))
+        })
+        .cloned()
+        .collect();
+    if !taken.is_empty() {
+        for key in &taken {
+            println!("⚠️  Entry '{}' already exists.", key);
+        }
+        if !force {
+            println!("❌ Nothing was {}. Use --force to move the entries in the way to the trash.", done(mode));
+            std::process::exit(EXIT_CONFLICT);
+        }
+        for key in &taken {
+            open.vault.move_to_trash(key);
+        }
+        println!("🗑️  Moved {} existing entr{} to the trash.", taken.len(), if taken.len() == 1 { "y" } else { "ies" });
+    }
+
+    let updated = if mode == Mode::Copy {
+        copy_entries(&mut open, &moves)?;
+        Vec::new()
+    } else {
+        let entries: Vec<_> = moves
+            .iter()
+            .filter_map(|(old, new)| open.vault.entries.remove(old).map(|e| (new.clone(), e)))
+            .collect();
+        open.vault.entries.extend(entries);
+        refs::retarget(&mut open.vault, &moves)
+    };
+    let verb = match mode {
+        Mode::Move => "move",
+        Mode::Rename => "rename",
+        Mode::Copy => "copy",
+    };
+    open.save(&Change::entry(verb, &from))?;
+
+    let (icon, what) = if mode == Mode::Copy { ("📋", "Copied") } else { ("🚚", "Moved") };
+    match moves.as_slice() {
+        [(old, new)] => println!("{} {} {} to {}.", icon, what, old, new),
+        _ => println!("{} {} {} entries from {} to {}.", icon, what, moves.len(), from.trim_matches('/'), to),
+    }
+    if !updated.is_empty() {
+        println!("🔗 Updated references in {}.", updated.join(", "));
+    }
+    Ok(())
+}
+
+/// Inserts a copy of every `(old key, new key)` entry under its new key.
+///
+/// Copies get new ids, and their blob-store attachments are duplicated so
+/// that removing one copy does not take the other's files with it.
+fn copy_entries(open: &mut OpenVault, copies: &[(String, String)]) -> Result<()> {
+    for (old, new) in copies {
+        let mut entry = open.vault.entries[old].clone();
+        entry.id = Uuid::new_v4();
+        for attachment in entry.attachments.iter_mut() {
+            if let AttachmentData::Blob(id) = &attachment.data {
+                let data = open.read_blob(id)?;
+                let copy = Uuid::new_v4();
+                open.write_blob(&copy, &data)?;
+                attachment.data = AttachmentData::Blob(copy);
+            }
+        }
+        open.vault.entries.insert(new.clone(), entry);
+    }
+    Ok(())
+}
+
+/// The past tense of what `mode` does, for messages.
+fn done(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Move | Mode::Rename => "moved",
+        Mode::Copy => "copied",
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use crate::{
+    generate::{check_policy_name, lookup, Policy, BUILTIN_POLICIES},
+    storage::open_vault,
+    vcs::Change,
+};
+
+/// Handles the `policy list` subcommand.
+///
+/// Lists the built-in password policies and those defined in the vault,
+/// with their specs and entropy.
+///
+/// # Errors
+/// Returns an error if reading or decryption fails.
+pub fn handle_policy_list(file: PathBuf) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let user: Vec<(&String, String, f64)> = open
+        .vault
+        .policies
+        .iter()
+        .map(|(name, p)| (name, p.to_string(), p.entropy_bits()))
+        .collect();
+    let width = BUILTIN_POLICIES
+        .iter()
+        .map(|(name, _, _)| *name)
+        .chain(user.iter().map(|(name, _, _)| name.as_str()))
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let spec_width = BUILTIN_POLICIES
+        .iter()
+        .map(|(_, spec, _)| *spec)
+        .chain(user.iter().map(|(_, spec, _)| spec.as_str()))
+        .map(|spec| spec.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    println!("📐 Built-in policies:");
+    for (name, spec, description) in BUILTIN_POLICIES {
+        let bits = Policy::parse(spec)?.entropy_bits();
+        let bits = format!("~{bits:.0}");
+        println!("  {:<width$}  {:<spec_width$}  {:>4} bits  {}", name, spec, bits, description);
+    }
+    println!("📐 Policies in this vault:");
+    if user.is_empty() {
+        println!("  (none; add one with 'policy set <name> <spec>')");
+    }
+    for (name, spec, bits) in user {
+        let bits = format!("~{bits:.0}");
+        println!("  {:<width$}  {:<spec_width$}  {:>4} bits", name, spec, bits);
+    }
+    Ok(())
+}
+
+/// Handles the `policy set` subcommand.
+///
+/// Defines (or replaces) the vault's policy `name` as `spec`, which is a
+/// policy spec or the name of another policy to copy.
+///
+/// Entries keep their own copy of a policy, so changing it here does not
+/// change the entries it was assigned to.
+///
+/// # Errors
+/// Returns an error if `name` cannot name a policy (see
+/// [`check_policy_name`]), `spec` is invalid, or decryption or saving fails.
+pub fn handle_policy_set(file: PathBuf, name: String, spec: String) -> Result<()> {
+    check_policy_name(&name)?;
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let policy = lookup(&open.vault, &spec)?;
+    let replaced = open.vault.policies.insert(name.clone(), policy.clone()).is_some();
+    open.save(&Change::vault("set password policy"))?;
+
+    let verb = if replaced { "Updated" } else { "Added" };
+    println!("📐 {} policy {}: {} (~{:.0} bits)", verb, name, policy, policy.entropy_bits());
+    Ok(())
+}
+
+/// Handles the `policy remove` subcommand.
+///
+/// Removes the vault's policy `name`; entries it was assigned to keep
+/// their copy.
+///
+/// # Errors
+/// Returns an error if decryption or saving fails.
+pub fn handle_policy_remove(file: PathBuf, name: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    if open.vault.policies.remove(&name).is_none() {
+        println!("⚠️  The vault has no policy called '{}'.", name);
+        return Ok(());
+    }
+    open.save(&Change::vault("remove password policy"))?;
+    println!("🗑️  Removed policy {}.", name);
+    Ok(())
+}
+
+/// Handles the `policy assign` subcommand.
+///
+/// Makes `policy` (a name or spec) the entry's generation policy: from then
+/// on, passwords generated for the entry (`add --generate`,
+/// `edit --generate`) follow it. With `policy` unset, the entry goes back to
+/// the default policy.
+///
+/// # Errors
+/// Returns an error if `policy` is neither a known policy nor a valid spec,
+/// or if decryption or saving fails.
+pub fn handle_policy_assign(file: PathBuf, key: String, policy: Option<String>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let policy = policy.as_deref().map(|p| lookup(&open.vault, p)).transpose()?;
+    let Some(entry) = open.vault.entries.get_mut(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    if !entry.kind.has_generated_password() {
+        return Err(anyhow!("{} entries have no generated password", entry.kind.name()));
+    }
+    if entry.policy == policy {
+        println!("Nothing changed.");
+        return Ok(());
+    }
+    entry.policy = policy.clone();
+    entry.touch();
+    open.save(&Change::entry("set policy", &key))?;
+
+    match policy {
+        Some(policy) => println!("📐 New passwords for {} follow: {} (~{:.0} bits)", key, policy, policy.entropy_bits()),
+        None => println!("📐 New passwords for {} follow the default policy.", key),
+    }
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use crate::{
+    refs::{self, Ref, RefField, Target},
+    storage::open_vault,
+};
+
+/// Handles the `refs` subcommand.
+///
+/// Shows how an entry is connected to others through references (see
+/// [`crate::refs`]): the reference to paste into another entry to reuse its
+/// password, the references it contains, and the entries that depend on it.
+///
+/// # Errors
+/// Returns an error if reading or decryption fails.
+pub fn handle_refs(file: PathBuf, key: String) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+
+    println!("🔗 {}", key);
+    println!("Reference its password with: {}", Ref::by_id(RefField::Password, entry.id));
+    println!("(U for the login, A for the URL, N for the notes, S:<name> for a custom field)");
+    if let Err(e) = refs::resolve_entry(&open.vault, &key) {
+        println!("⚠️  {e}");
+    }
+
+    println!("Uses:");
+    let uses = refs::references(entry);
+    if uses.is_empty() {
+        println!("  (nothing)");
+    }
+    for (field, r) in uses {
+        let target = match (&r.target, r.field.clone()) {
+            (Target::Id(id), f) => match open.vault.find_id(id) {
+                Some((k, _)) => format!("{k} ({f})"),
+                None => format!("{r} (missing entry)"),
+            },
+            (Target::Key(k), f) if open.vault.entries.contains_key(k) => format!("{k} ({f})"),
+            _ => format!("{r} (missing entry)"),
+        };
+        println!("  {} → {}", field, target);
+    }
+
+    println!("Used by:");
+    let users = refs::dependents(&open.vault, &key);
+    if users.is_empty() {
+        println!("  (nothing)");
+    }
+    let width = users.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+    for (user, fields) in users {
+        let fields: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+        println!("  {:<width$}  {}", user, fields.join(", "));
+    }
+    Ok(())
+}
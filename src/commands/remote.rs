@@ -0,0 +1,222 @@
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use crate::{
+    blobs,
+    merge::Strategy,
+    model::FileFormat,
+    remote::server::{PushOutcome, ServerLink},
+    storage::{load_fileformat, save_fileformat, Layout},
+    vcs::Change,
+};
+use super::merge::{merge_vaults, KeyCache};
+
+/// Handles the `remote login` subcommand.
+///
+/// Links the vault to an account on a `passmgr-server` instance.
+///
+/// # Behavior
+/// - Prompts for the access token (hidden input) if it is not given.
+/// - Checks the token against the server before saving the link.
+/// - The link is stored next to the vault in `<vault>.server.json`.
+/// - Warns if the vault has attachments in its blob store, which the server
+///   does not carry; new ones must fit into the vault.
+///
+/// # Errors
+/// Returns an error if the server rejects the token or cannot be reached.
+pub fn handle_remote_login(file: PathBuf, url: String, token: Option<String>) -> Result<()> {
+    let token = match token {
+        Some(t) => t,
+        None => rpassword::prompt_password("Access token: ")?,
+    };
+
+    let link = ServerLink {
+        url,
+        token,
+        revision: 0,
+        base: None,
+    };
+    let remote = link.fetch()?;
+    link.save(&file)?;
+
+    match remote {
+        Some((revision, _)) => println!("✅ Logged in. The server holds revision {}.", revision),
+        None => println!("✅ Logged in. The server holds no vault yet; run 'remote push'."),
+    }
+    let stored = blobs::count(&file, Layout::of(&file));
+    if stored > 0 {
+        println!(
+            "⚠️  {} attachment(s) are kept beside the vault and will not sync through the server; extract them before relying on other devices.",
+            stored
+        );
+    }
+    Ok(())
+}
+
+/// Handles the `remote push` subcommand.
+///
+/// Uploads the local vault to the sync server.
+///
+/// # Behavior
+/// - Only the encrypted vault file is sent; no password is asked for.
+/// - The server rejects the push if someone else pushed since the last sync;
+///   in that case `remote sync` merges both versions.
+///
+/// # Errors
+/// Returns an error if the vault is not linked or the upload fails.
+pub fn handle_remote_push(file: PathBuf) -> Result<()> {
+    let Some(mut link) = require_link(&file)? else {
+        return Ok(());
+    };
+    let Some(local) = load_fileformat(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    match link.push(&local, link.revision)? {
+        PushOutcome::Pushed(revision) => {
+            link.synced(revision, &local);
+            link.save(&file)?;
+            println!("⬆️  Pushed revision {}.", revision);
+        }
+        PushOutcome::Stale(revision) => {
+            println!("⚠️  The server has a newer revision ({}). Run 'remote sync' to merge.", revision);
+        }
+    }
+    Ok(())
+}
+
+/// Handles the `remote pull` subcommand.
+///
+/// Replaces the local vault with the copy stored on the sync server.
+///
+/// # Behavior
+/// - Creates the local vault if it does not exist yet.
+/// - Refuses to overwrite local changes made since the last sync;
+///   use `remote sync` to merge them instead.
+///
+/// # Errors
+/// Returns an error if the vault is not linked or the download fails.
+pub fn handle_remote_pull(file: PathBuf) -> Result<()> {
+    let Some(mut link) = require_link(&file)? else {
+        return Ok(());
+    };
+    let Some((revision, remote)) = link.fetch()? else {
+        println!("⚠️  The server holds no vault yet; run 'remote push'.");
+        return Ok(());
+    };
+
+    if let Some(local) = load_fileformat(&file)? {
+        if revision == link.revision && !link.local_changed(&local) {
+            println!("✅ Already up to date (revision {}).", revision);
+            return Ok(());
+        }
+        if link.local_changed(&local) && local.blob != remote.blob {
+            println!("⚠️  The local vault has changes that were never pushed. Run 'remote sync' to merge.");
+            return Ok(());
+        }
+    }
+
+    save_fileformat(&file, &remote, &Change::vault("pull from server"))?;
+    link.synced(revision, &remote);
+    link.save(&file)?;
+    println!("⬇️  Pulled revision {}.", revision);
+    Ok(())
+}
+
+/// Handles the `remote sync` subcommand.
+///
+/// Brings the local vault and the server copy up to date with each other.
+///
+/// # Behavior
+/// - Pushes if only the local vault changed, pulls if only the server copy changed.
+/// - If both changed, prompts for the master password, merges the two
+///   versions entry by entry against the last synced copy (see `merge`),
+///   saves the result locally and pushes it.
+///
+/// # Errors
+/// Returns an error if the vault is not linked, decryption fails,
+/// or the server cannot be reached.
+pub fn handle_remote_sync(file: PathBuf, strategy: Strategy) -> Result<()> {
+    let Some(mut link) = require_link(&file)? else {
+        return Ok(());
+    };
+    let Some(local) = load_fileformat(&file)? else {
+        return handle_remote_pull(file);
+    };
+
+    let Some((revision, remote)) = link.fetch()? else {
+        return handle_remote_push(file);
+    };
+
+    let local_changed = link.local_changed(&local) && local.blob != remote.blob;
+    let remote_changed = revision != link.revision;
+
+    let to_push = match (local_changed, remote_changed) {
+        (false, false) => {
+            println!("✅ Already up to date (revision {}).", revision);
+            return Ok(());
+        }
+        (false, true) => {
+            save_fileformat(&file, &remote, &Change::vault("pull from server"))?;
+            link.synced(revision, &remote);
+            link.save(&file)?;
+            println!("⬇️  Pulled revision {}.", revision);
+            return Ok(());
+        }
+        (true, false) => local,
+        (true, true) => {
+            println!("⚠️  The vault was changed both locally and on the server.");
+            let merged = merge_with_server(&link, &local, &remote, strategy)?;
+            save_fileformat(&file, &merged, &Change::vault("merge vault"))?;
+            // Push exactly what is now on disk, including its updated parents
+            load_fileformat(&file)?.ok_or_else(|| anyhow!("vault disappeared while merging"))?
+        }
+    };
+
+    match link.push(&to_push, revision)? {
+        PushOutcome::Pushed(new_revision) => {
+            link.synced(new_revision, &to_push);
+            link.save(&file)?;
+            println!("🔄 Synced; the server is now at revision {}.", new_revision);
+        }
+        PushOutcome::Stale(newer) => {
+            println!("⚠️  Someone pushed revision {} meanwhile. Run 'remote sync' again.", newer);
+        }
+    }
+    Ok(())
+}
+
+/// Merges the local vault with the server copy, using the last synced copy as ancestor.
+fn merge_with_server(
+    link: &ServerLink,
+    local: &FileFormat,
+    remote: &FileFormat,
+    strategy: Strategy,
+) -> Result<FileFormat> {
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut keys = KeyCache::new(master);
+    let ours = keys.decrypt(local)?;
+    let theirs = keys.decrypt(remote)?;
+    let base = match &link.base {
+        Some(b) => keys.decrypt(b).ok(),
+        None => None,
+    };
+
+    // The server keeps no timestamps, so for entries without a modification
+    // time `newest` treats the local copy as the newer one.
+    let merged = merge_vaults(base.as_ref(), &ours, &theirs, strategy, true)?;
+    let mut ff = keys.encrypt(&merged, local)?;
+    ff.parents = std::iter::once(remote.id().to_string())
+        .chain(remote.parents.iter().cloned())
+        .collect();
+    Ok(ff)
+}
+
+/// Loads the server link for `file`, printing a hint if there is none.
+fn require_link(file: &PathBuf) -> Result<Option<ServerLink>> {
+    let link = ServerLink::load(file)?;
+    if link.is_none() {
+        println!("⚠️  {:?} is not linked to a sync server. Run 'remote login' first.", file);
+    }
+    Ok(link)
+}
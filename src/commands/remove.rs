@@ -1,56 +1,80 @@
 use std::path::PathBuf;
 use anyhow::Result;
-use base64::{engine::general_purpose, Engine as _};
-use rpassword;
 use crate::{
-    crypto::{decrypt_vault, encrypt_vault},
-    model::FileFormat,
-    storage::{load_fileformat, save_fileformat},
+    storage::open_vault,
+    vcs::Change,
 };
 
 /// Handles the `remove` subcommand.
 ///
-/// Decrypts the vault, removes an entry by key, and saves the updated vault back to disk.
+/// Decrypts the vault, moves an entry to the trash by key, and saves the
+/// updated vault back to disk.
 ///
 /// # Behavior
 /// - If the vault file does not exist, prints an error message and exits gracefully.
 /// - Prompts the user for the master password.
-/// - Removes the entry with the given key if it exists.
+/// - Moves the entry with the given key into the vault's encrypted trash,
+///   from where `trash restore` brings it back.
+/// - With `recursive`, also removes every entry in the folder `key` and its
+///   subfolders; without it, a folder is only reported.
+/// - With `permanent`, the entries are deleted outright instead, together
+///   with their attachment blobs.
 /// - If removal succeeds, re-encrypts and saves the updated vault.
 /// - If the entry does not exist, prints a warning.
 ///
 /// # Errors
 /// Returns an error if file I/O, base64 decoding, decryption, or encryption fails.
-pub fn handle_remove(file: PathBuf, key: String) -> Result<()> {
-    // Ensure the vault file exists
-    if !file.exists() {
+pub fn handle_remove(file: PathBuf, key: String, recursive: bool, permanent: bool) -> Result<()> {
+    // Load the vault (file, directory or remote), which must already exist
+    let Some(locked) = open_vault(&file)? else {
         println!("❌ File {:?} not found. Please run 'init' first.", file);
         return Ok(());
-    }
-
-    // Load and decode the file contents
-    let ff = load_fileformat(&file)?.expect("Error reading file");
-    let salt = general_purpose::STANDARD.decode(&ff.salt)?;
-    let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+    };
 
     // Ask for master password (hidden input)
     let master = rpassword::prompt_password("Master password: ")?;
-    let mut vault = decrypt_vault(&blob, &master, &salt)?;
+    let mut open = locked.unlock(&master)?;
 
-    // Attempt to remove the specified entry
-    if vault.entries.remove(&key).is_some() {
-        // Re-encrypt and save the updated vault
-        let new_blob = encrypt_vault(&vault, &master, &salt)?;
-        let new_ff = FileFormat {
-            version: ff.version,
-            salt: ff.salt,
-            blob: general_purpose::STANDARD.encode(&new_blob),
-        };
-        save_fileformat(&file, &new_ff)?;
-        println!("🗑️  Removed: {}", key);
-    } else {
-        println!("⚠️  Entry '{}' not found.", key);
+    // Work out what to remove: the entry, or the whole folder
+    let folder = open.vault.folder_keys(&key);
+    let mut keys = Vec::new();
+    if open.vault.entries.contains_key(key.trim_matches('/')) {
+        keys.push(key.trim_matches('/').to_string());
+    }
+    if recursive {
+        keys.extend(folder.iter().cloned());
+    }
+    if keys.is_empty() {
+        if !folder.is_empty() {
+            println!("⚠️  '{}' is a folder with {} entries; use --recursive to remove it.", key, folder.len());
+        } else {
+            println!("⚠️  Entry '{}' not found.", key);
+        }
+        return Ok(());
     }
 
+    // Move the entries to the trash, or delete them for good
+    let mut deleted = Vec::new();
+    for k in &keys {
+        if permanent {
+            deleted.extend(open.vault.entries.remove(k));
+        } else {
+            open.vault.move_to_trash(k);
+        }
+    }
+    open.save(&Change::entry("remove", &key))?;
+    for entry in &deleted {
+        open.remove_entry_blobs(entry)?;
+    }
+
+    let what = match keys.as_slice() {
+        [single] => single.clone(),
+        _ => format!("folder {} ({} entries)", key.trim_matches('/'), keys.len()),
+    };
+    if permanent {
+        println!("🗑️  Permanently removed {}.", what);
+    } else {
+        println!("🗑️  Moved {} to the trash. Restore with 'trash restore {}'.", what, key.trim_matches('/'));
+    }
     Ok(())
 }
@@ -0,0 +1,266 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use crate::{
+    cli::RotateArgs,
+    journal::Pending,
+    model::Hooks,
+    refs,
+    storage::{open_vault, OpenVault},
+    vcs::Change,
+};
+use super::format_time;
+
+/// Handles the `rotate` subcommand.
+///
+/// Generates a new password for an entry following its policy (see
+/// [`crate::model::Entry::policy`]) and runs the entry's hooks (see
+/// [`Hooks`]) to change it where it is used.
+///
+/// # Behavior
+/// - The hooks get the old and the new password on stdin, one per line,
+///   and the key and login in `PASSMGR_KEY` and `PASSMGR_LOGIN`.
+/// - `--hook` and `--post-hook` replace the stored hooks for this run;
+///   with `--save-hooks`, exactly these are stored with the entry for later
+///   rotations. `--no-hooks` rotates without running any hook.
+/// - While the pre hook runs, the new password is only kept in a local
+///   journal (see [`crate::journal`]), never in the vault, its backups, git
+///   history or remote copy. It goes into the vault once the hook exits
+///   with 0; otherwise the vault is left as it was.
+/// - If an earlier rotation was interrupted while its hook ran, the
+///   password it was trying to set is shown first, in case the hook got
+///   through.
+/// - Once the new password is saved, the post hook runs; if it fails, a
+///   warning is shown and the new password is kept.
+///
+/// # Errors
+/// Returns an error if the entry has no generated password or its password
+/// is a reference, if the pre hook cannot be run or fails, or if
+/// decryption or saving fails.
+pub fn handle_rotate(args: RotateArgs) -> Result<()> {
+    let RotateArgs { file, key, hook, post_hook, save_hooks, no_hooks } = args;
+
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let Some(entry) = open.vault.entries.get(&key) else {
+        println!("⚠️  Entry '{}' not found.", key);
+        return Ok(());
+    };
+    let hooks = choose_hooks(&entry.hooks, hook, post_hook, save_hooks, no_hooks);
+
+    if let Some(pending) = open.pending_rotation()? {
+        println!(
+            "⚠️  The rotation of {} started {} was interrupted while its hook ran.",
+            pending.key,
+            format_time(&pending.started_at)
+        );
+        println!("   If the hook got through, the password is now: {}", pending.password);
+    }
+    rotate(&mut open, &key, &hooks, save_hooks)
+}
+
+/// Works out which hooks a rotation runs.
+///
+/// `hook` and `post_hook` take the place of the `stored` ones; with
+/// `save_hooks`, only the given hooks run (and are then stored), and with
+/// `no_hooks`, none do.
+fn choose_hooks(
+    stored: &Hooks,
+    hook: Option<String>,
+    post_hook: Option<String>,
+    save_hooks: bool,
+    no_hooks: bool,
+) -> Hooks {
+    if no_hooks {
+        Hooks::default()
+    } else if save_hooks {
+        Hooks { pre: hook, post: post_hook }
+    } else {
+        Hooks { pre: hook.or(stored.pre.clone()), post: post_hook.or(stored.post.clone()) }
+    }
+}
+
+/// Rotates the password of the entry `key`, running `hooks`, and stores
+/// `hooks` with the entry if `save_hooks` is set.
+fn rotate(open: &mut OpenVault, key: &str, hooks: &Hooks, save_hooks: bool) -> Result<()> {
+    let entry = open.vault.entries.get(key).ok_or_else(|| anyhow!("there is no entry '{key}'"))?;
+    if !entry.kind.has_generated_password() {
+        return Err(anyhow!("{} entries have no generated password", entry.kind.name()));
+    }
+    if !refs::parse(&entry.password)?.is_empty() {
+        return Err(anyhow!("the password of {} is a reference; rotate the entry it refers to", key));
+    }
+
+    let id = entry.id;
+    let login = refs::resolve_entry(&open.vault, key)?.login;
+    let policy = entry.policy.clone().unwrap_or_default();
+    let password = policy.generate()?;
+    let input = format!("{}\n{}\n", entry.password, password);
+
+    if let Some(pre) = &hooks.pre {
+        let pending = Pending { key: key.to_string(), id, password: password.clone(), started_at: Utc::now() };
+        open.begin_rotation(&pending)?;
+        println!("🪝 Running the rotate hook for {}...", key);
+        let failure = match run_hook(pre, &input, key, &login) {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("the rotate hook failed ({status})")),
+            Err(e) => Some(format!("{e:#}")),
+        };
+        if let Some(failure) = failure {
+            open.end_rotation()?;
+            return Err(anyhow!("{failure}; the password of {key} was not changed"));
+        }
+    }
+
+    let entry = open.vault.entries.get_mut(key).expect("the entry was looked up above");
+    entry.set_password(password);
+    if save_hooks {
+        entry.hooks = hooks.clone();
+    }
+    open.save(&Change::entry("rotate", key))?;
+    open.end_rotation()?;
+
+    println!("🔄 Rotated the password of {} (~{:.0} bits).", key, policy.entropy_bits());
+    match (save_hooks, hooks.is_empty()) {
+        (true, false) => println!("🪝 Saved the hooks of {}.", key),
+        (true, true) => println!("🪝 Removed the stored hooks of {}.", key),
+        _ => {}
+    }
+    if hooks.pre.is_none() {
+        println!("⚠️  No rotate hook is set for {}; change the password where it is used yourself.", key);
+    }
+    if let Some(post) = &hooks.post {
+        match run_hook(post, &input, key, &login) {
+            Ok(status) if status.success() => {}
+            Ok(status) => println!("⚠️  The post-rotate hook failed ({status}); the new password is saved anyway."),
+            Err(e) => println!("⚠️  {e:#}; the new password is saved anyway."),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the hook `command` through the shell, writing `input` to its stdin.
+fn run_hook(command: &str, input: &str, key: &str, login: &str) -> Result<ExitStatus> {
+    let mut shell = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+    shell.arg(if cfg!(windows) { "/C" } else { "-c" });
+    let mut child = shell
+        .arg(command)
+        .env("PASSMGR_KEY", key)
+        .env("PASSMGR_LOGIN", login)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run the hook `{command}`"))?;
+
+    // A hook that ignores its input may exit before reading it
+    let written = match child.stdin.take() {
+        Some(mut stdin) => match stdin.write_all(input.as_bytes()) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => Err(e),
+            _ => Ok(()),
+        },
+        None => Ok(()),
+    };
+    let status = child.wait().with_context(|| format!("could not run the hook `{command}`"))?;
+    written.with_context(|| format!("could not pass the passwords to the hook `{command}`"))?;
+    Ok(status)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+    use tempfile::TempDir;
+    use crate::{journal, model::Entry, storage::Layout};
+
+    /// Creates a vault in a temporary directory with one login entry.
+    fn scratch_vault() -> (TempDir, PathBuf, OpenVault) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vault.json");
+        let mut open = OpenVault::create(&path, Layout::File, "master").unwrap();
+        open.vault.entries.insert("db".into(), Entry::new("admin".into(), "old".into(), None));
+        open.save(&Change::vault("init vault")).unwrap();
+        (dir, path, open)
+    }
+
+    /// Reads the entry `db` back from disk.
+    fn stored(path: &Path) -> Entry {
+        let open = open_vault(path).unwrap().unwrap().unlock("master").unwrap();
+        open.vault.entries["db"].clone()
+    }
+
+    /// Stored hooks apply unless replaced; `--save-hooks` and `--no-hooks` take only what they say.
+    #[test]
+    fn chooses_hooks() {
+        let stored = Hooks { pre: Some("pre".into()), post: Some("post".into()) };
+        let hooks = |hook: Option<&str>, save: bool, none: bool| {
+            choose_hooks(&stored, hook.map(str::to_string), None, save, none)
+        };
+        assert_eq!(hooks(None, false, false), stored);
+        assert_eq!(hooks(Some("new"), false, false), Hooks { pre: Some("new".into()), post: Some("post".into()) });
+        assert_eq!(hooks(Some("new"), true, false), Hooks { pre: Some("new".into()), post: None });
+        assert_eq!(hooks(None, true, false), Hooks::default());
+        assert_eq!(hooks(None, false, true), Hooks::default());
+    }
+
+    /// Hooks see the passwords on stdin and the entry in the environment.
+    #[test]
+    fn runs_hooks_with_input() {
+        assert!(run_hook("true", "", "k", "l").unwrap().success());
+        assert!(!run_hook("false", "", "k", "l").unwrap().success());
+        let check = r#"read old; read new; [ "$old" = a ] && [ "$new" = b ] && [ "$PASSMGR_KEY/$PASSMGR_LOGIN" = k/l ]"#;
+        assert!(run_hook(check, "a\nb\n", "k", "l").unwrap().success());
+        assert_eq!(run_hook("exit 3", "a\nb\n", "k", "l").unwrap().code(), Some(3));
+    }
+
+    /// A successful hook commits the new password and stores the hooks.
+    #[test]
+    fn keeps_the_password_when_the_hook_succeeds() {
+        let (_dir, path, mut open) = scratch_vault();
+        let hooks = Hooks { pre: Some("true".into()), post: Some("false".into()) };
+        rotate(&mut open, "db", &hooks, true).unwrap();
+
+        let entry = stored(&path);
+        assert_ne!(entry.password, "old");
+        assert_eq!(entry.history[0].password, "old");
+        assert_eq!(entry.hooks, hooks);
+        assert!(!journal::path(&path).exists());
+    }
+
+    /// A failing hook leaves the vault on disk and in memory untouched.
+    #[test]
+    fn rolls_back_when_the_hook_fails() {
+        let (_dir, path, mut open) = scratch_vault();
+        let before = std::fs::read(&path).unwrap();
+        let hooks = Hooks { pre: Some("false".into()), post: None };
+        let err = rotate(&mut open, "db", &hooks, true).unwrap_err();
+
+        assert!(err.to_string().contains("was not changed"));
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+        assert_eq!(open.vault.entries["db"].password, "old");
+        assert!(open.vault.entries["db"].hooks.is_empty());
+        assert_eq!(stored(&path).password, "old");
+        assert!(!journal::path(&path).exists());
+    }
+
+    /// While the hook runs, the new password is only in the journal.
+    #[test]
+    fn journals_the_pending_password() {
+        let (_dir, path, mut open) = scratch_vault();
+        let journal = journal::path(&path);
+        let hooks = Hooks { pre: Some(format!("cp '{}' '{}.seen'", journal.display(), journal.display())), post: None };
+        rotate(&mut open, "db", &hooks, false).unwrap();
+
+        let seen = std::fs::read(format!("{}.seen", journal.display())).unwrap();
+        std::fs::rename(format!("{}.seen", journal.display()), &journal).unwrap();
+        assert!(!seen.is_empty());
+        let pending = open.pending_rotation().unwrap().unwrap();
+        assert_eq!(pending.key, "db");
+        assert_eq!(pending.password, stored(&path).password);
+    }
+}
@@ -0,0 +1,81 @@
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use anyhow::{anyhow, Result};
+use crate::{
+    refs,
+    search::{self, Query},
+    storage::open_vault,
+};
+use super::{get::show_entry, prompt_line};
+
+/// Handles the `search` subcommand.
+///
+/// Fuzzy-matches `query` against the keys, logins, URLs and tags of all
+/// entries (and their notes, with `notes`) and lists the matches, best
+/// first. Terms can be limited to one value, e.g. `login:alice tag:prod`;
+/// see [`crate::search`].
+///
+/// References are resolved before matching, so an entry whose login is
+/// taken from another entry is found by that login.
+///
+/// When exactly one entry matches and the command runs in a terminal, it
+/// offers to show that entry like `get` does.
+///
+/// # Errors
+/// Returns an error if the query is empty, or if reading or decryption fails.
+pub fn handle_search(file: PathBuf, query: Vec<String>, notes: bool) -> Result<()> {
+    let query_text = query.join(" ");
+    let query = Query::parse(&query_text, notes);
+    if query.terms.is_empty() {
+        return Err(anyhow!("the search query is empty"));
+    }
+
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    // Match against the values as shown, with references resolved
+    let mut resolved = open.vault.clone();
+    for (key, entry) in resolved.entries.iter_mut() {
+        if let Ok(e) = refs::resolve_entry(&open.vault, key) {
+            *entry = e;
+        }
+    }
+    let hits = search::search(&resolved, &query);
+
+    if hits.is_empty() {
+        println!("🔎 Nothing matches \"{}\".", query_text);
+        return Ok(());
+    }
+    println!(
+        "🔎 {} match{} for \"{}\":",
+        hits.len(),
+        if hits.len() == 1 { "" } else { "es" },
+        query_text
+    );
+    let width = hits.iter().map(|(k, _)| k.chars().count()).max().unwrap_or(0);
+    for (key, _) in &hits {
+        let entry = &resolved.entries[*key];
+        let mut line = format!("  {:<width$}  {}", key, entry.login);
+        if !entry.tags.is_empty() {
+            let tags: Vec<&str> = entry.tags.iter().map(String::as_str).collect();
+            line.push_str(&format!(" [{}]", tags.join(", ")));
+        }
+        println!("{}", line.trim_end());
+    }
+
+    if let [(key, _)] = hits.as_slice() {
+        if std::io::stdin().is_terminal() {
+            let answer = prompt_line(&format!("Show {}? [Y/n]: ", key))?;
+            if !answer.trim().to_lowercase().starts_with('n') {
+                let key = key.to_string();
+                return show_entry(&open, &key, false);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use crate::{
+    merge::Strategy,
+    storage::save_fileformat,
+    vcs::{self, Change, SyncOutcome},
+};
+use super::merge::{merge_vaults, parse_fileformat, KeyCache};
+
+/// Handles the `sync` subcommand.
+///
+/// Pulls the vault history from a git remote, merges it into the current
+/// branch and pushes the result back.
+///
+/// # Behavior
+/// - If the vault is not tracked in git, prints a hint and exits gracefully.
+/// - Works with any git remote, including a local bare repository.
+/// - If both sides changed the vault, prompts for the master password and
+///   merges the two versions entry by entry (see `merge`), resolving
+///   conflicts with `strategy`, then commits and pushes the result.
+/// - If the entry merge fails, the git merge is aborted and the local vault is left untouched.
+/// - If the merge was committed but cannot be pushed, it is kept locally and
+///   the next `sync` pushes it.
+///
+/// # Errors
+/// Returns an error if fetching, merging, decryption, or pushing fails.
+pub fn handle_sync(file: PathBuf, remote: String, strategy: Strategy) -> Result<()> {
+    if !vcs::is_tracked_repo(&file) {
+        println!("⚠️  {:?} is not tracked in git. Run 'init --git' or 'git init' first.", file);
+        return Ok(());
+    }
+
+    if vcs::sync(&file, &remote)? == SyncOutcome::Conflict {
+        println!("⚠️  The vault was changed both locally and on '{}'.", remote);
+        if let Err(e) = resolve_conflict(&file, strategy) {
+            match vcs::abort_merge(&file) {
+                Ok(()) => println!("The merge was aborted; your local vault is unchanged."),
+                Err(abort) => println!("⚠️  The merge could not be aborted: {:#}", abort),
+            }
+            return Err(e);
+        }
+        vcs::push_merge(&file, &remote).with_context(|| {
+            format!("the merge was committed locally but could not be pushed to '{remote}'; run 'sync' again to push it")
+        })?;
+    }
+
+    println!("🔄 Synced with '{}'.", remote);
+    Ok(())
+}
+
+/// Merges the local and incoming vault versions of a conflicted git merge
+/// and commits the result.
+fn resolve_conflict(file: &Path, strategy: Strategy) -> Result<()> {
+    let versions = vcs::conflict_versions(file)?;
+    let ours_ff = parse_fileformat(versions.ours.as_deref().ok_or_else(|| anyhow!("vault deleted locally"))?)?;
+    let theirs_ff = parse_fileformat(versions.theirs.as_deref().ok_or_else(|| anyhow!("vault deleted on remote"))?)?;
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut keys = KeyCache::new(master);
+    let ours = keys.decrypt(&ours_ff)?;
+    let theirs = keys.decrypt(&theirs_ff)?;
+    let base = match versions.base.as_deref() {
+        Some(contents) => Some(keys.decrypt(&parse_fileformat(contents)?)?),
+        None => None,
+    };
+
+    let merged = merge_vaults(base.as_ref(), &ours, &theirs, strategy, versions.ours_newer)?;
+    let new_ff = keys.encrypt(&merged, &ours_ff)?;
+    save_fileformat(file, &new_ff, &Change::vault("merge vault"))?;
+    vcs::commit_merge(file)
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use crate::{model::parse_days, storage::open_vault, vcs::Change};
+use super::format_time;
+
+/// Handles the `trash list` subcommand.
+///
+/// Lists the removed entries still in the trash, most recently removed first.
+///
+/// # Errors
+/// Returns an error if reading or decryption fails.
+pub fn handle_trash_list(file: PathBuf) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let open = locked.unlock(&master)?;
+
+    println!("🗑️  Trash:");
+    if open.vault.trash.is_empty() {
+        println!("(empty)");
+    }
+    let width = open.vault.trash.iter().map(|t| t.key.chars().count()).max().unwrap_or(0);
+    for t in open.vault.trash.iter().rev() {
+        println!("  {:<width$}  removed {}", t.key, format_time(&t.deleted_at));
+    }
+    Ok(())
+}
+
+/// Handles the `trash restore` subcommand.
+///
+/// Puts the most recently removed entry called `key` back into the vault,
+/// or every entry removed from the folder `key`. With `to`, a single entry
+/// is restored under that key instead.
+///
+/// # Behavior
+/// - Nothing is restored if any of the keys is taken by an existing entry.
+/// - Restored entries keep their id, history, attachments and timestamps.
+///
+/// # Errors
+/// Returns an error if decryption or saving fails.
+pub fn handle_trash_restore(file: PathBuf, key: String, to: Option<String>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let mut restored = open.vault.take_from_trash(&key);
+    if restored.is_empty() {
+        println!("⚠️  Nothing called '{}' is in the trash.", key);
+        return Ok(());
+    }
+    if let Some(to) = &to {
+        if restored.len() > 1 {
+            println!("⚠️  '{}' is a folder of {} entries; --as only works for a single entry.", key, restored.len());
+            return Ok(());
+        }
+        restored[0].key = to.clone();
+    }
+
+    let taken: Vec<&String> = restored
+        .iter()
+        .map(|t| &t.key)
+        .filter(|k| open.vault.entries.contains_key(*k))
+        .collect();
+    if !taken.is_empty() {
+        for key in taken {
+            println!("⚠️  Entry '{}' already exists.", key);
+        }
+        println!("Nothing was restored. Use --as to restore a single entry under another key.");
+        return Ok(());
+    }
+
+    let count = restored.len();
+    let first = restored[0].key.clone();
+    for t in restored {
+        open.vault.entries.insert(t.key, t.entry);
+    }
+    open.save(&Change::entry("restore", &key))?;
+
+    if count == 1 {
+        println!("♻️  Restored {}.", first);
+    } else {
+        println!("♻️  Restored {} entries from {}.", count, key.trim_matches('/'));
+    }
+    Ok(())
+}
+
+/// Handles the `trash purge` subcommand.
+///
+/// Permanently deletes the trashed entries removed more than `older_than`
+/// days ago (e.g. `30d`), or the whole trash, together with their
+/// attachment blobs.
+///
+/// # Errors
+/// Returns an error if `older_than` is not a number of days, or if
+/// decryption or saving fails.
+pub fn handle_trash_purge(file: PathBuf, older_than: Option<String>) -> Result<()> {
+    let Some(locked) = open_vault(&file)? else {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    };
+    let cutoff = older_than
+        .as_deref()
+        .map(parse_days)
+        .transpose()?
+        .map(|days| Utc::now() - Duration::days(days.into()));
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut open = locked.unlock(&master)?;
+
+    let purged = open.vault.purge_trash(cutoff);
+    if purged.is_empty() {
+        println!("Nothing to purge.");
+        return Ok(());
+    }
+    open.save(&Change::vault("purge trash"))?;
+    for t in &purged {
+        open.remove_entry_blobs(&t.entry)?;
+    }
+
+    println!(
+        "🔥 Permanently deleted {} entr{} from the trash.",
+        purged.len(),
+        if purged.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Local, TimeZone};
+use serde_json::Value;
+use crate::{
+    crypto::decrypt_json_with_key,
+    dirvault,
+    model::FileFormat,
+    perms,
+    remote,
+    storage::{list_backups, load_fileformat, save_fileformat, vault_exists, Layout},
+    verify::{check_blob, check_entry, check_header, check_vault},
+    vcs::Change,
+};
+use super::merge::KeyCache;
+
+/// Handles the `verify` subcommand.
+///
+/// Checks a vault for damage and tells a mistyped password apart from a
+/// corrupted file.
+///
+/// # Behavior
+/// - Before asking for a password, checks the JSON structure of the file,
+///   base64 encoding, salt and nonce lengths and the header fields.
+/// - After decrypting, validates every entry against the vault schema.
+/// - If decryption fails, tries the same password on the backups: if a
+///   backup opens, the vault file is damaged; otherwise the password is
+///   most likely wrong.
+/// - If the vault is damaged, points at the newest intact backup; with
+///   `restore`, that backup replaces the vault (the damaged file is kept
+///   as a backup itself).
+/// - Directory vaults are checked manifest first, then entry by entry.
+///
+/// # Errors
+/// Returns an error (and so a non-zero exit status) if any problem was found.
+pub fn handle_verify(file: PathBuf, restore: bool) -> Result<()> {
+    if !vault_exists(&file)? {
+        println!("❌ File {:?} not found. Please run 'init' first.", file);
+        return Ok(());
+    }
+
+    let problems = match Layout::of(&file) {
+        Layout::File => verify_file(&file, restore)?,
+        Layout::Directory => {
+            if restore {
+                println!("⚠️  Directory vaults have no backups; restore damaged entries from git instead.");
+            }
+            verify_directory(&file)?
+        }
+    };
+
+    if problems > 0 {
+        return Err(anyhow!("verification found {problems} problem(s) in {}", file.display()));
+    }
+    println!("✅ {:?} is intact.", file);
+    Ok(())
+}
+
+/// Verifies a single-file vault and returns the number of problems found.
+fn verify_file(file: &Path, restore: bool) -> Result<usize> {
+    let text = if remote::is_remote(file) {
+        let ff = load_fileformat(file)?.ok_or_else(|| anyhow!("remote vault disappeared"))?;
+        serde_json::to_string(&ff)?
+    } else {
+        perms::check(file)?;
+        fs::read_to_string(file).with_context(|| format!("Failed to read file: {}", file.display()))?
+    };
+
+    println!("🔍 Checking {:?}", file);
+    let (ff, header_problems) = check_header(&text);
+    report("file structure", &header_problems);
+    let mut problems = header_problems.len();
+
+    let backups = if remote::is_remote(file) { Vec::new() } else { list_backups(file)? };
+    if ff.is_none() && backups.is_empty() {
+        println!("💾 There are no backups to restore from.");
+        return Ok(problems);
+    }
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut keys = KeyCache::new(master);
+
+    let mut decrypted = false;
+    if let Some(ff) = &ff {
+        match decrypt_value(&mut keys, ff) {
+            Ok(value) => {
+                decrypted = true;
+                let schema_problems = check_vault(&value);
+                report("vault contents", &schema_problems);
+                problems += schema_problems.len();
+                let count = value["entries"].as_object().map_or(0, |e| e.len());
+                println!("🔓 Decrypted {} entr{}.", count, if count == 1 { "y" } else { "ies" });
+            }
+            Err(e) => {
+                problems += 1;
+                println!("❌ Decryption failed: {e:#}");
+            }
+        }
+    }
+
+    if problems == 0 {
+        return Ok(0);
+    }
+
+    // Try the backups, newest first, with the same password
+    let intact = backups.iter().find_map(|path| {
+        let text = fs::read_to_string(path).ok()?;
+        let (backup, header_problems) = check_header(&text);
+        let backup = backup.filter(|_| header_problems.is_empty())?;
+        let value = decrypt_value(&mut keys, &backup).ok()?;
+        check_vault(&value).is_empty().then_some((path, backup))
+    });
+
+    match &intact {
+        Some(_) if ff.is_some() && !decrypted => {
+            println!("🩹 The password opens a backup, so the vault file itself is damaged.")
+        }
+        None if ff.is_some() && !decrypted && !backups.is_empty() => {
+            println!("🔑 No backup opens with this password either; it was most likely mistyped.")
+        }
+        None if ff.is_some() && !decrypted => {
+            println!("🔑 Either the password is wrong or the ciphertext is damaged; there is no backup to compare with.")
+        }
+        _ => {}
+    }
+
+    match intact {
+        Some((path, backup)) => {
+            println!("💾 Newest intact backup: {} ({})", path.display(), backup_time(path));
+            if restore {
+                save_fileformat(file, &backup, &Change::vault("restore backup"))?;
+                println!("⏪ Vault restored from the backup; the damaged file was kept as a backup.");
+                problems = 0;
+            } else {
+                println!("Restore it with: rust-passmgr verify --file {:?} --restore", file);
+            }
+        }
+        None if !backups.is_empty() && decrypted => {
+            println!("💾 None of the {} backups is intact either.", backups.len())
+        }
+        None => {}
+    }
+
+    Ok(problems)
+}
+
+/// Verifies a directory vault and returns the number of problems found.
+fn verify_directory(root: &Path) -> Result<usize> {
+    perms::check(root)?;
+    println!("🔍 Checking {:?}", root);
+
+    let manifest_path = dirvault::manifest_path(root);
+    let text = fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read file: {}", manifest_path.display()))?;
+    let (manifest, header_problems) = check_header(&text);
+    report("manifest", &header_problems);
+    let mut problems = header_problems.len();
+
+    let entries = dirvault::list_entries(root)?;
+    let mut blobs = Vec::new();
+    for (key, path) in entries {
+        let mut entry_problems = Vec::new();
+        if let Err(e) = dirvault::entry_path(root, &key) {
+            entry_problems.push(e.to_string());
+        }
+        let blob = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        entry_problems.extend(check_blob(&blob));
+        report(&format!("entry '{key}'"), &entry_problems);
+        problems += entry_problems.len();
+        if entry_problems.is_empty() {
+            blobs.push((key, blob));
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        println!("💾 Without a readable manifest the entries cannot be decrypted; restore it from git.");
+        return Ok(problems);
+    };
+
+    let master = rpassword::prompt_password("Master password: ")?;
+    let mut keys = KeyCache::new(master);
+    if let Err(e) = decrypt_value(&mut keys, &manifest) {
+        println!("❌ Decryption of the manifest failed: {e:#}");
+        return Ok(problems + 1);
+    }
+
+    let salt = general_purpose::STANDARD.decode(&manifest.salt)?;
+    let key = keys.key(&salt)?;
+    let mut opened = 0;
+    for (name, blob) in blobs {
+        let decrypted = general_purpose::STANDARD
+            .decode(blob.trim())
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| decrypt_json_with_key::<Value>(&bytes, &key));
+        match decrypted {
+            Ok(value) => {
+                let entry_problems = check_entry(&name, &value);
+                report(&format!("entry '{name}'"), &entry_problems);
+                problems += entry_problems.len();
+                opened += 1;
+            }
+            Err(e) => {
+                println!("❌ Entry '{}' cannot be decrypted: {e:#}", name);
+                problems += 1;
+            }
+        }
+    }
+    println!("🔓 Decrypted {} entr{}.", opened, if opened == 1 { "y" } else { "ies" });
+    if problems > 0 {
+        println!("💾 Directory vaults have no backups; restore damaged files from git history.");
+    }
+
+    Ok(problems)
+}
+
+/// Decrypts the blob of `ff` into untyped JSON, so the schema can be checked separately.
+fn decrypt_value(keys: &mut KeyCache, ff: &FileFormat) -> Result<Value> {
+    let salt = general_purpose::STANDARD.decode(&ff.salt)?;
+    let blob = general_purpose::STANDARD.decode(&ff.blob)?;
+    let key = keys.key(&salt)?;
+    decrypt_json_with_key(&blob, &key)
+}
+
+/// Prints the problems found in one part of the vault.
+fn report(part: &str, problems: &[String]) {
+    for problem in problems {
+        println!("❌ {}: {}", part, problem);
+    }
+}
+
+/// Formats the creation time encoded in a backup file name.
+fn backup_time(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.parse::<i64>().ok())
+        .and_then(|millis| Local.timestamp_millis_opt(millis).single())
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "unknown time".to_string())
+}
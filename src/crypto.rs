@@ -10,7 +10,8 @@ use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
-use serde_json;
+
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::model::Vault;
 
@@ -78,23 +79,34 @@ pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
 ///
 /// # Arguments
 /// * `vault` — reference to the vault structure.
-/// * `password` — password to derive the key from.
-/// * `salt` — random salt used for key derivation.
+/// * `key` — key derived from the master password with [`derive_key`].
 ///
 /// # Returns
 /// A vector containing the nonce and ciphertext.
-pub fn encrypt_vault(vault: &Vault, password: &str, salt: &[u8]) -> Result<Vec<u8>> {
-    let key = derive_key(password, salt)?;
-    let cipher = Aes256Gcm::new_from_slice(&key)
+pub fn encrypt_vault_with_key(vault: &Vault, key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    encrypt_json_with_key(vault, key)
+}
+
+/// Encrypts any serializable value as JSON with an already derived key.
+///
+/// Uses the same `[ nonce | ciphertext ]` format as [`encrypt_vault_with_key`];
+/// the directory vault layout uses it to encrypt single entries.
+pub fn encrypt_json_with_key<T: Serialize>(value: &T, key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    encrypt_bytes_with_key(&serde_json::to_vec(value)?, key)
+}
+
+/// Encrypts raw bytes with an already derived key, in the same
+/// `[ nonce | ciphertext ]` format; used for attachment blobs.
+pub fn encrypt_bytes_with_key(pt: &[u8], key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key)
         .map_err(|e| anyhow!("invalid key for AES-GCM: {e}"))?;
 
-    let pt = serde_json::to_vec(vault)?;
     let mut nonce_bytes = [0u8; NONCE_LEN];
     OsRng.fill_bytes(&mut nonce_bytes);
     let nonce = Nonce::from_slice(&nonce_bytes);
 
     let ct = cipher
-        .encrypt(nonce, pt.as_ref())
+        .encrypt(nonce, pt)
         .map_err(|e| anyhow!("encrypt failed: {e}"))?;
 
     let mut out = Vec::with_capacity(NONCE_LEN + ct.len());
@@ -110,25 +122,38 @@ pub fn encrypt_vault(vault: &Vault, password: &str, salt: &[u8]) -> Result<Vec<u
 /// [ nonce (12 bytes) | ciphertext... ]
 /// ```
 ///
+/// Entries saved before entries had ids are given one
+/// (see [`Vault::assign_missing_ids`]).
+///
 /// # Errors
-/// Returns an error if the password is incorrect,
+/// Returns an error if the key (i.e. the password) is incorrect,
 /// the file is corrupted, or the JSON cannot be deserialized.
-pub fn decrypt_vault(blob: &[u8], password: &str, salt: &[u8]) -> Result<Vault> {
+pub fn decrypt_vault_with_key(blob: &[u8], key: &[u8; KEY_LEN]) -> Result<Vault> {
+    let mut vault: Vault = decrypt_json_with_key(blob, key)?;
+    vault.assign_missing_ids();
+    Ok(vault)
+}
+
+/// Decrypts a blob made by [`encrypt_json_with_key`] back into a value.
+pub fn decrypt_json_with_key<T: DeserializeOwned>(blob: &[u8], key: &[u8; KEY_LEN]) -> Result<T> {
+    let pt = decrypt_bytes_with_key(blob, key)?;
+    let value: T = serde_json::from_slice(&pt)?;
+    Ok(value)
+}
+
+/// Decrypts a blob made by [`encrypt_bytes_with_key`].
+pub fn decrypt_bytes_with_key(blob: &[u8], key: &[u8; KEY_LEN]) -> Result<Vec<u8>> {
     if blob.len() < NONCE_LEN {
         return Err(anyhow!("blob too short"));
     }
 
     let (nonce_bytes, ct) = blob.split_at(NONCE_LEN);
-    let key = derive_key(password, salt)?;
-    let cipher = Aes256Gcm::new_from_slice(&key)
+    let cipher = Aes256Gcm::new_from_slice(key)
         .map_err(|e| anyhow!("invalid key for AES-GCM: {e}"))?;
 
-    let pt = cipher
+    cipher
         .decrypt(Nonce::from_slice(nonce_bytes), ct)
-        .map_err(|e| anyhow!("decryption failed (bad password or corrupted file): {e}"))?;
-
-    let vault: Vault = serde_json::from_slice(&pt)?;
-    Ok(vault)
+        .map_err(|e| anyhow!("decryption failed (bad password or corrupted file): {e}"))
 }
 
 #[cfg(test)]
@@ -142,8 +167,9 @@ mod tests {
         let vault = Vault::default();
         let salt = generate_salt();
         let password = "secret";
-        let enc = encrypt_vault(&vault, password, &salt).unwrap();
-        let dec = decrypt_vault(&enc, password, &salt).unwrap();
+        let key = derive_key(password, &salt).unwrap();
+        let enc = encrypt_vault_with_key(&vault, &key).unwrap();
+        let dec = decrypt_vault_with_key(&enc, &key).unwrap();
         assert_eq!(vault, dec);
     }
 }
@@ -0,0 +1,274 @@
+//! Directory-per-entry vault layout.
+//!
+//! Instead of one `vault.json` blob, the vault is a directory in which
+//! every [`Entry`] is its own encrypted file, at a path taken from its key
+//! (like `pass` does with its password store):
+//!
+//! ```text
+//! vault/
+//! ├── .passmgr.json          manifest: salt + encrypted vault metadata
+//! ├── example.com.enc
+//! └── work/
+//!     └── github.com.enc     entry "work/github.com"
+//! ```
+//!
+//! The manifest is a [`FileFormat`] whose blob holds the [`Vault`] without
+//! its entries. Every file is encrypted with the key derived from the
+//! manifest's salt, and only files whose contents changed are rewritten, so
+//! git diffs and merges work per entry. Entry keys are visible as file names,
+//! so new vault directories are created with mode `0700`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use crate::{
+    crypto::{decrypt_json_with_key, encrypt_json_with_key, KEY_LEN},
+    model::{Entry, FileFormat, Vault},
+    perms,
+    storage::load_fileformat,
+};
+
+/// Name of the manifest file inside the vault directory.
+pub const MANIFEST: &str = ".passmgr.json";
+
+/// Extension of encrypted entry files.
+pub const ENTRY_EXT: &str = "enc";
+
+/// Returns the manifest path of the vault directory `root`.
+pub fn manifest_path(root: &Path) -> PathBuf {
+    root.join(MANIFEST)
+}
+
+/// Loads the manifest of the vault directory `root`.
+pub fn load_manifest(root: &Path) -> Result<FileFormat> {
+    let path = manifest_path(root);
+    load_fileformat(&path)?
+        .ok_or_else(|| anyhow!("{} is not a vault directory (no {MANIFEST})", root.display()))
+}
+
+/// Returns the file holding the entry `key`, e.g. `work/github.com.enc`.
+///
+/// Keys are split on `/` into directories. Empty components, `.` and `..`,
+/// components starting with a dot and backslashes are rejected, so entries
+/// always stay inside the vault directory and never clash with the manifest.
+pub fn entry_path(root: &Path, key: &str) -> Result<PathBuf> {
+    let valid = !key.is_empty()
+        && !key.contains('\\')
+        && !key.contains('\0')
+        && key.split('/').all(|c| !c.is_empty() && !c.starts_with('.'));
+    if !valid {
+        return Err(anyhow!(
+            "entry key {key:?} cannot be stored as a file (use '/'-separated names without empty or dot-prefixed parts)"
+        ));
+    }
+    Ok(root.join(format!("{key}.{ENTRY_EXT}")))
+}
+
+/// Lists the entry files below `root` together with their keys.
+pub fn list_entries(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    entry_files(root)?
+        .into_iter()
+        .map(|file| Ok((entry_key(root, &file)?, file)))
+        .collect()
+}
+
+/// Reads and decrypts every entry file below `root`.
+pub fn read_entries(root: &Path, key: &[u8; KEY_LEN]) -> Result<BTreeMap<String, Entry>> {
+    let mut entries = BTreeMap::new();
+    for (name, file) in list_entries(root)? {
+        let entry = read_file(&file, key)
+            .with_context(|| format!("Failed to read entry {}", file.display()))?;
+        entries.insert(name, entry);
+    }
+    Ok(entries)
+}
+
+/// Creates a new vault directory at `root` holding `vault`.
+///
+/// `header` supplies the format version and salt for the manifest;
+/// `key` must be the key derived from that salt.
+pub fn create(root: &Path, header: &FileFormat, key: &[u8; KEY_LEN], vault: &Vault) -> Result<()> {
+    perms::create_dir_all(root)?;
+    write(root, header, key, &Vault::default(), vault, true)
+}
+
+/// Writes the changes between `before` and `after` to the vault directory.
+///
+/// Entries that are new or changed are re-encrypted, removed entries are
+/// deleted (together with directories left empty), and the manifest is only
+/// rewritten when the vault metadata changed or `force_manifest` is set.
+/// All paths are checked before anything is written.
+pub fn write(
+    root: &Path,
+    header: &FileFormat,
+    key: &[u8; KEY_LEN],
+    before: &Vault,
+    after: &Vault,
+    force_manifest: bool,
+) -> Result<()> {
+    let changed: Vec<(PathBuf, &Entry)> = after
+        .entries
+        .iter()
+        .filter(|(k, e)| before.entries.get(*k) != Some(*e))
+        .map(|(k, e)| Ok((entry_path(root, k)?, e)))
+        .collect::<Result<_>>()?;
+    let removed: Vec<PathBuf> = before
+        .entries
+        .keys()
+        .filter(|k| !after.entries.contains_key(*k))
+        .map(|k| entry_path(root, k))
+        .collect::<Result<_>>()?;
+
+    let metadata = without_entries(after);
+    if force_manifest || metadata != without_entries(before) {
+        let manifest = FileFormat {
+            version: header.version,
+            salt: header.salt.clone(),
+            blob: general_purpose::STANDARD.encode(encrypt_json_with_key(&metadata, key)?),
+            parents: Vec::new(),
+        };
+        let serialized = serde_json::to_string_pretty(&manifest)
+            .with_context(|| "Failed to serialize FileFormat to JSON")?;
+        write_synced(&manifest_path(root), serialized.as_bytes())?;
+    }
+
+    for (path, entry) in changed {
+        if let Some(dir) = path.parent() {
+            perms::create_dir_all(dir)?;
+        }
+        let blob = general_purpose::STANDARD.encode(encrypt_json_with_key(entry, key)?);
+        write_synced(&path, format!("{blob}\n").as_bytes())?;
+    }
+
+    for path in removed {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove entry file: {}", path.display()))?;
+        prune_empty_dirs(root, &path);
+    }
+
+    Ok(())
+}
+
+/// Returns the metadata part of a vault: everything except its entries.
+fn without_entries(vault: &Vault) -> Vault {
+    let mut metadata = vault.clone();
+    metadata.entries.clear();
+    metadata
+}
+
+/// Decrypts a single entry file.
+fn read_file(path: &Path, key: &[u8; KEY_LEN]) -> Result<Entry> {
+    let text = fs::read_to_string(path)?;
+    let blob = general_purpose::STANDARD.decode(text.trim())?;
+    decrypt_json_with_key(&blob, key)
+}
+
+/// Writes a file and flushes it to disk.
+fn write_synced(path: &Path, data: &[u8]) -> Result<()> {
+    let mut f = perms::create_file(path)?;
+    f.write_all(data)
+        .with_context(|| format!("Failed to write data to file: {}", path.display()))?;
+    f.sync_all()
+        .with_context(|| format!("Failed to sync file to disk: {}", path.display()))
+}
+
+/// Lists all entry files below `root`, skipping dot-files and dot-directories
+/// (the manifest, `.git`, ...).
+fn entry_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let listing = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+        for item in listing {
+            let path = item?.path();
+            if path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')) {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|ext| ext == ENTRY_EXT) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Turns an entry file path back into its key (`work/github.com.enc` → `work/github.com`).
+fn entry_key(root: &Path, file: &Path) -> Result<String> {
+    let relative = file.strip_prefix(root)?.with_extension("");
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(s) => Ok(s.to_string_lossy().into_owned()),
+            _ => Err(anyhow!("unexpected entry path: {}", file.display())),
+        })
+        .collect::<Result<_>>()?;
+    Ok(parts.join("/"))
+}
+
+/// Removes directories between `file` and `root` that became empty.
+fn prune_empty_dirs(root: &Path, file: &Path) {
+    let mut dir = file.parent();
+    while let Some(d) = dir {
+        if d == root || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use crate::{
+        storage::{open_vault, Layout, OpenVault},
+        vcs::Change,
+    };
+
+    /// Keys map onto nested files and back.
+    #[test]
+    fn maps_keys_to_paths() {
+        let root = Path::new("vault");
+        let path = entry_path(root, "work/github.com").unwrap();
+        assert_eq!(path, Path::new("vault/work/github.com.enc"));
+        assert_eq!(entry_key(root, &path).unwrap(), "work/github.com");
+
+        for bad in ["", "../x", "a//b", "/abs", ".passmgr", "a/.git", "a\\b"] {
+            assert!(entry_path(root, bad).is_err(), "{bad:?} should be rejected");
+        }
+    }
+
+    /// A saved directory vault loads back as it was, and entries removed
+    /// before a save lose their files and emptied directories.
+    #[test]
+    fn saves_and_loads_a_directory_vault() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("vault");
+        let mut open = OpenVault::create(&root, Layout::Directory, "master").unwrap();
+        open.vault.entries.insert("mail".into(), Entry::new("alice".into(), "pw1".into(), None));
+        open.vault.entries.insert("work/github.com".into(), Entry::new("alice".into(), "pw2".into(), None));
+        open.vault.settings.attachment_inline_limit = 1024;
+        open.save(&Change::vault("add entries")).unwrap();
+        assert!(root.join("work/github.com.enc").is_file());
+
+        let mut loaded = open_vault(&root).unwrap().unwrap().unlock("master").unwrap();
+        assert_eq!(loaded.vault.entries, open.vault.entries);
+        assert_eq!(loaded.vault.settings, open.vault.settings);
+
+        loaded.vault.entries.remove("work/github.com");
+        loaded.save(&Change::entry("remove", "work/github.com")).unwrap();
+        assert!(!root.join("work/github.com.enc").exists());
+        assert!(!root.join("work").exists());
+        assert!(root.join("mail.enc").is_file());
+
+        let reloaded = open_vault(&root).unwrap().unwrap().unlock("master").unwrap();
+        assert_eq!(reloaded.vault.entries.keys().collect::<Vec<_>>(), ["mail"]);
+        assert_eq!(reloaded.vault.entries["mail"], open.vault.entries["mail"]);
+    }
+}
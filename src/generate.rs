@@ -0,0 +1,608 @@
+//! Random password generation.
+//!
+//! A [`Policy`] says how long a password is, which character classes it
+//! draws from and how many characters of each class it needs at least.
+//! Passwords are drawn from [`OsRng`], the operating system's secure random
+//! number generator.
+//!
+//! Policies can also be written as a compact spec for `add --generate`,
+//! a comma-separated list such as `16,no-symbols` or
+//! `length=24,symbols=!#$%,min-digits=2`; see [`Policy::parse`]. Entries
+//! and vaults store policies in this form. Commonly needed policies ship
+//! under a name ([`BUILTIN_POLICIES`]), and users can name their own in the
+//! vault; [`lookup`] finds either.
+//!
+//! A [`Passphrase`] instead strings together words picked at random from a
+//! wordlist (diceware): the EFF large wordlist, embedded in builds with the
+//! `bundled-wordlist` feature ([`BUNDLED_WORDLIST`]) and otherwise read from
+//! [`installed_wordlist`], where it is downloaded the first time it is
+//! needed, plus any extra lists (see [`load_wordlists`]).
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{model::Vault, perms};
+
+/// Symbols used when a policy does not name its own.
+pub const DEFAULT_SYMBOLS: &str = "!@#$%^&*()-_=+[]{}<>?/~;:.";
+
+/// Characters that are easily confused with each other in many fonts.
+pub const AMBIGUOUS: &str = "0Oo1lI|`'\"";
+
+/// Policies available by name in every vault: name, spec and description.
+pub const BUILTIN_POLICIES: &[(&str, &str, &str)] = &[
+    ("default", "20", "20 characters from all classes"),
+    ("strong", "32,min-upper=2,min-digits=2,min-symbols=2", "32 characters, at least 2 of each class but lowercase"),
+    ("alphanumeric", "20,no-symbols", "letters and digits only"),
+    ("legacy", "16,symbols=!#$%&*", "16 characters with only common symbols, for sites with old rules"),
+    ("readable", "16,no-symbols,no-ambiguous", "easy to read out or type in: no symbols or look-alikes"),
+    ("pin", "6,no-lower,no-upper,no-symbols", "a 6-digit PIN"),
+];
+
+/// How to generate a password.
+///
+/// Each class is either left out (`None`) or used with a minimum number of
+/// characters (`Some(n)`, where `n` may be `0`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    /// Number of characters.
+    pub length: u32,
+    /// `a`–`z`
+    pub lowercase: Option<u32>,
+    /// `A`–`Z`
+    pub uppercase: Option<u32>,
+    /// `0`–`9`
+    pub digits: Option<u32>,
+    /// The characters of [`Policy::symbol_set`].
+    pub symbols: Option<u32>,
+    /// The symbols to draw from.
+    pub symbol_set: String,
+    /// Leave out the characters in [`AMBIGUOUS`].
+    pub exclude_ambiguous: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            length: 20,
+            lowercase: Some(1),
+            uppercase: Some(1),
+            digits: Some(1),
+            symbols: Some(1),
+            symbol_set: DEFAULT_SYMBOLS.to_string(),
+            exclude_ambiguous: false,
+        }
+    }
+}
+
+impl Policy {
+    /// Parses a policy spec: comma-separated items applied to the default
+    /// policy, in order.
+    ///
+    /// - `N` or `length=N` — the length
+    /// - `lower`, `upper`, `digits`, `symbols` — use a class (at least one
+    ///   character of it); `no-lower`, `no-upper`, `no-digits`,
+    ///   `no-symbols` — leave it out
+    /// - `min-lower=N`, `min-upper=N`, `min-digits=N`, `min-symbols=N` —
+    ///   at least `N` characters of a class (which turns it on)
+    /// - `symbols=SET` — draw symbols from `SET` (which cannot contain commas)
+    /// - `no-ambiguous` — leave out look-alike characters
+    ///
+    /// # Errors
+    /// Returns an error for unknown items or numbers that do not parse, and
+    /// for policies that cannot produce a password (see [`Policy::validate`]).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut policy = Policy::default();
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let number = |s: &str| s.parse::<u32>().map_err(|_| anyhow!("{s:?} in policy item {item:?} is not a number"));
+            match item.split_once('=') {
+                None if item.chars().all(|c| c.is_ascii_digit()) => policy.length = number(item)?,
+                None => match item {
+                    "lower" => policy.lowercase = policy.lowercase.or(Some(1)),
+                    "upper" => policy.uppercase = policy.uppercase.or(Some(1)),
+                    "digits" => policy.digits = policy.digits.or(Some(1)),
+                    "symbols" => policy.symbols = policy.symbols.or(Some(1)),
+                    "no-lower" => policy.lowercase = None,
+                    "no-upper" => policy.uppercase = None,
+                    "no-digits" => policy.digits = None,
+                    "no-symbols" => policy.symbols = None,
+                    "no-ambiguous" => policy.exclude_ambiguous = true,
+                    _ => return Err(anyhow!("unknown policy item {item:?}")),
+                },
+                Some(("length", n)) => policy.length = number(n)?,
+                Some(("min-lower", n)) => policy.lowercase = Some(number(n)?),
+                Some(("min-upper", n)) => policy.uppercase = Some(number(n)?),
+                Some(("min-digits", n)) => policy.digits = Some(number(n)?),
+                Some(("min-symbols", n)) => policy.symbols = Some(number(n)?),
+                Some(("symbols", set)) => {
+                    policy.symbol_set = set.to_string();
+                    policy.symbols = policy.symbols.or(Some(1));
+                }
+                Some(_) => return Err(anyhow!("unknown policy item {item:?}")),
+            }
+        }
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// The character classes in use, with their minimum counts and characters.
+    fn classes(&self) -> Vec<(u32, Vec<char>)> {
+        [
+            (self.lowercase, "abcdefghijklmnopqrstuvwxyz"),
+            (self.uppercase, "ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
+            (self.digits, "0123456789"),
+            (self.symbols, self.symbol_set.as_str()),
+        ]
+        .into_iter()
+        .filter_map(|(min, chars)| {
+            let mut chars: Vec<char> = chars
+                .chars()
+                .filter(|c| !(self.exclude_ambiguous && AMBIGUOUS.contains(*c)))
+                .collect();
+            chars.sort_unstable();
+            chars.dedup();
+            min.map(|min| (min, chars))
+        })
+        .collect()
+    }
+
+    /// All characters a password may contain, without duplicates.
+    pub fn alphabet(&self) -> Vec<char> {
+        let mut chars: Vec<char> = self.classes().into_iter().flat_map(|(_, c)| c).collect();
+        chars.sort_unstable();
+        chars.dedup();
+        chars
+    }
+
+    /// Checks that the policy can produce a password.
+    ///
+    /// # Errors
+    /// Returns an error if the length is zero, no class is used, a used
+    /// class has no characters left, the symbols contain whitespace, or the
+    /// minimum counts add up to more than the length.
+    pub fn validate(&self) -> Result<()> {
+        if self.length == 0 {
+            return Err(anyhow!("the password length must be at least 1"));
+        }
+        if self.symbol_set.chars().any(char::is_whitespace) {
+            return Err(anyhow!("symbols cannot contain whitespace"));
+        }
+        let classes = self.classes();
+        if classes.is_empty() {
+            return Err(anyhow!("the policy leaves out every character class"));
+        }
+        if classes.iter().any(|(_, chars)| chars.is_empty()) {
+            return Err(anyhow!("a character class of the policy has no characters left"));
+        }
+        let required: u32 = classes.iter().map(|(min, _)| min).sum();
+        if required > self.length {
+            return Err(anyhow!(
+                "the minimum counts need {required} characters, but the length is {}",
+                self.length
+            ));
+        }
+        Ok(())
+    }
+
+    /// Generates a password following the policy.
+    ///
+    /// The required characters of each class are drawn first, the rest from
+    /// the whole alphabet, and the result is shuffled.
+    ///
+    /// # Errors
+    /// Returns an error if the policy is invalid (see [`Policy::validate`]).
+    pub fn generate(&self) -> Result<String> {
+        self.validate()?;
+        let alphabet = self.alphabet();
+        let mut chars = Vec::with_capacity(self.length as usize);
+        for (min, class) in self.classes() {
+            for _ in 0..min {
+                chars.push(*class.choose(&mut OsRng).expect("classes are not empty"));
+            }
+        }
+        while chars.len() < self.length as usize {
+            chars.push(*alphabet.choose(&mut OsRng).expect("the alphabet is not empty"));
+        }
+        chars.shuffle(&mut OsRng);
+        Ok(chars.into_iter().collect())
+    }
+
+    /// Estimates the entropy of the generated passwords in bits, as if
+    /// every character were drawn from the whole alphabet.
+    pub fn entropy_bits(&self) -> f64 {
+        self.length as f64 * (self.alphabet().len() as f64).log2()
+    }
+}
+
+/// Finds the policy called `name` (user-defined in `vault` first, then
+/// built in), or else reads `name` as a policy spec.
+///
+/// # Errors
+/// Returns an error if `name` is neither a known policy nor a valid spec.
+pub fn lookup(vault: &Vault, name: &str) -> Result<Policy> {
+    if let Some(policy) = vault.policies.get(name) {
+        return Ok(policy.clone());
+    }
+    if let Some((_, spec, _)) = BUILTIN_POLICIES.iter().find(|(n, _, _)| *n == name) {
+        return Policy::parse(spec);
+    }
+    Policy::parse(name).map_err(|e| anyhow!("{name:?} is neither a known policy nor a valid policy spec ({e})"))
+}
+
+/// Checks that `name` can name a user-defined policy: it must not be taken
+/// by a built-in policy or be readable as a spec.
+///
+/// # Errors
+/// Returns an error describing why the name cannot be used.
+pub fn check_policy_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains([',', '=']) || name.chars().any(char::is_whitespace) {
+        return Err(anyhow!("policy names cannot be empty or contain commas, '=' or spaces"));
+    }
+    if BUILTIN_POLICIES.iter().any(|(n, _, _)| *n == name) {
+        return Err(anyhow!("'{name}' is a built-in policy"));
+    }
+    if Policy::parse(name).is_ok() {
+        return Err(anyhow!("'{name}' reads as a policy spec; pick another name"));
+    }
+    Ok(())
+}
+
+impl Serialize for Policy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Policy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        Policy::parse(&spec).map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for Policy {
+    /// Writes the policy as a spec that [`Policy::parse`] reads back,
+    /// leaving out what matches the default policy.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = vec![self.length.to_string()];
+        for (name, class) in [
+            ("lower", self.lowercase),
+            ("upper", self.uppercase),
+            ("digits", self.digits),
+            ("symbols", self.symbols),
+        ] {
+            match class {
+                None => items.push(format!("no-{name}")),
+                Some(1) => {}
+                Some(n) => items.push(format!("min-{name}={n}")),
+            }
+        }
+        if self.symbols.is_some() && self.symbol_set != DEFAULT_SYMBOLS {
+            items.push(format!("symbols={}", self.symbol_set));
+        }
+        if self.exclude_ambiguous {
+            items.push("no-ambiguous".to_string());
+        }
+        f.write_str(&items.join(","))
+    }
+}
+
+/// File name of the EFF large wordlist in the data directory.
+pub const EFF_WORDLIST_FILE: &str = "eff_large_wordlist.txt";
+
+/// Number of words in the EFF large wordlist: one per roll of five dice.
+pub const EFF_WORDLIST_LEN: usize = 7776;
+
+/// Where the EFF large wordlist can be downloaded.
+pub const EFF_WORDLIST_URL: &str = "https://www.eff.org/files/2016/07/18/eff_large_wordlist.txt";
+
+/// The EFF large wordlist, embedded from `assets/eff_large_wordlist.txt`
+/// by the `bundled-wordlist` feature.
+#[cfg(feature = "bundled-wordlist")]
+pub const BUNDLED_WORDLIST: Option<&str> = Some(include_str!("../assets/eff_large_wordlist.txt"));
+
+/// The EFF large wordlist, embedded from `assets/eff_large_wordlist.txt`
+/// by the `bundled-wordlist` feature.
+#[cfg(not(feature = "bundled-wordlist"))]
+pub const BUNDLED_WORDLIST: Option<&str> = None;
+
+/// How to generate a diceware passphrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passphrase {
+    /// Number of words.
+    pub words: u32,
+    /// Put between the words.
+    pub separator: String,
+    /// Start every word with a capital letter.
+    pub capitalize: bool,
+    /// Number of random digits, each inserted at the end of a random word.
+    pub digits: u32,
+}
+
+impl Default for Passphrase {
+    fn default() -> Self {
+        Passphrase {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+            digits: 0,
+        }
+    }
+}
+
+impl Passphrase {
+    /// Generates a passphrase from the words of `wordlist`.
+    ///
+    /// # Errors
+    /// Returns an error if no words are asked for or the list has fewer than
+    /// two words.
+    pub fn generate(&self, wordlist: &[String]) -> Result<String> {
+        if self.words == 0 {
+            return Err(anyhow!("a passphrase needs at least one word"));
+        }
+        if wordlist.len() < 2 {
+            return Err(anyhow!("the wordlist needs at least two words"));
+        }
+        let mut words: Vec<String> = (0..self.words)
+            .map(|_| {
+                let word = wordlist.choose(&mut OsRng).expect("the wordlist is not empty");
+                if self.capitalize {
+                    capitalize(word)
+                } else {
+                    word.clone()
+                }
+            })
+            .collect();
+        for _ in 0..self.digits {
+            let i = OsRng.gen_range(0..words.len());
+            words[i].push(char::from(b'0' + OsRng.gen_range(0..10u8)));
+        }
+        Ok(words.join(&self.separator))
+    }
+
+    /// Estimates the entropy in bits of passphrases from a list of
+    /// `wordlist_len` words: the words, plus each digit and its position.
+    pub fn entropy_bits(&self, wordlist_len: usize) -> f64 {
+        let words = self.words as f64 * (wordlist_len as f64).log2();
+        let digits = self.digits as f64 * (10f64.log2() + (self.words as f64).log2());
+        words + digits
+    }
+}
+
+/// Upper-cases the first letter of `word`.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Reads the words of a wordlist: one per line, or in the EFF format of
+/// dice rolls followed by the word (`11111\tabacus`). Empty lines and lines
+/// starting with `#` are skipped.
+pub fn parse_wordlist(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let first = parts.next().filter(|w| !w.starts_with('#'))?;
+            match parts.next() {
+                Some(word) if first.chars().all(|c| c.is_ascii_digit()) => Some(word.to_string()),
+                _ => Some(first.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Reads the EFF large wordlist, checking that it has one word for every
+/// roll of five dice, in order (`11111` to `66666`).
+///
+/// # Errors
+/// Returns an error if a roll is missing, out of order or has no word.
+pub fn parse_eff_wordlist(text: &str) -> Result<Vec<String>> {
+    let mut words = Vec::with_capacity(EFF_WORDLIST_LEN);
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    for n in 0..EFF_WORDLIST_LEN {
+        let roll: String = (0..5).rev().map(|i| char::from(b'1' + (n / 6usize.pow(i) % 6) as u8)).collect();
+        let line = lines.next().ok_or_else(|| anyhow!("the EFF wordlist ends before roll {roll}"))?;
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [r, word] if r == roll => words.push(word.to_string()),
+            _ => return Err(anyhow!("the EFF wordlist has {line:?} where roll {roll} belongs")),
+        }
+    }
+    if let Some(line) = lines.next() {
+        return Err(anyhow!("the EFF wordlist has an extra line {line:?}"));
+    }
+    Ok(words)
+}
+
+/// Returns where the EFF large wordlist is kept in builds without
+/// [`BUNDLED_WORDLIST`]: [`EFF_WORDLIST_FILE`] in
+/// `$XDG_DATA_HOME/rust-passmgr`, `~/.local/share/rust-passmgr` or
+/// `%APPDATA%\rust-passmgr` on Windows.
+pub fn installed_wordlist() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local").join("share")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("rust-passmgr").join(EFF_WORDLIST_FILE))
+}
+
+/// Downloads the EFF large wordlist from [`EFF_WORDLIST_URL`] and, once it
+/// has been checked (see [`parse_eff_wordlist`]), saves it to `path`.
+pub fn download_wordlist(path: &Path) -> Result<Vec<String>> {
+    let text = ureq::get(EFF_WORDLIST_URL)
+        .timeout(std::time::Duration::from_secs(10))
+        .call()
+        .map_err(|e| anyhow!("could not download the EFF wordlist: {e}"))?
+        .into_string()
+        .context("could not download the EFF wordlist")?;
+    let words = parse_eff_wordlist(&text)?;
+    if let Some(dir) = path.parent() {
+        perms::create_dir_all(dir)?;
+    }
+    perms::write_file(path, text.as_bytes())?;
+    Ok(words)
+}
+
+/// Loads the EFF large wordlist (bundled, or else installed, or else
+/// downloaded to [`installed_wordlist`]) together with the extra lists in
+/// `$PASSMGR_WORDLIST` and `extra`, without duplicates.
+///
+/// # Errors
+/// Returns an error if an extra list cannot be read, or if there are no
+/// words at all, explaining where to put the EFF large wordlist.
+pub fn load_wordlists(extra: &[PathBuf]) -> Result<Vec<String>> {
+    let mut failure = None;
+    let mut words = match (BUNDLED_WORDLIST, installed_wordlist()) {
+        (Some(text), _) => parse_wordlist(text),
+        (None, Some(path)) if path.is_file() => read_wordlist(&path)?,
+        (None, Some(path)) => match download_wordlist(&path) {
+            Ok(words) => {
+                println!("⬇️  Downloaded the EFF large wordlist to {}.", path.display());
+                words
+            }
+            Err(e) => {
+                failure = Some(e);
+                Vec::new()
+            }
+        },
+        (None, None) => Vec::new(),
+    };
+    let from_env = std::env::var_os("PASSMGR_WORDLIST").map(PathBuf::from);
+    for path in from_env.iter().chain(extra) {
+        words.extend(read_wordlist(path)?);
+    }
+    words.sort_unstable();
+    words.dedup();
+    if words.is_empty() {
+        let place = installed_wordlist().map_or_else(|| EFF_WORDLIST_FILE.to_string(), |p| p.display().to_string());
+        let reason = failure.map(|e| format!(" ({e:#})")).unwrap_or_default();
+        return Err(anyhow!(
+            "no wordlist found{reason}: download the EFF large wordlist from {EFF_WORDLIST_URL} to {place}, or pass --wordlist"
+        ));
+    }
+    Ok(words)
+}
+
+/// Reads the words of one wordlist file.
+fn read_wordlist(path: &Path) -> Result<Vec<String>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read wordlist: {}", path.display()))?;
+    Ok(parse_wordlist(&text))
+}
+
+/// Describes the strength of a password with the given entropy.
+pub fn strength(bits: f64) -> &'static str {
+    match bits {
+        b if b < 40.0 => "weak",
+        b if b < 64.0 => "fair",
+        b if b < 100.0 => "strong",
+        _ => "very strong",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generated passwords have the length and minimum counts of the policy.
+    #[test]
+    fn follows_the_policy() {
+        let policy = Policy::parse("12,no-upper,min-digits=4,symbols=#!,no-ambiguous").unwrap();
+        for _ in 0..50 {
+            let password = policy.generate().unwrap();
+            assert_eq!(password.chars().count(), 12);
+            assert!(password.chars().filter(|c| c.is_ascii_digit()).count() >= 4);
+            assert!(password.chars().any(|c| c == '#' || c == '!'));
+            assert!(password.chars().all(|c| !c.is_ascii_uppercase() && !AMBIGUOUS.contains(c)));
+        }
+        assert_eq!(policy.to_string(), "12,no-upper,min-digits=4,symbols=#!,no-ambiguous");
+        assert_eq!(Policy::parse(&policy.to_string()).unwrap(), policy);
+    }
+
+    /// Impossible policies are rejected, and entropy grows with the alphabet.
+    #[test]
+    fn rejects_impossible_policies() {
+        assert!(Policy::parse("0").is_err());
+        assert!(Policy::parse("no-lower,no-upper,no-digits,no-symbols").is_err());
+        assert!(Policy::parse("4,min-digits=3,min-lower=2").is_err());
+        assert!(Policy::parse("no-upper,no-lower,no-symbols,digits,symbols=").is_err());
+        assert!(Policy::parse("length=ten").is_err());
+        assert!(Policy::parse("bogus").is_err());
+
+        let digits = Policy::parse("10,no-lower,no-upper,no-symbols").unwrap();
+        assert!((digits.entropy_bits() - 10.0 * 10f64.log2()).abs() < 1e-9);
+        assert!(Policy::default().entropy_bits() > digits.entropy_bits());
+        assert_eq!(strength(digits.entropy_bits()), "weak");
+    }
+
+    /// Policies are found by user-defined name, built-in name or spec.
+    #[test]
+    fn looks_up_policies() {
+        for (name, spec, _) in BUILTIN_POLICIES {
+            assert_eq!(Policy::parse(spec).unwrap().to_string(), *spec, "{name}");
+        }
+        let mut vault = Vault::default();
+        vault.policies.insert("bank".into(), Policy::parse("12,symbols=!").unwrap());
+        assert_eq!(lookup(&vault, "bank").unwrap().length, 12);
+        assert_eq!(lookup(&vault, "pin").unwrap().alphabet().len(), 10);
+        assert_eq!(lookup(&vault, "24,no-symbols").unwrap().length, 24);
+        assert!(lookup(&vault, "nope").is_err());
+
+        assert!(check_policy_name("bank").is_ok());
+        assert!(check_policy_name("legacy").is_err());
+        assert!(check_policy_name("no-symbols").is_err());
+        assert!(check_policy_name("a,b").is_err());
+
+        let json = serde_json::to_string(&vault.policies).unwrap();
+        assert_eq!(json, r#"{"bank":"12,symbols=!"}"#);
+    }
+
+    /// Wordlists are read in both formats; passphrases use their words.
+    #[test]
+    fn generates_passphrases() {
+        let words = parse_wordlist("# comment\n11111\tabacus\n11112 abdomen\n\nzebra\n");
+        assert_eq!(words, ["abacus", "abdomen", "zebra"]);
+
+        let options = Passphrase { words: 4, separator: ".".into(), capitalize: true, digits: 2 };
+        let phrase = options.generate(&words).unwrap();
+        let parts: Vec<&str> = phrase.split('.').collect();
+        assert_eq!(parts.len(), 4);
+        assert!(parts.iter().all(|p| p.starts_with(|c: char| c.is_uppercase())));
+        assert_eq!(phrase.chars().filter(char::is_ascii_digit).count(), 2);
+
+        let eff = Passphrase::default().entropy_bits(7776);
+        assert!((eff - 6.0 * 7776f64.log2()).abs() < 1e-9);
+        assert!(Passphrase::default().generate(&words[..1]).is_err());
+    }
+
+    /// The EFF format is checked roll by roll.
+    #[test]
+    fn checks_the_eff_wordlist() {
+        let rolls: Vec<String> = (11111..=66666)
+            .map(|n: u32| n.to_string())
+            .filter(|r| r.chars().all(|c| ('1'..='6').contains(&c)))
+            .collect();
+        let text: String = rolls.iter().map(|r| format!("{r}\tw{r}\n")).collect();
+        let words = parse_eff_wordlist(&text).unwrap();
+        assert_eq!(words.len(), EFF_WORDLIST_LEN);
+        assert_eq!((words[0].as_str(), words[EFF_WORDLIST_LEN - 1].as_str()), ("w11111", "w66666"));
+
+        assert!(parse_eff_wordlist(&text.replacen("11112", "11113", 1)).is_err());
+        assert!(parse_eff_wordlist(&text[..text.len() - 13]).is_err());
+        assert!(parse_eff_wordlist(&format!("{text}66667\textra\n")).is_err());
+    }
+
+    /// The bundled list is the complete EFF large wordlist.
+    #[cfg(feature = "bundled-wordlist")]
+    #[test]
+    fn bundles_the_eff_wordlist() {
+        let words = parse_eff_wordlist(BUNDLED_WORDLIST.unwrap()).unwrap();
+        assert_eq!(words.len(), EFF_WORDLIST_LEN);
+        assert_eq!(parse_wordlist(BUNDLED_WORDLIST.unwrap()), words);
+    }
+}
@@ -0,0 +1,75 @@
+//! Local journal of a password rotation in progress.
+//!
+//! While a rotate hook changes a password on some service, the new password
+//! is not in the vault yet: it only goes there once the hook has succeeded.
+//! So that it is not lost if the command is interrupted in between, it is
+//! kept in a `.rotating` file beside the vault, stored and encrypted like
+//! the usage file (see [`crate::usage`]). The journal is removed once the
+//! rotation has finished either way.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::{
+    crypto::{decrypt_json_with_key, encrypt_json_with_key, KEY_LEN},
+    perms,
+    remote,
+};
+
+/// A rotation whose hook was started but has not finished.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pending {
+    /// Key of the entry being rotated.
+    pub key: String,
+    /// Id of the entry being rotated.
+    pub id: Uuid,
+    /// The new password the hook was given.
+    pub password: String,
+    /// When the hook was started.
+    pub started_at: DateTime<Utc>,
+}
+
+/// Returns the journal file of the vault at `path`.
+pub fn path(vault: &Path) -> PathBuf {
+    if remote::is_remote(vault) {
+        return remote::cache_sidecar(vault, "rotating");
+    }
+    let mut name = vault.file_name().unwrap_or_default().to_os_string();
+    name.push(".rotating");
+    vault.with_file_name(name)
+}
+
+/// Reads the pending rotation of the vault at `path`, if there is one.
+pub fn read(vault: &Path, key: &[u8; KEY_LEN]) -> Result<Option<Pending>> {
+    let file = path(vault);
+    if !file.exists() {
+        return Ok(None);
+    }
+    let blob = fs::read(&file).with_context(|| format!("Failed to read journal: {}", file.display()))?;
+    let pending = decrypt_json_with_key(&blob, key)
+        .with_context(|| format!("Failed to decrypt journal: {}", file.display()))?;
+    Ok(Some(pending))
+}
+
+/// Records `pending` as the rotation in progress.
+pub fn write(vault: &Path, key: &[u8; KEY_LEN], pending: &Pending) -> Result<()> {
+    let file = path(vault);
+    if let Some(dir) = file.parent().filter(|d| !d.as_os_str().is_empty()) {
+        perms::create_dir_all(dir)?;
+    }
+    perms::write_file(&file, &encrypt_json_with_key(pending, key)?)
+}
+
+/// Removes the journal of the vault at `path`; a missing one is fine.
+pub fn clear(vault: &Path) -> Result<()> {
+    let file = path(vault);
+    match fs::remove_file(&file) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove journal: {}", file.display()))
+        }
+        _ => Ok(()),
+    }
+}
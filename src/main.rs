@@ -3,9 +3,23 @@
 //! A minimal offline password manager built in Rust.
 //! Provides a simple CLI for creating, encrypting, and managing password vaults.
 
+mod blobs;
 mod crypto;
+mod dirvault;
+mod generate;
+mod journal;
 mod model;
+mod perms;
+mod merge;
+mod refs;
+mod remote;
+mod search;
+mod sshkey;
 mod storage;
+mod totp;
+mod usage;
+mod vcs;
+mod verify;
 mod commands;
 mod cli;
 
@@ -0,0 +1,460 @@
+//! Entry-level three-way merge of vaults.
+//!
+//! Two diverged copies of a vault are merged entry by entry against a
+//! common ancestor (found through [`crate::model::FileFormat::parents`]).
+//! Entries are matched by their stable [`Entry::id`], so a rename is told
+//! apart from a deletion plus an addition, and a rename on one side combines
+//! with an edit on the other. A change made on only one side is taken as
+//! is, and an entry changed differently on both sides becomes a
+//! [`Conflict`] that the caller resolves. Without an ancestor the merge
+//! degrades to a union of both copies, where every entry present on both
+//! sides with different contents is a conflict.
+//!
+//! Keys stay unique: when two different entries end up with the same key,
+//! the one from the other copy gets a numbered suffix (`github.com (2)`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use clap::ValueEnum;
+use uuid::Uuid;
+use crate::generate::Policy;
+use crate::model::{Entry, Settings, TrashedEntry, Vault};
+
+/// How conflicting entries are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Ask for every conflict.
+    Interactive,
+    /// Keep the local version.
+    Ours,
+    /// Take the other version.
+    Theirs,
+    /// Take the entry modified last (or, for entries without a modification
+    /// time, the version from the most recently modified copy).
+    Newest,
+}
+
+/// Which side of a conflict to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// An entry changed differently on both sides.
+///
+/// Each side holds the entry's key and contents there; `None` means the
+/// entry does not exist on that side (it was deleted or never added).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The entry's stable id.
+    pub id: Uuid,
+    /// The entry's key, for display (the local one if it still exists).
+    pub key: String,
+    pub base: Option<(String, Entry)>,
+    pub ours: Option<(String, Entry)>,
+    pub theirs: Option<(String, Entry)>,
+}
+
+impl Conflict {
+    /// Returns the key and entry kept for the given side.
+    pub fn side(&self, side: Side) -> Option<&(String, Entry)> {
+        match side {
+            Side::Ours => self.ours.as_ref(),
+            Side::Theirs => self.theirs.as_ref(),
+        }
+    }
+
+    /// Returns the side whose entry was modified last, if both sides still
+    /// have the entry and both know their modification time.
+    pub fn newer_side(&self) -> Option<Side> {
+        let ours = self.ours.as_ref()?.1.modified_at?;
+        let theirs = self.theirs.as_ref()?.1.modified_at?;
+        Some(if ours >= theirs { Side::Ours } else { Side::Theirs })
+    }
+}
+
+/// Result of [`three_way`].
+#[derive(Debug, Default)]
+pub struct MergeOutcome {
+    /// Merged vault. Conflicting entries hold the local version until resolved.
+    pub merged: Vault,
+    /// Entries that need a decision.
+    pub conflicts: Vec<Conflict>,
+    /// Number of entries whose change was taken from the other copy.
+    pub from_theirs: usize,
+    /// Entries from the other copy stored under a new key, because their
+    /// key was taken by a different entry: `(wanted key, new key)`.
+    pub renamed: Vec<(String, String)>,
+}
+
+impl MergeOutcome {
+    /// Applies the chosen side of a conflict to the merged vault.
+    pub fn resolve(&mut self, conflict: &Conflict, side: Side) {
+        if let Some((key, _)) = &conflict.ours {
+            self.merged.entries.remove(key);
+        }
+        if let Some((key, entry)) = conflict.side(side) {
+            let free = self.merged.free_key(key, &conflict.id);
+            if free != *key {
+                self.renamed.push((key.clone(), free.clone()));
+            }
+            self.merged.entries.insert(free, entry.clone());
+        }
+    }
+}
+
+/// Merges `ours` and `theirs` entry by entry against an optional common ancestor.
+///
+/// For every entry id: if both sides agree, that version is kept; if only
+/// one side differs from the ancestor, its change (including a deletion or
+/// rename) wins; if both changed, the key and the contents are merged
+/// separately, and only a clash in either is reported as a conflict.
+/// Entries are compared by [`Entry::contents`], so merely using an entry is
+/// not an edit; the merged entry keeps the later `last_used_at` of both sides.
+/// Vault settings and user-defined policies are merged one by one with
+/// [`merge_settings`] and [`merge_policies`].
+/// The trash is merged with [`merge_trash`].
+pub fn three_way(base: Option<&Vault>, ours: &Vault, theirs: &Vault) -> MergeOutcome {
+    let by_id = |vault: &Vault| -> BTreeMap<Uuid, (String, Entry)> {
+        vault.entries.iter().map(|(k, e)| (e.id, (k.clone(), e.clone()))).collect()
+    };
+    let (ours_ids, theirs_ids) = (by_id(ours), by_id(theirs));
+    let base_ids = base.map(by_id).unwrap_or_default();
+    let ids: BTreeSet<&Uuid> = ours_ids.keys().chain(theirs_ids.keys()).collect();
+
+    let mut outcome = MergeOutcome::default();
+    outcome.merged.settings = merge_settings(base.map(|b| &b.settings), &ours.settings, &theirs.settings);
+    outcome.merged.policies = merge_policies(base.map(|b| &b.policies), &ours.policies, &theirs.policies);
+    outcome.merged.trash = merge_trash(base.map_or(&[][..], |b| &b.trash), &ours.trash, &theirs.trash);
+    let mut results = Vec::new();
+    for id in ids {
+        let o = ours_ids.get(id);
+        let t = theirs_ids.get(id);
+        let b = base_ids.get(id);
+        let state = |v: Option<&(String, Entry)>| v.map(|(k, e)| (k.clone(), e.contents()));
+        let last_used = o.and_then(|(_, e)| e.last_used_at).max(t.and_then(|(_, e)| e.last_used_at));
+        let with_last_used = |(k, e): &(String, Entry)| {
+            let mut e = e.clone();
+            e.last_used_at = last_used;
+            (k.clone(), e)
+        };
+
+        let merged = if state(o) == state(t) || state(t) == state(b) {
+            o.map(with_last_used)
+        } else if state(o) == state(b) {
+            outcome.from_theirs += 1;
+            t.map(with_last_used)
+        } else if let (Some(o), Some(t), Some(b)) = (o, t, b) {
+            // Both sides changed: a rename on one side and an edit on the other combine
+            match (pick(&b.0, &o.0, &t.0), pick(&b.1.contents(), &o.1.contents(), &t.1.contents())) {
+                (Some(key), Some(contents)) => {
+                    outcome.from_theirs += 1;
+                    Some(with_last_used(&(key.clone(), contents.clone())))
+                }
+                _ => {
+                    outcome.conflicts.push(conflict(id, Some(b), Some(o), Some(t)));
+                    Some(with_last_used(o))
+                }
+            }
+        } else {
+            outcome.conflicts.push(conflict(id, b, o, t));
+            o.map(with_last_used)
+        };
+
+        if let Some((key, entry)) = merged {
+            let local = o.is_some_and(|(k, _)| *k == key);
+            results.push((local, key, entry));
+        }
+    }
+
+    // Entries under their local key first, so entries from the other copy
+    // yield when two different entries want the same key
+    results.sort_by_key(|(local, _, _)| !local);
+    for (_, key, entry) in results {
+        let free = outcome.merged.free_key(&key, &entry.id);
+        if free != key {
+            outcome.renamed.push((key, free.clone()));
+        }
+        outcome.merged.entries.insert(free, entry);
+    }
+
+    outcome
+}
+
+/// Merges the trash of both sides: everything trashed on either side is
+/// kept, except what was already in the ancestor's trash and has since been
+/// restored or purged on one side. An entry trashed on both sides is kept
+/// once, as it was trashed last.
+fn merge_trash(base: &[TrashedEntry], ours: &[TrashedEntry], theirs: &[TrashedEntry]) -> Vec<TrashedEntry> {
+    let same = |a: &TrashedEntry, b: &TrashedEntry| a.entry.id == b.entry.id && a.deleted_at == b.deleted_at;
+    let holds = |side: &[TrashedEntry], t: &TrashedEntry| side.iter().any(|s| same(s, t));
+    let mut latest: BTreeMap<Uuid, &TrashedEntry> = BTreeMap::new();
+    for t in ours
+        .iter()
+        .chain(theirs.iter().filter(|t| !holds(ours, t)))
+        .filter(|t| !holds(base, t) || (holds(ours, t) && holds(theirs, t)))
+    {
+        let slot = latest.entry(t.entry.id).or_insert(t);
+        if t.deleted_at > slot.deleted_at {
+            *slot = t;
+        }
+    }
+    let mut merged: Vec<TrashedEntry> = latest.into_values().cloned().collect();
+    merged.sort_by_key(|t| t.deleted_at);
+    merged
+}
+
+/// Merges the vault settings of both sides one setting at a time.
+///
+/// A setting changed on one side takes that change; if both sides changed
+/// it differently, ours wins. Without an ancestor, ours are kept.
+fn merge_settings(base: Option<&Settings>, ours: &Settings, theirs: &Settings) -> Settings {
+    let Some(base) = base else {
+        return ours.clone();
+    };
+    let setting = |get: fn(&Settings) -> u64| {
+        let (b, o, t) = (get(base), get(ours), get(theirs));
+        *pick(&b, &o, &t).unwrap_or(&o)
+    };
+    Settings {
+        attachment_inline_limit: setting(|s| s.attachment_inline_limit),
+        attachment_max_size: setting(|s| s.attachment_max_size),
+    }
+}
+
+/// Merges the user-defined policies of both sides name by name.
+///
+/// A policy added, changed or removed on one side takes that change; if
+/// both sides changed it differently, ours wins. Without an ancestor the
+/// result is the union of both sides, again preferring ours.
+fn merge_policies(
+    base: Option<&BTreeMap<String, Policy>>,
+    ours: &BTreeMap<String, Policy>,
+    theirs: &BTreeMap<String, Policy>,
+) -> BTreeMap<String, Policy> {
+    let Some(base) = base else {
+        let mut merged = theirs.clone();
+        merged.extend(ours.iter().map(|(k, v)| (k.clone(), v.clone())));
+        return merged;
+    };
+    let names: BTreeSet<&String> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (o, t) = (ours.get(name), theirs.get(name));
+            let policy = *pick(&base.get(name), &o, &t).unwrap_or(&o);
+            policy.map(|p| (name.clone(), p.clone()))
+        })
+        .collect()
+}
+
+/// Three-way merges a single value: the side that changed wins, and
+/// `None` means both sides changed it differently.
+fn pick<'a, T: PartialEq>(base: &'a T, ours: &'a T, theirs: &'a T) -> Option<&'a T> {
+    if ours == theirs || theirs == base {
+        Some(ours)
+    } else if ours == base {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+fn conflict(
+    id: &Uuid,
+    base: Option<&(String, Entry)>,
+    ours: Option<&(String, Entry)>,
+    theirs: Option<&(String, Entry)>,
+) -> Conflict {
+    let key = ours.or(theirs).map(|(k, _)| k.clone()).unwrap_or_default();
+    Conflict {
+        id: *id,
+        key,
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(login: &str, password: &str) -> Entry {
+        Entry {
+            login: login.to_string(),
+            password: password.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Builds a vault whose entries get ids derived from their keys, like
+    /// a vault saved before ids existed.
+    fn vault(entries: &[(&str, Entry)]) -> Vault {
+        let mut vault = Vault {
+            entries: entries
+                .iter()
+                .map(|(k, e)| (k.to_string(), e.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        vault.assign_missing_ids();
+        vault
+    }
+
+    /// Changes made on different keys are combined without conflicts.
+    #[test]
+    fn combines_independent_changes() {
+        let base = vault(&[("a", entry("a", "1")), ("b", entry("b", "1"))]);
+        let ours = vault(&[("a", entry("a", "2")), ("b", entry("b", "1"))]);
+        let theirs = vault(&[("a", entry("a", "1")), ("b", entry("b", "1")), ("c", entry("c", "1"))]);
+
+        let out = three_way(Some(&base), &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert_eq!(out.from_theirs, 1);
+        assert_eq!(
+            out.merged,
+            vault(&[("a", entry("a", "2")), ("b", entry("b", "1")), ("c", entry("c", "1"))])
+        );
+    }
+
+    /// A deletion on one side wins over an untouched entry on the other.
+    #[test]
+    fn propagates_deletions() {
+        let base = vault(&[("a", entry("a", "1")), ("b", entry("b", "1"))]);
+        let ours = base.clone();
+        let theirs = vault(&[("b", entry("b", "1"))]);
+
+        let out = three_way(Some(&base), &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert!(!out.merged.entries.contains_key("a"));
+    }
+
+    /// Diverging edits of the same key are reported, and resolving applies the chosen side.
+    #[test]
+    fn reports_and_resolves_conflicts() {
+        let base = vault(&[("a", entry("a", "1"))]);
+        let ours = vault(&[("a", entry("a", "2"))]);
+        let theirs = vault(&[]);
+
+        let mut out = three_way(Some(&base), &ours, &theirs);
+        assert_eq!(out.conflicts.len(), 1);
+        assert_eq!(out.merged, ours);
+
+        let conflict = out.conflicts[0].clone();
+        out.resolve(&conflict, Side::Theirs);
+        assert!(out.merged.entries.is_empty());
+    }
+
+    /// Using an entry on one side does not conflict with an edit on the other.
+    #[test]
+    fn usage_is_not_an_edit() {
+        let base = vault(&[("a", entry("a", "1"))]);
+        let mut used = entry("a", "1");
+        used.last_used_at = Some(chrono::Utc::now());
+        let ours = vault(&[("a", used.clone())]);
+        let theirs = vault(&[("a", entry("a", "2"))]);
+
+        let out = three_way(Some(&base), &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert_eq!(out.merged.entries["a"].password, "2");
+        assert_eq!(out.merged.entries["a"].last_used_at, used.last_used_at);
+    }
+
+    /// Without an ancestor, one-sided entries are kept rather than deleted.
+    #[test]
+    fn union_without_ancestor() {
+        let ours = vault(&[("a", entry("a", "1"))]);
+        let theirs = vault(&[("b", entry("b", "1"))]);
+
+        let out = three_way(None, &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert_eq!(out.merged.entries.len(), 2);
+    }
+
+    /// Settings merge one by one; without an ancestor the local ones stay.
+    #[test]
+    fn merges_settings_one_by_one() {
+        let settings = |inline: u64, max: u64| Settings { attachment_inline_limit: inline, attachment_max_size: max };
+        let base = settings(1, 10);
+
+        let merged = merge_settings(Some(&base), &settings(2, 10), &settings(1, 20));
+        assert_eq!(merged, settings(2, 20));
+        let merged = merge_settings(Some(&base), &settings(2, 10), &settings(3, 10));
+        assert_eq!(merged, settings(2, 10));
+        assert_eq!(merge_settings(None, &settings(2, 10), &settings(3, 30)), settings(2, 10));
+    }
+
+    /// Policies merge by name: additions on both sides are kept, and
+    /// without an ancestor the local ones are not replaced.
+    #[test]
+    fn merges_policies_by_name() {
+        let policy = |spec: &str| Policy::parse(spec).unwrap();
+        let map = |items: &[(&str, &str)]| -> BTreeMap<String, Policy> {
+            items.iter().map(|(name, spec)| (name.to_string(), policy(spec))).collect()
+        };
+
+        let base = map(&[("bank", "16"), ("old", "12")]);
+        let ours = map(&[("bank", "18"), ("old", "12"), ("work", "24")]);
+        let theirs = map(&[("bank", "16"), ("home", "20,no-symbols")]);
+        assert_eq!(
+            merge_policies(Some(&base), &ours, &theirs),
+            map(&[("bank", "18"), ("home", "20,no-symbols"), ("work", "24")])
+        );
+
+        let ours = map(&[("bank", "18"), ("work", "24")]);
+        let theirs = map(&[("bank", "16"), ("home", "20")]);
+        assert_eq!(merge_policies(None, &ours, &theirs), map(&[("bank", "18"), ("home", "20"), ("work", "24")]));
+    }
+
+    /// A rename on one side combines with an edit on the other.
+    #[test]
+    fn combines_rename_and_edit() {
+        let base = vault(&[("a", entry("a", "1"))]);
+        let mut ours = base.clone();
+        let renamed = ours.entries.remove("a").unwrap();
+        ours.entries.insert("b".to_string(), renamed);
+        let mut theirs = base.clone();
+        theirs.entries.get_mut("a").unwrap().password = "2".to_string();
+
+        let out = three_way(Some(&base), &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert_eq!(out.merged.entries.len(), 1);
+        assert_eq!(out.merged.entries["b"].password, "2");
+        assert_eq!(out.merged.entries["b"].id, base.entries["a"].id);
+    }
+
+    /// Different entries added under the same key on both sides are both kept.
+    #[test]
+    fn keeps_both_entries_on_key_clash() {
+        let ours = Vault { entries: [("a".to_string(), Entry::new("x".into(), "1".into(), None))].into(), ..Default::default() };
+        let theirs = Vault { entries: [("a".to_string(), Entry::new("y".into(), "2".into(), None))].into(), ..Default::default() };
+
+        let out = three_way(None, &ours, &theirs);
+        assert!(out.conflicts.is_empty());
+        assert_eq!(out.merged.entries["a"].login, "x");
+        assert_eq!(out.merged.entries["a (2)"].login, "y");
+        assert_eq!(out.renamed, vec![("a".to_string(), "a (2)".to_string())]);
+    }
+
+    /// Removals from either side reach the merged trash; restores and purges stick.
+    #[test]
+    fn merges_trash() {
+        let mut base = vault(&[("a", entry("a", "1")), ("b", entry("b", "1")), ("c", entry("c", "1"))]);
+        base.move_to_trash("a");
+        let mut ours = base.clone();
+        ours.move_to_trash("b");
+        let mut theirs = base.clone();
+        theirs.move_to_trash("c");
+        assert_eq!(theirs.purge_trash(None).len(), 2);
+        theirs.move_to_trash("b");
+
+        let out = three_way(Some(&base), &ours, &theirs);
+        let trashed: Vec<&str> = out.merged.trash.iter().map(|t| t.key.as_str()).collect();
+        // "a" was purged by theirs, "c" trashed and purged by theirs, "b" trashed on both sides
+        assert_eq!(trashed, ["b"]);
+        let latest = ours.trash[1].deleted_at.max(theirs.trash[0].deleted_at);
+        assert_eq!(out.merged.trash[0].deleted_at, latest);
+        assert!(out.merged.entries.is_empty());
+    }
+}
@@ -1,34 +1,950 @@
 use serde::{Serialize, Deserialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use uuid::Uuid;
+use crate::generate::Policy;
+use crate::sshkey::{PrivateKey, PublicKey};
+use crate::totp::Totp;
 
 /// Represents a single password entry in the vault.
 ///
-/// Each entry contains a login, password, and optional notes.
-/// The key for each entry is stored separately in the [`Vault`] map.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Each entry contains a login, password, and optional notes, plus the
+/// sites it belongs to, free-form tags and a few timestamps.
+/// The key for each entry is stored separately in the [`Vault`] map; it
+/// is the entry's title and may change, while [`Entry::id`] never does.
+///
+/// Fields added after the first release default to empty, so older
+/// vaults still load; their timestamps are simply unknown.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Entry {
+    /// Stable identifier, kept when the entry is renamed.
+    ///
+    /// Nil only until the vault is loaded: entries written before ids
+    /// existed get one from [`Vault::assign_missing_ids`].
+    #[serde(default, skip_serializing_if = "Uuid::is_nil")]
+    pub id: Uuid,
+    /// What the entry holds (login, card, SSH key, ...); older entries are logins.
+    #[serde(default, skip_serializing_if = "EntryKind::is_login")]
+    pub kind: EntryKind,
     /// Account login or username.
     pub login: String,
     /// Account password (stored in plaintext inside the decrypted vault).
     pub password: String,
     /// Optional notes or description for the entry.
     pub notes: Option<String>,
+    /// Sites the account is used on (e.g. `https://github.com/login`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    /// Free-form labels used to group and filter entries (e.g. `work`).
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// Custom fields (PINs, security questions, recovery codes, ...), in display order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    /// Earlier passwords, newest first, at most [`Entry::HISTORY_LEN`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PasswordChange>,
+    /// Attached files (recovery codes, licenses, ...), in the order they were added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    /// When the entry was created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// When the entry's contents were last changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Utc>>,
+    /// When the entry was last shown with `get`, as recorded by versions
+    /// that stored it in the vault; times of use now go to the local usage
+    /// file instead (see [`crate::usage`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When the credential stops being valid; `get` and `due` warn after that.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Days after which the password should be changed again, counted from
+    /// its last change (see [`Entry::rotation_due_at`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_every: Option<u32>,
+    /// How new passwords for this entry are generated, e.g. to fit a site
+    /// that caps length or symbols; `None` uses the default policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<Policy>,
+    /// Commands `rotate` runs to change the password where it is used.
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+}
+
+impl Entry {
+    /// Number of earlier passwords kept in [`Entry::history`].
+    pub const HISTORY_LEN: usize = 10;
+
+    /// Creates an entry stamped with the current time as creation and modification time.
+    pub fn new(login: String, password: String, notes: Option<String>) -> Self {
+        let now = Utc::now();
+        Entry {
+            id: Uuid::new_v4(),
+            login,
+            password,
+            notes,
+            created_at: Some(now),
+            modified_at: Some(now),
+            ..Default::default()
+        }
+    }
+
+    /// Records that the entry's contents were just changed.
+    pub fn touch(&mut self) {
+        self.modified_at = Some(Utc::now());
+    }
+
+    /// Replaces the password, keeping the old one in the history.
+    ///
+    /// Setting the same password again changes nothing.
+    pub fn set_password(&mut self, password: String) {
+        if password == self.password {
+            return;
+        }
+        let old = std::mem::replace(&mut self.password, password);
+        self.history.insert(0, PasswordChange { password: old, replaced_at: Some(Utc::now()) });
+        self.history.truncate(Self::HISTORY_LEN);
+        self.touch();
+    }
+
+    /// Makes the earlier password at `index` of the history current again,
+    /// keeping the one it replaces in the history.
+    ///
+    /// Returns `false` and changes nothing if that password is the current
+    /// one already. Panics if `index` is out of range.
+    pub fn restore_password(&mut self, index: usize) -> bool {
+        if self.history[index].password == self.password {
+            return false;
+        }
+        let old = self.history.remove(index);
+        self.set_password(old.password);
+        true
+    }
+
+    /// Returns the custom field called `name`.
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// When the current password was set: when the previous one was
+    /// replaced, or else when the entry was created.
+    pub fn password_changed_at(&self) -> Option<DateTime<Utc>> {
+        match self.history.first() {
+            Some(change) => change.replaced_at,
+            None => self.created_at,
+        }
+    }
+
+    /// When the password is due to be rotated under [`Entry::rotate_every`].
+    ///
+    /// Returns `None` if the entry has no rotation policy. If the time of the
+    /// last password change is unknown, rotation is due immediately
+    /// (`Some(DateTime::UNIX_EPOCH)`).
+    pub fn rotation_due_at(&self) -> Option<DateTime<Utc>> {
+        let days = self.rotate_every?;
+        Some(match self.password_changed_at() {
+            Some(changed) => changed + chrono::Duration::days(days.into()),
+            None => DateTime::UNIX_EPOCH,
+        })
+    }
+
+    /// Returns the attachment called `name`.
+    pub fn attachment(&self, name: &str) -> Option<&Attachment> {
+        self.attachments.iter().find(|a| a.name == name)
+    }
+
+    /// Returns the entry without its usage time, for comparing contents.
+    ///
+    /// Looking an entry up used to change `last_used_at`, which should not
+    /// count as an edit when copies of the vault are merged.
+    pub fn contents(&self) -> Entry {
+        Entry {
+            last_used_at: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// The type of an [`Entry`], with the data specific to that type.
+///
+/// The entry's `login` and `password` keep their meaning where the type
+/// has them: an API token, a Wi-Fi passphrase and an SSH key passphrase
+/// are all the entry's password, so history and rotation work for them.
+/// Cards, identities and secure notes leave both empty.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryKind {
+    /// A website or service account.
+    #[default]
+    Login,
+    /// Free text, kept in the entry's notes.
+    SecureNote,
+    /// A payment card.
+    Card(Card),
+    /// Personal details.
+    Identity(Identity),
+    /// An SSH key pair; the password is the key's passphrase.
+    SshKey(SshKey),
+    /// An API token, stored as the password; the login is the key id, if any.
+    ApiToken,
+    /// A Wi-Fi network; the password is the network's passphrase.
+    Wifi(Wifi),
+}
+
+impl EntryKind {
+    /// Whether this is the default login type.
+    pub fn is_login(&self) -> bool {
+        *self == EntryKind::Login
+    }
+
+    /// Whether the entry's password can be generated: logins, API tokens
+    /// and Wi-Fi passphrases.
+    pub fn has_generated_password(&self) -> bool {
+        matches!(self, EntryKind::Login | EntryKind::ApiToken | EntryKind::Wifi(_))
+    }
+
+    /// Returns a human-readable name of the type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryKind::Login => "login",
+            EntryKind::SecureNote => "secure note",
+            EntryKind::Card(_) => "card",
+            EntryKind::Identity(_) => "identity",
+            EntryKind::SshKey(_) => "SSH key",
+            EntryKind::ApiToken => "API token",
+            EntryKind::Wifi(_) => "Wi-Fi network",
+        }
+    }
+
+    /// Checks the type-specific data, together with the entry's password.
+    pub fn validate(&self, password: &str) -> Result<()> {
+        match self {
+            EntryKind::Login | EntryKind::SecureNote | EntryKind::ApiToken => Ok(()),
+            EntryKind::Card(card) => card.validate(),
+            EntryKind::Identity(identity) => identity.validate(),
+            EntryKind::SshKey(key) => key.validate(),
+            EntryKind::Wifi(wifi) => wifi.validate(password),
+        }
+    }
+}
+
+/// The type of entry to create, as chosen on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum EntryType {
+    #[default]
+    Login,
+    Note,
+    Card,
+    Identity,
+    SshKey,
+    ApiToken,
+    Wifi,
+}
+
+impl EntryType {
+    /// Returns the type of an existing entry.
+    pub fn of(kind: &EntryKind) -> Self {
+        match kind {
+            EntryKind::Login => EntryType::Login,
+            EntryKind::SecureNote => EntryType::Note,
+            EntryKind::Card(_) => EntryType::Card,
+            EntryKind::Identity(_) => EntryType::Identity,
+            EntryKind::SshKey(_) => EntryType::SshKey,
+            EntryKind::ApiToken => EntryType::ApiToken,
+            EntryKind::Wifi(_) => EntryType::Wifi,
+        }
+    }
+}
+
+/// A payment card.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Card {
+    /// Name on the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder: Option<String>,
+    /// Card number, digits only.
+    pub number: String,
+    /// Expiry month as `MM/YY`.
+    pub expiry: String,
+    /// Security code from the back of the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cvv: Option<String>,
+}
+
+impl Card {
+    /// Strips spaces and dashes from a card number as typed.
+    pub fn normalize_number(number: &str) -> String {
+        number.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+    }
+
+    /// Turns `M/YY`, `MM/YY` or `MM/YYYY` into `MM/YY`.
+    pub fn normalize_expiry(expiry: &str) -> Result<String> {
+        let invalid = || anyhow!("expiry {expiry:?} is not a month in the form MM/YY");
+        let (month, year) = expiry.trim().split_once('/').ok_or_else(invalid)?;
+        let month: u32 = month.parse().map_err(|_| invalid())?;
+        let year: u32 = match year.len() {
+            2 => year.parse().map_err(|_| invalid())?,
+            4 => year.parse::<u32>().map_err(|_| invalid())? % 100,
+            _ => return Err(invalid()),
+        };
+        if !(1..=12).contains(&month) {
+            return Err(invalid());
+        }
+        Ok(format!("{month:02}/{year:02}"))
+    }
+
+    /// Checks the number (length and Luhn checksum), expiry and security code.
+    pub fn validate(&self) -> Result<()> {
+        let digits: Vec<u32> = self
+            .number
+            .chars()
+            .map(|c| c.to_digit(10).ok_or_else(|| anyhow!("card number may only contain digits")))
+            .collect::<Result<_>>()?;
+        if !(12..=19).contains(&digits.len()) {
+            return Err(anyhow!("card number has {} digits, expected 12 to 19", digits.len()));
+        }
+        if !luhn(&digits) {
+            return Err(anyhow!("card number fails the Luhn check; is there a typo?"));
+        }
+        if Card::normalize_expiry(&self.expiry)? != self.expiry {
+            return Err(anyhow!("expiry {:?} is not in the form MM/YY", self.expiry));
+        }
+        if let Some(cvv) = &self.cvv {
+            if !(3..=4).contains(&cvv.len()) || !cvv.chars().all(|c| c.is_ascii_digit()) {
+                return Err(anyhow!("security code must be 3 or 4 digits"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the card expired before `today` (cards are valid through their expiry month).
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        let Some((month, year)) = self.expiry.split_once('/') else {
+            return false;
+        };
+        let (Ok(month), Ok(year)) = (month.parse::<u32>(), year.parse::<i32>()) else {
+            return false;
+        };
+        (2000 + year, month) < (today.year(), today.month())
+    }
+
+    /// Returns the number with all but the last four digits masked.
+    pub fn masked_number(&self) -> String {
+        let shown = self.number.len().saturating_sub(4);
+        format!("•••• {}", &self.number[shown..])
+    }
+}
+
+/// Checks the Luhn checksum of a card number.
+fn luhn(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Personal details.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Identity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// Date of birth as `YYYY-MM-DD`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<String>,
+}
+
+impl Identity {
+    /// Checks the email address and date of birth.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(email) = &self.email {
+            FieldKind::Email.validate(email)?;
+        }
+        if let Some(date) = &self.birth_date {
+            FieldKind::Date.validate(date)?;
+        }
+        Ok(())
+    }
+}
+
+/// An SSH key pair.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct SshKey {
+    /// The PEM armored private key.
+    pub private_key: String,
+    /// The public key line (`ssh-ed25519 AAAA... comment`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl SshKey {
+    /// Checks that both keys parse and, where the private key records its
+    /// public key, that they belong together.
+    pub fn validate(&self) -> Result<()> {
+        let private = PrivateKey::parse(&self.private_key)?;
+        if let Some(line) = &self.public_key {
+            let public = PublicKey::parse(line)?;
+            if private.public.is_some_and(|p| !p.same_key(&public)) {
+                return Err(anyhow!("the public key does not belong to the private key"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A Wi-Fi network.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Wifi {
+    /// Network name.
+    pub ssid: String,
+    /// Security type.
+    pub security: WifiSecurity,
+    /// Whether the network does not broadcast its name.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+impl Wifi {
+    /// Checks the SSID and that `password` suits the security type.
+    pub fn validate(&self, password: &str) -> Result<()> {
+        if self.ssid.is_empty() || self.ssid.len() > 32 {
+            return Err(anyhow!("SSID must be 1 to 32 bytes long"));
+        }
+        let hex = |len: usize| password.len() == len && password.chars().all(|c| c.is_ascii_hexdigit());
+        let ascii = password.chars().all(|c| c.is_ascii() && !c.is_ascii_control());
+        let ok = match self.security {
+            WifiSecurity::Open => password.is_empty(),
+            WifiSecurity::Wep => (ascii && matches!(password.len(), 5 | 13)) || hex(10) || hex(26),
+            WifiSecurity::Wpa | WifiSecurity::Wpa2 | WifiSecurity::Wpa3 => {
+                (ascii && (8..=63).contains(&password.len())) || hex(64)
+            }
+            WifiSecurity::Enterprise => true,
+        };
+        if !ok {
+            return Err(anyhow!(match self.security {
+                WifiSecurity::Open => "open networks have no password",
+                WifiSecurity::Wep => "WEP keys are 5 or 13 characters, or 10 or 26 hex digits",
+                _ => "WPA passphrases are 8 to 63 printable ASCII characters, or 64 hex digits",
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Security type of a Wi-Fi network.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WifiSecurity {
+    Open,
+    Wep,
+    Wpa,
+    #[default]
+    Wpa2,
+    Wpa3,
+    /// WPA-Enterprise (802.1X); the login is the identity.
+    Enterprise,
+}
+
+impl std::fmt::Display for WifiSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            WifiSecurity::Open => "open",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Wpa2 => "WPA2",
+            WifiSecurity::Wpa3 => "WPA3",
+            WifiSecurity::Enterprise => "WPA-Enterprise",
+        })
+    }
+}
+
+/// A previous password of an [`Entry`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PasswordChange {
+    /// The password that was replaced.
+    pub password: String,
+    /// When it was replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_at: Option<DateTime<Utc>>,
+}
+
+/// The commands `rotate` runs for an [`Entry`], through the shell.
+///
+/// Both get the old and the new password on stdin, one per line, and the
+/// entry's key and login in `PASSMGR_KEY` and `PASSMGR_LOGIN`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Hooks {
+    /// Changes the password on the service (e.g. runs `ALTER USER`); the
+    /// new password is only kept if it exits with 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre: Option<String>,
+    /// Runs once the new password is saved (e.g. restarts a service that
+    /// uses it); if it fails, the new password is kept anyway.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<String>,
+}
+
+impl Hooks {
+    /// Whether no hook is set.
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_none() && self.post.is_none()
+    }
+}
+
+/// A custom field of an [`Entry`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Field {
+    /// Field name, unique within the entry (e.g. `PIN`).
+    pub name: String,
+    /// What the value holds; controls validation and display.
+    #[serde(rename = "type", default)]
+    pub kind: FieldKind,
+    /// The value, stored in plaintext inside the decrypted vault.
+    pub value: String,
+}
+
+/// A file attached to an [`Entry`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    /// File name, unique within the entry (e.g. `recovery-codes.pdf`).
+    pub name: String,
+    /// MIME type, e.g. `application/pdf`.
+    pub mime: String,
+    /// Size of the contents in bytes.
+    pub size: u64,
+    /// Hex SHA-256 of the contents, checked when the file is extracted.
+    pub sha256: String,
+    /// When the file was attached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_at: Option<DateTime<Utc>>,
+    /// Where the contents are kept.
+    pub data: AttachmentData,
+}
+
+/// Where the contents of an [`Attachment`] are kept.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentData {
+    /// Base64 contents stored in the vault itself.
+    Inline(String),
+    /// Contents stored encrypted in the sidecar blob store under this id
+    /// (see [`crate::blobs`]).
+    Blob(Uuid),
+}
+
+impl Attachment {
+    /// Guesses a MIME type from the extension of a file name.
+    pub fn guess_mime(name: &str) -> &'static str {
+        let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+        match ext.as_str() {
+            "pdf" => "application/pdf",
+            "txt" | "text" | "asc" => "text/plain",
+            "md" => "text/markdown",
+            "csv" => "text/csv",
+            "json" => "application/json",
+            "yaml" | "yml" => "application/yaml",
+            "toml" => "application/toml",
+            "xml" => "application/xml",
+            "html" | "htm" => "text/html",
+            "pem" | "crt" | "cer" | "key" => "application/x-pem-file",
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "svg" => "image/svg+xml",
+            "zip" => "application/zip",
+            "gz" | "tgz" => "application/gzip",
+            "tar" => "application/x-tar",
+            _ => "application/octet-stream",
+        }
+    }
+}
+
+/// Vault-wide settings, changed with the `config` command.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Settings {
+    /// Attachments up to this many bytes are stored inside the vault;
+    /// larger ones go to the sidecar blob store.
+    pub attachment_inline_limit: u64,
+    /// Largest attachment accepted, in bytes.
+    pub attachment_max_size: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            attachment_inline_limit: 64 * 1024,
+            attachment_max_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl Settings {
+    /// Names of the settings, as used by `config`.
+    pub const NAMES: &'static [&'static str] = &["attachment-inline-limit", "attachment-max-size"];
+
+    /// Whether every setting has its default value.
+    pub fn is_default(&self) -> bool {
+        *self == Settings::default()
+    }
+
+    /// Returns the value of the setting called `name`, formatted for display.
+    pub fn get(&self, name: &str) -> Result<String> {
+        match name {
+            "attachment-inline-limit" => Ok(format_size(self.attachment_inline_limit)),
+            "attachment-max-size" => Ok(format_size(self.attachment_max_size)),
+            _ => Err(anyhow!("unknown setting '{name}' (known: {})", Self::NAMES.join(", "))),
+        }
+    }
+
+    /// Changes the setting called `name`.
+    ///
+    /// # Errors
+    /// Returns an error for unknown names and values that do not parse.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        match name {
+            "attachment-inline-limit" => self.attachment_inline_limit = parse_size(value)?,
+            "attachment-max-size" => self.attachment_max_size = parse_size(value)?,
+            _ => return Err(anyhow!("unknown setting '{name}' (known: {})", Self::NAMES.join(", "))),
+        }
+        Ok(())
+    }
+}
+
+/// Parses a size such as `512`, `64K`, `10M` or `1G` (powers of 1024) into bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let upper = s.to_ascii_uppercase();
+    let digits = upper.trim_end_matches('B').trim_end_matches('I');
+    let (number, unit) = match digits.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&digits[..i], c),
+        _ => (digits, ' '),
+    };
+    let factor: u64 = match unit {
+        ' ' => 1,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        _ => return Err(anyhow!("{s:?} is not a size (use e.g. 512, 64K, 10M or 1G)")),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .ok_or_else(|| anyhow!("{s:?} is not a size (use e.g. 512, 64K, 10M or 1G)"))
+}
+
+/// Parses a number of days such as `90`, `90d`, `12w` or `1y` (365 days).
+pub fn parse_days(s: &str) -> Result<u32> {
+    let s = s.trim();
+    let lower = s.to_ascii_lowercase();
+    let (number, factor) = match lower.char_indices().last() {
+        Some((i, 'd')) => (&lower[..i], 1),
+        Some((i, 'w')) => (&lower[..i], 7),
+        Some((i, 'y')) => (&lower[..i], 365),
+        _ => (lower.as_str(), 1),
+    };
+    number
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .ok_or_else(|| anyhow!("{s:?} is not a number of days (use e.g. 30, 30d, 12w or 1y)"))
+}
+
+/// Formats a byte count for display, e.g. `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else if value.fract() == 0.0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// The type of a custom [`Field`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    /// Plain text, shown as is.
+    #[default]
+    Text,
+    /// A secret such as a PIN, masked unless asked for.
+    Hidden,
+    /// A web address.
+    Url,
+    /// An email address.
+    Email,
+    /// A calendar date (`YYYY-MM-DD`).
+    Date,
+    /// A TOTP secret or `otpauth://` URI; shown as the current code.
+    Totp,
+}
+
+impl FieldKind {
+    /// Checks that `value` is valid for this type.
+    pub fn validate(self, value: &str) -> Result<()> {
+        match self {
+            FieldKind::Text | FieldKind::Hidden => Ok(()),
+            FieldKind::Url => url::Url::parse(value)
+                .map(|_| ())
+                .map_err(|e| anyhow!("{value:?} is not a valid URL ({e})")),
+            FieldKind::Email => match value.split_once('@') {
+                Some((user, domain))
+                    if !user.is_empty() && domain.contains('.') && !value.contains(char::is_whitespace) =>
+                {
+                    Ok(())
+                }
+                _ => Err(anyhow!("{value:?} is not a valid email address")),
+            },
+            FieldKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|_| ())
+                .map_err(|_| anyhow!("{value:?} is not a date in the form YYYY-MM-DD")),
+            FieldKind::Totp => Totp::parse(value).map(|_| ()),
+        }
+    }
+
+    /// Whether values of this type are secret and masked by default.
+    pub fn is_secret(self) -> bool {
+        matches!(self, FieldKind::Hidden | FieldKind::Totp)
+    }
+}
+
+impl std::fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FieldKind::Text => "text",
+            FieldKind::Hidden => "hidden",
+            FieldKind::Url => "url",
+            FieldKind::Email => "email",
+            FieldKind::Date => "date",
+            FieldKind::Totp => "totp",
+        };
+        f.write_str(name)
+    }
 }
 
 /// Represents the entire password vault.
 ///
 /// The vault is stored as a map of key names to [`Entry`] objects.
 /// It is serialized and encrypted when saved to disk.
+///
+/// Keys are `/`-separated paths: `work/aws/prod-root` is the entry
+/// `prod-root` in the folder `work/aws`. Folders are not stored; a folder
+/// exists as long as some key starts with its path.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct Vault {
     /// A map of entry keys (e.g. "example.com") to their corresponding entries.
     pub entries: BTreeMap<String, Entry>,
+    /// Vault-wide settings; older vaults use the defaults.
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
+    pub settings: Settings,
+    /// Removed entries, oldest first, until they are restored or purged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trash: Vec<TrashedEntry>,
+    /// Password policies defined by the user, by name (see
+    /// [`crate::generate::lookup`]).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub policies: BTreeMap<String, Policy>,
+}
+
+/// An entry removed from the [`Vault`], kept in its trash.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrashedEntry {
+    /// The key the entry had when it was removed.
+    pub key: String,
+    /// When it was removed.
+    pub deleted_at: DateTime<Utc>,
+    /// The entry as it was.
+    pub entry: Entry,
+}
+
+impl Vault {
+    /// Namespace of the ids derived for entries saved before ids existed.
+    const LEGACY_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a57_93e4_4d0b_8a61_5c0e_d2b7_49f3);
+
+    /// Gives every entry without an id one derived from its key.
+    ///
+    /// The id only depends on the key, so copies of an old vault that
+    /// diverged before the upgrade still agree on the ids of their entries.
+    pub fn assign_missing_ids(&mut self) {
+        for (key, entry) in self.entries.iter_mut().filter(|(_, e)| e.id.is_nil()) {
+            entry.id = Uuid::new_v5(&Self::LEGACY_ID_NAMESPACE, key.as_bytes());
+        }
+    }
+
+    /// Returns the key and entry with the given id.
+    pub fn find_id(&self, id: &Uuid) -> Option<(&String, &Entry)> {
+        self.entries.iter().find(|(_, e)| e.id == *id)
+    }
+
+    /// Returns the keys of the entries in `folder` and its subfolders, in order.
+    ///
+    /// Leading and trailing slashes are ignored; the empty folder holds every entry.
+    pub fn folder_keys(&self, folder: &str) -> Vec<String> {
+        let folder = folder.trim_matches('/');
+        if folder.is_empty() {
+            return self.entries.keys().cloned().collect();
+        }
+        let prefix = format!("{folder}/");
+        self.entries
+            .range(prefix.clone()..)
+            .map(|(k, _)| k)
+            .take_while(|k| k.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Works out where moving `from` to `to` puts each entry, as
+    /// `(old key, new key)` pairs.
+    ///
+    /// `from` is an entry key or a folder (or both, in which case both
+    /// move). If `to` ends with `/` or is an existing folder, `from` moves
+    /// into it under its own name, like `mv` does; otherwise it is renamed
+    /// to `to`. Whether the new keys are free is left to the caller.
+    ///
+    /// # Errors
+    /// Returns an error if `from` is empty (or just `/`), nothing is called
+    /// `from`, or a folder would be moved into itself.
+    pub fn move_targets(&self, from: &str, to: &str) -> Result<Vec<(String, String)>> {
+        let from = from.trim_matches('/');
+        let into = to.ends_with('/') || !self.folder_keys(to).is_empty();
+        let to = to.trim_matches('/');
+        let name = from.rsplit('/').next().unwrap_or(from);
+        let dest = match (into, to.is_empty()) {
+            (true, true) => name.to_string(),
+            (true, false) => format!("{to}/{name}"),
+            (false, _) => to.to_string(),
+        };
+        self.targets(from, &dest)
+    }
+
+    /// Like [`Vault::move_targets`], but `to` is always the new name of
+    /// `from`, even if it is an existing folder.
+    ///
+    /// # Errors
+    /// Returns an error if `from` is empty (or just `/`), nothing is called
+    /// `from`, or a folder would be moved into itself.
+    pub fn rename_targets(&self, from: &str, to: &str) -> Result<Vec<(String, String)>> {
+        self.targets(from.trim_matches('/'), to.trim_matches('/'))
+    }
+
+    /// Pairs every key under `from` with its key under `dest`.
+    fn targets(&self, from: &str, dest: &str) -> Result<Vec<(String, String)>> {
+        // An empty source would be the whole vault, which cannot move into a folder of itself
+        if from.is_empty() {
+            return Err(anyhow!("name an entry or folder to move; the whole vault cannot be moved"));
+        }
+        if dest.is_empty() {
+            return Err(anyhow!("the new name is empty"));
+        }
+
+        let mut moves = Vec::new();
+        if self.entries.contains_key(from) {
+            moves.push((from.to_string(), dest.to_string()));
+        }
+        for key in self.folder_keys(from) {
+            let new = format!("{dest}/{}", &key[from.len() + 1..]);
+            moves.push((key, new));
+        }
+
+        if moves.is_empty() {
+            return Err(anyhow!("there is no entry or folder '{from}'"));
+        }
+        if dest == from || dest.starts_with(&format!("{from}/")) {
+            return Err(anyhow!("cannot move '{from}' to '{dest}' (into itself)"));
+        }
+        Ok(moves)
+    }
+
+    /// Moves the entry `key` into the trash, stamped with the current time.
+    ///
+    /// Returns `false` if there is no such entry.
+    pub fn move_to_trash(&mut self, key: &str) -> bool {
+        let Some(entry) = self.entries.remove(key) else {
+            return false;
+        };
+        self.trash.push(TrashedEntry {
+            key: key.to_string(),
+            deleted_at: Utc::now(),
+            entry,
+        });
+        true
+    }
+
+    /// Takes the trashed entries to restore for `key`, without putting them back.
+    ///
+    /// `key` names a trashed entry or a folder of them; for every key only
+    /// the most recently removed entry is taken. Returns an empty list if
+    /// nothing in the trash matches.
+    pub fn take_from_trash(&mut self, key: &str) -> Vec<TrashedEntry> {
+        let key = key.trim_matches('/');
+        let prefix = format!("{key}/");
+        let matches = |k: &str| k == key || k.starts_with(&prefix);
+        let mut latest: BTreeMap<&str, usize> = BTreeMap::new();
+        for (i, t) in self.trash.iter().enumerate().filter(|(_, t)| matches(&t.key)) {
+            latest.insert(&t.key, i);
+        }
+        let mut picked: Vec<usize> = latest.into_values().collect();
+        picked.sort_unstable();
+        let mut taken: Vec<TrashedEntry> = picked.into_iter().rev().map(|i| self.trash.remove(i)).collect();
+        taken.reverse();
+        taken
+    }
+
+    /// Removes trashed entries deleted before `cutoff` (all of them if
+    /// `None`) and returns them.
+    pub fn purge_trash(&mut self, cutoff: Option<DateTime<Utc>>) -> Vec<TrashedEntry> {
+        let (purged, kept) = std::mem::take(&mut self.trash)
+            .into_iter()
+            .partition(|t| cutoff.is_none_or(|c| t.deleted_at < c));
+        self.trash = kept;
+        purged
+    }
+
+    /// Returns `key`, or `key (2)`, `key (3)`, ... if it is taken by another entry.
+    pub fn free_key(&self, key: &str, id: &Uuid) -> String {
+        let taken = |k: &str| self.entries.get(k).is_some_and(|e| e.id != *id);
+        if !taken(key) {
+            return key.to_string();
+        }
+        (2..)
+            .map(|n| format!("{key} ({n})"))
+            .find(|k| !taken(k))
+            .expect("some suffix is free")
+    }
 }
 
 /// Represents the file storage format of the encrypted vault.
 ///
 /// This struct is serialized to JSON and written to disk.
-/// It contains a version number, the salt, and the encrypted blob.
+/// It contains a version number, the salt, the encrypted blob,
+/// and the identifiers of the versions it was derived from.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct FileFormat {
     /// Format version (used for backward compatibility in future releases).
@@ -37,4 +953,195 @@ pub struct FileFormat {
     pub salt: String,
     /// Base64-encoded AES-GCM ciphertext of the vault data.
     pub blob: String,
+    /// Identifiers (see [`FileFormat::id`]) of earlier versions of this vault,
+    /// newest first. Filled in on save and used to find the common ancestor
+    /// when two diverged copies are merged.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parents: Vec<String>,
+}
+
+impl FileFormat {
+    /// Length of a version identifier: the base64 form of the 12-byte nonce.
+    const ID_LEN: usize = 16;
+
+    /// Returns an identifier for this saved version of the vault.
+    ///
+    /// Every save encrypts with a fresh random nonce, and the blob starts
+    /// with that nonce, so its base64 prefix uniquely identifies the version.
+    pub fn id(&self) -> &str {
+        &self.blob[..self.blob.len().min(Self::ID_LEN)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Old passwords are kept newest first, up to the history limit.
+    #[test]
+    fn keeps_bounded_password_history() {
+        let mut entry = Entry::new("alice".into(), "p0".into(), None);
+        entry.set_password("p0".into());
+        assert!(entry.history.is_empty());
+
+        for i in 1..=Entry::HISTORY_LEN + 2 {
+            entry.set_password(format!("p{i}"));
+        }
+        assert_eq!(entry.password, format!("p{}", Entry::HISTORY_LEN + 2));
+        assert_eq!(entry.history.len(), Entry::HISTORY_LEN);
+        assert_eq!(entry.history[0].password, format!("p{}", Entry::HISTORY_LEN + 1));
+        assert_eq!(entry.history.last().unwrap().password, "p2");
+    }
+
+    /// Restoring swaps the current password into the history, and restoring
+    /// the current password keeps the history as it is.
+    #[test]
+    fn restores_earlier_passwords() {
+        let mut entry = Entry::new("alice".into(), "p0".into(), None);
+        entry.set_password("p1".into());
+        entry.set_password("p2".into());
+
+        assert!(entry.restore_password(1));
+        assert_eq!(entry.password, "p0");
+        let history: Vec<_> = entry.history.iter().map(|c| c.password.as_str()).collect();
+        assert_eq!(history, ["p2", "p1"]);
+
+        entry.history.push(PasswordChange { password: "p0".into(), replaced_at: None });
+        assert!(!entry.restore_password(2));
+        assert_eq!(entry.password, "p0");
+        assert_eq!(entry.history.len(), 3);
+    }
+
+    /// Folders hold the keys below them, but not keys that merely share a prefix.
+    #[test]
+    fn lists_folder_keys() {
+        let vault = Vault {
+            entries: ["work", "work/aws/root", "work/github", "workshop", "home"]
+                .into_iter()
+                .map(|k| (k.to_string(), Entry::default()))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(vault.folder_keys("work/"), ["work/aws/root", "work/github"]);
+        assert_eq!(vault.folder_keys("/work/aws"), ["work/aws/root"]);
+        assert!(vault.folder_keys("work/aws/root").is_empty());
+        assert_eq!(vault.folder_keys("").len(), 5);
+    }
+
+    /// Card numbers are checked with Luhn, expiry dates are normalized.
+    #[test]
+    fn validates_cards() {
+        let mut card = Card {
+            number: Card::normalize_number("4111 1111 1111 1111"),
+            expiry: Card::normalize_expiry("8/2027").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(card.expiry, "08/27");
+        assert!(card.validate().is_ok());
+        assert_eq!(card.masked_number(), "•••• 1111");
+
+        let date = |y, m| NaiveDate::from_ymd_opt(y, m, 1).unwrap();
+        assert!(!card.is_expired(date(2027, 8)));
+        assert!(card.is_expired(date(2027, 9)));
+
+        card.number = "4111111111111112".to_string();
+        assert!(card.validate().is_err());
+        assert!(Card::normalize_expiry("13/27").is_err());
+    }
+
+    /// Moves rename, or move into existing folders like `mv` does.
+    #[test]
+    fn plans_moves() {
+        let vault = Vault {
+            entries: ["work/aws/root", "work/aws/dev", "home/wifi", "archive/old"]
+                .into_iter()
+                .map(|k| (k.to_string(), Entry::default()))
+                .collect(),
+            ..Default::default()
+        };
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+
+        assert_eq!(vault.move_targets("home/wifi", "home/router").unwrap(), [pair("home/wifi", "home/router")]);
+        assert_eq!(vault.move_targets("home/wifi", "archive").unwrap(), [pair("home/wifi", "archive/wifi")]);
+        assert_eq!(
+            vault.move_targets("work/aws", "cloud/").unwrap(),
+            [pair("work/aws/dev", "cloud/aws/dev"), pair("work/aws/root", "cloud/aws/root")]
+        );
+        assert_eq!(
+            vault.move_targets("work/aws/", "aws").unwrap(),
+            [pair("work/aws/dev", "aws/dev"), pair("work/aws/root", "aws/root")]
+        );
+        assert!(vault.move_targets("work", "work/sub").is_err());
+        assert!(vault.move_targets("nothing", "x").is_err());
+        assert!(vault.move_targets("/", "archive/").is_err());
+        assert!(vault.move_targets("", "archive").is_err());
+        assert!(vault.rename_targets("/", "all").is_err());
+
+        assert_eq!(vault.rename_targets("home/wifi", "archive").unwrap(), [pair("home/wifi", "archive")]);
+        assert_eq!(
+            vault.rename_targets("work/aws", "cloud").unwrap(),
+            [pair("work/aws/dev", "cloud/dev"), pair("work/aws/root", "cloud/root")]
+        );
+    }
+
+    /// Size settings parse with binary suffixes and print back readably.
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("64K").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("10 MiB").unwrap(), 10 << 20);
+        assert_eq!(parse_size("1g").unwrap(), 1 << 30);
+        assert!(parse_size("ten").is_err());
+        assert!(parse_size("5T").is_err());
+
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(64 * 1024), "64 KiB");
+        assert_eq!(format_size(1536 * 1024), "1.5 MiB");
+
+        let mut settings = Settings::default();
+        settings.set("attachment-max-size", "50M").unwrap();
+        assert_eq!(settings.get("attachment-max-size").unwrap(), "50 MiB");
+        assert!(settings.set("no-such-setting", "1").is_err());
+    }
+
+    /// Rotation is due a fixed number of days after the last password change.
+    #[test]
+    fn computes_rotation_due() {
+        assert_eq!(parse_days("90").unwrap(), 90);
+        assert_eq!(parse_days("12w").unwrap(), 84);
+        assert_eq!(parse_days("1Y").unwrap(), 365);
+        assert!(parse_days("3 months").is_err());
+
+        let mut entry = Entry::new("alice".into(), "one".into(), None);
+        assert_eq!(entry.rotation_due_at(), None);
+        entry.rotate_every = Some(30);
+        let created = entry.created_at.unwrap();
+        assert_eq!(entry.rotation_due_at(), Some(created + chrono::Duration::days(30)));
+
+        entry.set_password("two".into());
+        let changed = entry.history[0].replaced_at.unwrap();
+        assert_eq!(entry.rotation_due_at(), Some(changed + chrono::Duration::days(30)));
+
+        entry.created_at = None;
+        entry.history.clear();
+        assert_eq!(entry.rotation_due_at(), Some(DateTime::UNIX_EPOCH));
+    }
+
+    /// Restoring takes the latest removal of each key, from a folder too.
+    #[test]
+    fn restores_from_trash() {
+        let mut vault = Vault::default();
+        for (key, login) in [("work/a", "1"), ("work/a", "2"), ("work/b", "3"), ("home", "4")] {
+            vault.entries.insert(key.to_string(), Entry::new(login.into(), String::new(), None));
+            assert!(vault.move_to_trash(key));
+        }
+        assert!(!vault.move_to_trash("work/a"));
+
+        let restored = vault.take_from_trash("work/");
+        let logins: Vec<&str> = restored.iter().map(|t| t.entry.login.as_str()).collect();
+        assert_eq!(logins, ["2", "3"]);
+        let left: Vec<&str> = vault.trash.iter().map(|t| t.entry.login.as_str()).collect();
+        assert_eq!(left, ["1", "4"]);
+        assert!(vault.take_from_trash("nothing").is_empty());
+    }
 }
@@ -0,0 +1,275 @@
+//! File permission handling for vault files.
+//!
+//! Everything rust-passmgr writes that holds vault data (vaults, backups,
+//! entry files, the remote cache, server links) is created readable by
+//! the owner only: files as `0600`, directories as `0700`, regardless of
+//! the umask.
+//!
+//! When a vault is loaded, [`check`] looks for setups that expose it to
+//! other local users: group/world access, another owner, or a
+//! world-writable parent directory such as `/tmp`. By default these are
+//! reported as warnings; with `--strict-perms` ([`set_strict`]) they are
+//! errors. On non-Unix platforms the checks are skipped.
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{anyhow, Context, Result};
+
+/// Mode for files holding vault data.
+pub const FILE_MODE: u32 = 0o600;
+
+/// Mode for directories created to hold vault data.
+pub const DIR_MODE: u32 = 0o700;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Turns permission warnings into errors (the `--strict-perms` option).
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Creates (or truncates) a file readable and writable by the owner only.
+///
+/// An existing file is switched to [`FILE_MODE`] as well.
+pub fn create_file(path: &Path) -> Result<File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, FILE_MODE);
+
+    let f = options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+    restrict(path, FILE_MODE)?;
+    Ok(f)
+}
+
+/// Writes `data` to a private file (see [`create_file`]).
+pub fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    create_file(path)?
+        .write_all(data)
+        .with_context(|| format!("Failed to write data to file: {}", path.display()))
+}
+
+/// Writes `data` to a new private file, failing if `path` already exists.
+///
+/// The check and the creation are one step, so a file that appears in the
+/// meantime is never overwritten.
+pub fn write_new_file(path: &Path, data: &[u8]) -> Result<()> {
+    use std::io::Write;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, FILE_MODE);
+
+    options
+        .open(path)
+        .with_context(|| format!("Failed to create file: {}", path.display()))?
+        .write_all(data)
+        .with_context(|| format!("Failed to write data to file: {}", path.display()))
+}
+
+/// Copies `from` to a private file at `to`.
+///
+/// Unlike [`fs::copy`], the copy does not inherit the source's permissions.
+pub fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    let data = fs::read(from).with_context(|| format!("Failed to read file: {}", from.display()))?;
+    write_file(to, &data)
+}
+
+/// Creates a directory and any missing parents with [`DIR_MODE`].
+///
+/// Directories that already exist keep their permissions.
+pub fn create_dir_all(path: &Path) -> Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, DIR_MODE);
+
+    builder
+        .create(path)
+        .with_context(|| format!("Failed to create directory: {}", path.display()))
+}
+
+#[cfg(unix)]
+fn restrict(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set permissions on {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn restrict(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Checks that the vault at `path` (a file or a vault directory) is private.
+///
+/// Problems are printed as warnings, or returned as an error in strict mode.
+pub fn check(path: &Path) -> Result<()> {
+    check_as(path, STRICT.load(Ordering::Relaxed))
+}
+
+/// [`check`] with the strict mode given explicitly.
+fn check_as(path: &Path, strict: bool) -> Result<()> {
+    let problems = problems(path);
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    if strict {
+        return Err(anyhow!(
+            "refusing to open {} (--strict-perms): {}",
+            path.display(),
+            problems.join("; ")
+        ));
+    }
+    for problem in problems {
+        eprintln!("⚠️  {}: {}", path.display(), problem);
+    }
+    Ok(())
+}
+
+/// Lists the ways in which the vault at `path` is exposed to other users.
+#[cfg(unix)]
+fn problems(path: &Path) -> Vec<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut problems = Vec::new();
+    let Ok(meta) = fs::metadata(path) else {
+        return problems;
+    };
+
+    let mode = meta.mode() & 0o777;
+    if mode & 0o077 != 0 {
+        let wanted = if meta.is_dir() { DIR_MODE } else { FILE_MODE };
+        problems.push(format!(
+            "accessible by group or other users (mode {mode:o}); run 'chmod {wanted:o} {}'",
+            path.display()
+        ));
+    }
+
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    let euid = unsafe { libc::geteuid() };
+    if meta.uid() != euid {
+        problems.push(format!("owned by another user (uid {})", meta.uid()));
+    }
+
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = fs::metadata(parent) {
+        if dir.mode() & 0o002 != 0 {
+            problems.push(format!(
+                "stored in the world-writable directory {}",
+                parent.display()
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(not(unix))]
+fn problems(_path: &Path) -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// Creates an empty private directory inside `tmp`.
+    fn private_dir(tmp: &TempDir) -> PathBuf {
+        let dir = tmp.path().join("vault");
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Returns the permission bits of `path`.
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    /// Sets the permission bits of `path`.
+    fn chmod(path: &Path, mode: u32) {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    /// New, overwritten and copied files end up owner-only.
+    #[test]
+    fn creates_private_files() {
+        let tmp = TempDir::new().unwrap();
+        let dir = private_dir(&tmp);
+        let file = dir.join("vault.json");
+        write_file(&file, b"one").unwrap();
+        assert_eq!(mode(&file), FILE_MODE);
+
+        chmod(&file, 0o644);
+        write_file(&file, b"two").unwrap();
+        assert_eq!(mode(&file), FILE_MODE);
+        assert_eq!(fs::read(&file).unwrap(), b"two");
+
+        let public = dir.join("public");
+        fs::write(&public, b"three").unwrap();
+        chmod(&public, 0o644);
+        copy_file(&public, &dir.join("copy")).unwrap();
+        assert_eq!(mode(&dir.join("copy")), FILE_MODE);
+    }
+
+    /// New files are never written over an existing one.
+    #[test]
+    fn refuses_to_overwrite_new_files() {
+        let tmp = TempDir::new().unwrap();
+        let dir = private_dir(&tmp);
+        let file = dir.join("codes.txt");
+        write_new_file(&file, b"one").unwrap();
+        assert_eq!(mode(&file), FILE_MODE);
+        assert!(write_new_file(&file, b"two").is_err());
+        assert_eq!(fs::read(&file).unwrap(), b"one");
+    }
+
+    /// New directories are owner-only; existing ones keep their mode.
+    #[test]
+    fn creates_private_directories() {
+        let tmp = TempDir::new().unwrap();
+        let dir = private_dir(&tmp);
+        assert_eq!(mode(&dir), DIR_MODE);
+
+        let nested = dir.join("a").join("b");
+        create_dir_all(&nested).unwrap();
+        assert_eq!(mode(&dir.join("a")), DIR_MODE);
+        assert_eq!(mode(&nested), DIR_MODE);
+
+        chmod(&nested, 0o755);
+        create_dir_all(&nested).unwrap();
+        assert_eq!(mode(&nested), 0o755);
+    }
+
+    /// Exposed vaults only fail the check in strict mode.
+    #[test]
+    fn refuses_exposed_vaults_when_strict() {
+        let tmp = TempDir::new().unwrap();
+        let dir = private_dir(&tmp);
+        let file = dir.join("vault.json");
+        write_file(&file, b"{}").unwrap();
+        assert!(check_as(&file, true).is_ok());
+
+        chmod(&file, 0o644);
+        assert!(check_as(&file, false).is_ok());
+        let err = check_as(&file, true).unwrap_err().to_string();
+        assert!(err.contains("--strict-perms") && err.contains("chmod 600"), "{err}");
+
+        chmod(&file, FILE_MODE);
+        chmod(&dir, 0o777);
+        let err = check_as(&file, true).unwrap_err().to_string();
+        assert!(err.contains("world-writable"), "{err}");
+        chmod(&dir, DIR_MODE);
+    }
+}
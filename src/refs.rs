@@ -0,0 +1,393 @@
+//! References from one entry's fields to another's.
+//!
+//! A value may contain KeePass-style references of the form
+//! `{REF:<field>@<search>:<text>}`, which are replaced by the referenced
+//! value when the entry is shown or exported:
+//!
+//! - `<field>` is `T` (the key), `U` (login), `P` (password), `A` (first
+//!   URL), `N` (notes) or `S:<name>` for a custom field.
+//! - `<search>` is `I` to find the entry by id (with or without hyphens,
+//!   so renaming the target does not break the reference) or `T` to find
+//!   it by key.
+//!
+//! For example `{REF:P@I:0b6f3c1e-2f4a-4c7e-9d1a-5e8b7c6d4f21}` stands for
+//! the password of that entry. Referenced values may contain references
+//! themselves; a reference that leads back to itself is reported as a cycle.
+
+use std::fmt;
+use std::ops::Range;
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+use crate::model::{Entry, Vault};
+
+/// Opening of a reference.
+const REF_START: &str = "{REF:";
+
+/// Which value of the target entry a reference stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefField {
+    /// The entry's key (`T`).
+    Title,
+    /// `U`
+    Login,
+    /// `P`
+    Password,
+    /// The first URL (`A`).
+    Url,
+    /// `N`
+    Notes,
+    /// A custom field (`S:<name>`).
+    Custom(String),
+}
+
+impl fmt::Display for RefField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefField::Title => f.write_str("key"),
+            RefField::Login => f.write_str("login"),
+            RefField::Password => f.write_str("password"),
+            RefField::Url => f.write_str("url"),
+            RefField::Notes => f.write_str("notes"),
+            RefField::Custom(name) => write!(f, "field '{name}'"),
+        }
+    }
+}
+
+/// How a reference finds its target entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// By stable id (`I`).
+    Id(Uuid),
+    /// By key (`T`).
+    Key(String),
+}
+
+/// A parsed `{REF:...}` reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ref {
+    pub field: RefField,
+    pub target: Target,
+}
+
+impl Ref {
+    /// Returns the reference to `field` of the entry with the given id.
+    pub fn by_id(field: RefField, id: Uuid) -> Self {
+        Ref { field, target: Target::Id(id) }
+    }
+
+    /// Finds the entry the reference points to.
+    fn find<'a>(&self, vault: &'a Vault) -> Option<(&'a String, &'a Entry)> {
+        match &self.target {
+            Target::Id(id) => vault.find_id(id),
+            Target::Key(key) => vault.entries.get_key_value(key),
+        }
+    }
+
+    /// Whether the reference points to the entry `key`.
+    pub fn points_to(&self, key: &str, entry: &Entry) -> bool {
+        match &self.target {
+            Target::Id(id) => *id == entry.id,
+            Target::Key(k) => k == key,
+        }
+    }
+}
+
+impl fmt::Display for Ref {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match &self.field {
+            RefField::Title => "T".to_string(),
+            RefField::Login => "U".to_string(),
+            RefField::Password => "P".to_string(),
+            RefField::Url => "A".to_string(),
+            RefField::Notes => "N".to_string(),
+            RefField::Custom(name) => format!("S:{name}"),
+        };
+        match &self.target {
+            Target::Id(id) => write!(f, "{{REF:{field}@I:{id}}}"),
+            Target::Key(key) => write!(f, "{{REF:{field}@T:{key}}}"),
+        }
+    }
+}
+
+/// Finds the references in `text`, with their byte ranges.
+///
+/// # Errors
+/// Returns an error for a `{REF:` that is not a valid reference.
+pub fn parse(text: &str) -> Result<Vec<(Range<usize>, Ref)>> {
+    let mut refs = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = text[from..].find(REF_START) {
+        let start = from + pos;
+        let body_start = start + REF_START.len();
+        let end = text[body_start..]
+            .find('}')
+            .map(|i| body_start + i)
+            .ok_or_else(|| anyhow!("unterminated reference in {text:?}"))?;
+        let body = &text[body_start..end];
+        let invalid = || anyhow!("invalid reference {{REF:{body}}} (expected e.g. {{REF:P@I:<id>}})");
+
+        let (field, search) = body.rsplit_once('@').ok_or_else(invalid)?;
+        let field = match field {
+            "T" => RefField::Title,
+            "U" => RefField::Login,
+            "P" => RefField::Password,
+            "A" => RefField::Url,
+            "N" => RefField::Notes,
+            f => match f.strip_prefix("S:") {
+                Some(name) if !name.is_empty() => RefField::Custom(name.to_string()),
+                _ => return Err(invalid()),
+            },
+        };
+        let target = match search.split_once(':') {
+            Some(("I", id)) => Target::Id(Uuid::parse_str(id).map_err(|_| invalid())?),
+            Some(("T", key)) if !key.is_empty() => Target::Key(key.to_string()),
+            _ => return Err(invalid()),
+        };
+
+        refs.push((start..end + 1, Ref { field, target }));
+        from = end + 1;
+    }
+    Ok(refs)
+}
+
+/// Returns a copy of the entry `key` with every reference in its login,
+/// password, notes, URLs and custom fields replaced by the referenced value.
+///
+/// # Errors
+/// Returns an error if a reference is invalid, points to no entry or
+/// field, or leads back to itself.
+pub fn resolve_entry(vault: &Vault, key: &str) -> Result<Entry> {
+    let entry = vault
+        .entries
+        .get(key)
+        .ok_or_else(|| anyhow!("there is no entry '{key}'"))?;
+    let mut resolver = Resolver { vault, stack: Vec::new() };
+    let mut resolved = entry.clone();
+
+    resolved.login = resolver.value(key, entry, &RefField::Login)?;
+    resolved.password = resolver.value(key, entry, &RefField::Password)?;
+    if entry.notes.is_some() {
+        resolved.notes = Some(resolver.value(key, entry, &RefField::Notes)?);
+    }
+    if let Some(first) = resolved.urls.first_mut() {
+        *first = resolver.value(key, entry, &RefField::Url)?;
+    }
+    for url in resolved.urls.iter_mut().skip(1) {
+        *url = resolver.text(url)?;
+    }
+    for field in resolved.fields.iter_mut() {
+        field.value = resolver.value(key, entry, &RefField::Custom(field.name.clone()))?;
+    }
+    Ok(resolved)
+}
+
+/// Lists the entries whose values reference the entry `key`, with the
+/// fields the references are in.
+pub fn dependents(vault: &Vault, key: &str) -> Vec<(String, Vec<RefField>)> {
+    let Some(target) = vault.entries.get(key) else {
+        return Vec::new();
+    };
+    vault
+        .entries
+        .iter()
+        .filter_map(|(k, e)| {
+            let fields: Vec<RefField> = references(e)
+                .into_iter()
+                .filter(|(_, r)| r.points_to(key, target))
+                .map(|(field, _)| field)
+                .fold(Vec::new(), |mut acc, f| {
+                    if !acc.contains(&f) {
+                        acc.push(f);
+                    }
+                    acc
+                });
+            (!fields.is_empty()).then(|| (k.clone(), fields))
+        })
+        .collect()
+}
+
+/// Points references by key (`@T:`) at the new keys after entries were
+/// renamed, given as `(old key, new key)` pairs.
+///
+/// Returns the keys of the entries whose values were changed; references by
+/// id need no update.
+pub fn retarget(vault: &mut Vault, renames: &[(String, String)]) -> Vec<String> {
+    let rewrite = |text: &mut String| {
+        let Ok(refs) = parse(text) else {
+            return false;
+        };
+        let mut changed = false;
+        for (range, r) in refs.into_iter().rev() {
+            let Target::Key(key) = &r.target else { continue };
+            if let Some((_, new)) = renames.iter().find(|(old, _)| old == key) {
+                let moved = Ref { field: r.field, target: Target::Key(new.clone()) };
+                text.replace_range(range, &moved.to_string());
+                changed = true;
+            }
+        }
+        changed
+    };
+
+    let mut updated = Vec::new();
+    for (key, entry) in vault.entries.iter_mut() {
+        let mut changed = rewrite(&mut entry.login);
+        changed |= rewrite(&mut entry.password);
+        if let Some(notes) = entry.notes.as_mut() {
+            changed |= rewrite(notes);
+        }
+        for url in entry.urls.iter_mut() {
+            changed |= rewrite(url);
+        }
+        for field in entry.fields.iter_mut() {
+            changed |= rewrite(&mut field.value);
+        }
+        if changed {
+            updated.push(key.clone());
+        }
+    }
+    updated
+}
+
+/// Returns every valid reference in an entry's values, with the field it is in.
+pub fn references(entry: &Entry) -> Vec<(RefField, Ref)> {
+    let mut values: Vec<(RefField, &str)> = vec![
+        (RefField::Login, &entry.login),
+        (RefField::Password, &entry.password),
+    ];
+    values.extend(entry.notes.as_deref().map(|n| (RefField::Notes, n)));
+    values.extend(entry.urls.iter().map(|u| (RefField::Url, u.as_str())));
+    values.extend(entry.fields.iter().map(|f| (RefField::Custom(f.name.clone()), f.value.as_str())));
+
+    values
+        .into_iter()
+        .flat_map(|(field, value)| {
+            parse(value)
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |(_, r)| (field.clone(), r))
+        })
+        .collect()
+}
+
+/// Resolves references, remembering the values being resolved to find cycles.
+struct Resolver<'a> {
+    vault: &'a Vault,
+    /// The values currently being resolved, outermost first.
+    stack: Vec<(Uuid, RefField, String)>,
+}
+
+impl Resolver<'_> {
+    /// Replaces every reference in `text`.
+    fn text(&mut self, text: &str) -> Result<String> {
+        let refs = parse(text)?;
+        let mut out = String::with_capacity(text.len());
+        let mut last = 0;
+        for (range, r) in refs {
+            out.push_str(&text[last..range.start]);
+            let (key, entry) = r
+                .find(self.vault)
+                .ok_or_else(|| anyhow!("reference {r} points to no entry"))?;
+            out.push_str(&self.value(key, entry, &r.field)?);
+            last = range.end;
+        }
+        out.push_str(&text[last..]);
+        Ok(out)
+    }
+
+    /// Returns the resolved value of `field` of an entry.
+    fn value(&mut self, key: &str, entry: &Entry, field: &RefField) -> Result<String> {
+        if self.stack.iter().any(|(id, f, _)| *id == entry.id && f == field) {
+            let path: Vec<String> = self
+                .stack
+                .iter()
+                .skip_while(|(id, f, _)| !(*id == entry.id && f == field))
+                .map(|(_, f, k)| format!("{k} ({f})"))
+                .chain([format!("{key} ({field})")])
+                .collect();
+            return Err(anyhow!("reference cycle: {}", path.join(" → ")));
+        }
+
+        let raw = match field {
+            RefField::Title => return Ok(key.to_string()),
+            RefField::Login => entry.login.clone(),
+            RefField::Password => entry.password.clone(),
+            RefField::Url => entry.urls.first().cloned().unwrap_or_default(),
+            RefField::Notes => entry.notes.clone().unwrap_or_default(),
+            RefField::Custom(name) => entry
+                .field(name)
+                .map(|f| f.value.clone())
+                .ok_or_else(|| anyhow!("entry '{key}' has no field '{name}'"))?,
+        };
+
+        self.stack.push((entry.id, field.clone(), key.to_string()));
+        let resolved = self.text(&raw);
+        self.stack.pop();
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Field, FieldKind};
+
+    fn vault(entries: &[(&str, &str)]) -> Vault {
+        let mut vault = Vault::default();
+        for (key, password) in entries {
+            vault.entries.insert(key.to_string(), Entry::new("user".into(), password.to_string(), None));
+        }
+        vault
+    }
+
+    /// References resolve by id and by key, recursively and inside other text.
+    #[test]
+    fn resolves_references() {
+        let mut v = vault(&[("sso", "hunter2"), ("jira", ""), ("wiki", "")]);
+        let sso = v.entries["sso"].id;
+        v.entries.get_mut("jira").unwrap().password = Ref::by_id(RefField::Password, sso).to_string();
+        v.entries.get_mut("wiki").unwrap().password = "{REF:P@T:jira}".to_string();
+        v.entries.get_mut("wiki").unwrap().fields.push(Field {
+            name: "hint".into(),
+            kind: FieldKind::Text,
+            value: "same as {REF:T@I:".to_string() + &sso.simple().to_string() + "}!",
+        });
+
+        assert_eq!(resolve_entry(&v, "jira").unwrap().password, "hunter2");
+        let wiki = resolve_entry(&v, "wiki").unwrap();
+        assert_eq!(wiki.password, "hunter2");
+        assert_eq!(wiki.fields[0].value, "same as sso!");
+
+        let users: Vec<String> = dependents(&v, "sso").into_iter().map(|(k, _)| k).collect();
+        assert_eq!(users, ["jira", "wiki"]);
+    }
+
+    /// Cycles, dangling and malformed references are errors.
+    #[test]
+    fn rejects_bad_references() {
+        let mut v = vault(&[("a", "{REF:P@T:b}"), ("b", "{REF:P@T:a}"), ("c", "{REF:P@T:nope}")]);
+        let err = resolve_entry(&v, "a").unwrap_err().to_string();
+        assert_eq!(err, "reference cycle: a (password) → b (password) → a (password)");
+        assert!(resolve_entry(&v, "c").is_err());
+
+        v.entries.get_mut("c").unwrap().password = "{REF:X@T:a}".to_string();
+        assert!(resolve_entry(&v, "c").is_err());
+        v.entries.get_mut("c").unwrap().password = "{REF:P@T:a".to_string();
+        assert!(resolve_entry(&v, "c").is_err());
+    }
+
+    /// Renaming an entry updates references to it by key, not those by id.
+    #[test]
+    fn retargets_renamed_keys() {
+        let mut v = vault(&[("old", "pw"), ("user", "{REF:P@T:old}/{REF:U@T:other}")]);
+        let id = v.entries["old"].id;
+        v.entries.get_mut("user").unwrap().login = Ref::by_id(RefField::Login, id).to_string();
+        let entry = v.entries.remove("old").unwrap();
+        v.entries.insert("new".into(), entry);
+
+        let renames = [("old".to_string(), "new".to_string())];
+        assert_eq!(retarget(&mut v, &renames), ["user"]);
+        assert_eq!(v.entries["user"].password, "{REF:P@T:new}/{REF:U@T:other}");
+        assert_eq!(v.entries["user"].login, Ref::by_id(RefField::Login, id).to_string());
+        assert!(retarget(&mut v, &renames).is_empty());
+    }
+}
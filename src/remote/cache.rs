@@ -0,0 +1,79 @@
+//! Local cache of remote vaults.
+//!
+//! Each remote vault URL maps to one JSON file holding the last fetched
+//! or saved [`FileFormat`] (which is already encrypted) and its ETag.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::model::FileFormat;
+use crate::perms;
+
+/// A cached copy of a remote vault.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cached {
+    /// ETag of the cached version, returned with it by offline loads.
+    pub etag: Option<String>,
+    /// The encrypted vault as stored on the server.
+    pub vault: FileFormat,
+}
+
+/// Returns the directory holding cached remote vaults.
+///
+/// Uses `$XDG_CACHE_HOME/rust-passmgr`, `~/.cache/rust-passmgr`, or
+/// `%LOCALAPPDATA%\rust-passmgr` on Windows.
+fn cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("rust-passmgr")
+}
+
+/// Returns the cache file used for the remote vault at `location`.
+pub fn cache_path(location: &Path) -> PathBuf {
+    let digest = Sha256::digest(location.to_string_lossy().as_bytes());
+    cache_dir().join(format!("{}.json", &hex::encode(digest)[..16]))
+}
+
+/// Reads the cached copy of a remote vault, if any.
+pub fn read(location: &Path) -> Result<Option<Cached>> {
+    let path = cache_path(location);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let s = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    let cached = serde_json::from_str(&s)
+        .with_context(|| format!("Failed to parse cache file: {}", path.display()))?;
+    Ok(Some(cached))
+}
+
+/// Replaces the cached copy of a remote vault.
+pub fn write(location: &Path, vault: &FileFormat, etag: Option<&str>) -> Result<()> {
+    let path = cache_path(location);
+    if let Some(dir) = path.parent() {
+        perms::create_dir_all(dir)?;
+    }
+
+    let cached = Cached {
+        etag: etag.map(str::to_string),
+        vault: vault.clone(),
+    };
+    let s = serde_json::to_string_pretty(&cached)
+        .with_context(|| "Failed to serialize cache entry")?;
+    perms::write_file(&path, s.as_bytes())
+}
+
+/// Forgets the cached copy of a remote vault (e.g. after it was deleted remotely).
+pub fn clear(location: &Path) -> Result<()> {
+    let path = cache_path(location);
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove cache file: {}", path.display()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! Remote vault storage for rust-passmgr.
+//!
+//! Lets the vault live on a WebDAV share (e.g. Nextcloud) or in an
+//! S3-compatible bucket (e.g. MinIO) instead of a local file. A remote
+//! vault is addressed by URL wherever a vault path is expected:
+//!
+//! - `webdav://host/path/vault.json` (HTTPS) or `webdav+http://host:port/path/vault.json`
+//! - `s3://host/bucket/vault.json` (HTTPS) or `s3+http://host:port/bucket/vault.json`
+//!
+//! Writes are conditional on the ETag the vault was loaded with, so a vault
+//! changed by someone else in the meantime is detected instead of being
+//! overwritten. The last fetched copy is kept (still encrypted) in a local
+//! cache, which is used for reads when the server cannot be reached; since
+//! every process refreshes it, writes never take their ETag from it.
+//!
+//! [`server`] holds the client for `passmgr-server`, which syncs a local
+//! vault with a revisioned copy on a self-hosted server.
+
+mod cache;
+mod s3;
+pub mod server;
+mod webdav;
+
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use url::Url;
+use crate::model::FileFormat;
+
+/// A vault body fetched from a remote store.
+#[derive(Debug)]
+pub struct Fetched {
+    /// Raw contents of the remote object.
+    pub body: Vec<u8>,
+    /// Entity tag identifying this version, if the server sent one.
+    pub etag: Option<String>,
+}
+
+/// Condition a write must satisfy on the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+    /// Only overwrite the object if it still has this ETag.
+    IfMatch(String),
+    /// Only create the object if it does not exist yet.
+    IfNoneMatch,
+}
+
+/// Returned when the server rejects a write because its precondition failed.
+#[derive(Debug)]
+pub struct PreconditionFailed;
+
+impl fmt::Display for PreconditionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the remote vault was changed since it was last fetched; \
+             run the command again to work on the latest version"
+        )
+    }
+}
+
+impl std::error::Error for PreconditionFailed {}
+
+/// Returned when the server cannot be reached at all.
+#[derive(Debug)]
+pub struct Unreachable(pub String);
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "remote vault unreachable: {}", self.0)
+    }
+}
+
+impl std::error::Error for Unreachable {}
+
+/// A place a single vault object can be fetched from and stored to.
+pub trait RemoteStore {
+    /// Fetches the vault, or returns `Ok(None)` if it does not exist.
+    fn fetch(&self) -> Result<Option<Fetched>>;
+
+    /// Stores the vault if `precondition` holds and returns the new ETag.
+    ///
+    /// Fails with [`PreconditionFailed`] if the server rejects the condition.
+    fn store(&self, body: &[u8], precondition: &Precondition) -> Result<Option<String>>;
+}
+
+/// Returns `true` if `path` is a remote vault URL rather than a local file.
+pub fn is_remote(path: &Path) -> bool {
+    path.to_str().is_some_and(|s| {
+        ["webdav://", "webdav+http://", "s3://", "s3+http://"]
+            .iter()
+            .any(|scheme| s.starts_with(scheme))
+    })
+}
+
+/// Opens the remote store addressed by `path`.
+pub fn open(path: &Path) -> Result<Box<dyn RemoteStore>> {
+    let location = path
+        .to_str()
+        .ok_or_else(|| anyhow!("invalid remote vault URL: {}", path.display()))?;
+    let (scheme, rest) = location
+        .split_once("://")
+        .ok_or_else(|| anyhow!("invalid remote vault URL: {location}"))?;
+
+    let (kind, http_scheme) = match scheme.split_once('+') {
+        Some((kind, "http")) => (kind, "http"),
+        None => (scheme, "https"),
+        _ => return Err(anyhow!("unsupported remote scheme: {scheme}")),
+    };
+    let url = Url::parse(&format!("{http_scheme}://{rest}"))
+        .with_context(|| format!("invalid remote vault URL: {location}"))?;
+
+    match kind {
+        "webdav" => Ok(Box::new(webdav::WebDav::new(url)?)),
+        "s3" => Ok(Box::new(s3::S3::new(url)?)),
+        _ => Err(anyhow!("unsupported remote scheme: {scheme}")),
+    }
+}
+
+/// Loads a remote vault, falling back to the local cache when offline.
+///
+/// Returns the vault together with the ETag it was fetched as, which
+/// [`save`] needs to replace exactly this version. Successful fetches
+/// refresh the cache.
+pub fn load(path: &Path) -> Result<Option<(FileFormat, Option<String>)>> {
+    let store = open(path)?;
+    match store.fetch() {
+        Ok(Some(fetched)) => {
+            let ff: FileFormat = serde_json::from_slice(&fetched.body)
+                .with_context(|| format!("Failed to parse JSON in {}", path.display()))?;
+            cache::write(path, &ff, fetched.etag.as_deref())?;
+            Ok(Some((ff, fetched.etag)))
+        }
+        Ok(None) => {
+            cache::clear(path)?;
+            Ok(None)
+        }
+        Err(e) if e.is::<Unreachable>() => match cache::read(path)? {
+            Some(cached) => {
+                eprintln!("⚠️  {e}; using the cached copy (read-only).");
+                Ok(Some((cached.vault, cached.etag)))
+            }
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns a local file kept next to the cached copy of a remote vault,
+/// for data that is never uploaded (such as [`crate::usage`]).
+pub fn cache_sidecar(path: &Path, extension: &str) -> PathBuf {
+    cache::cache_path(path).with_extension(extension)
+}
+
+/// Saves a remote vault and returns the ETag of the new version.
+///
+/// With the `etag` a vault was loaded with (see [`load`]), the vault is only
+/// overwritten if that is still the current version; without one, it is
+/// only created if absent. Either way a version stored by someone else in
+/// the meantime makes the save fail with [`PreconditionFailed`].
+pub fn save(path: &Path, ff: &FileFormat, etag: Option<&str>) -> Result<Option<String>> {
+    let store = open(path)?;
+    let precondition = match etag {
+        Some(etag) => Precondition::IfMatch(etag.to_string()),
+        None => Precondition::IfNoneMatch,
+    };
+
+    let body = serde_json::to_vec_pretty(ff)
+        .with_context(|| "Failed to serialize FileFormat to JSON")?;
+    let etag = match store.store(&body, &precondition)? {
+        Some(etag) => Some(etag),
+        // Some servers omit the ETag on PUT; ask for it so the next write stays conditional.
+        None => store.fetch()?.and_then(|f| f.etag),
+    };
+
+    cache::write(path, ff, etag.as_deref())?;
+    Ok(etag)
+}
+
+/// Sends a request and maps ureq errors onto the remote error types.
+///
+/// Returns `Ok(None)` for `404 Not Found`.
+fn send(request: ureq::Request, body: Option<&[u8]>) -> Result<Option<ureq::Response>> {
+    let result = match body {
+        Some(b) => request.send_bytes(b),
+        None => request.call(),
+    };
+
+    match result {
+        Ok(resp) => Ok(Some(resp)),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(ureq::Error::Status(412, _)) => Err(PreconditionFailed.into()),
+        Err(ureq::Error::Status(code, resp)) => {
+            let text = resp.into_string().unwrap_or_default();
+            Err(anyhow!("remote server returned {code}: {}", text.trim()))
+        }
+        Err(ureq::Error::Transport(t)) => Err(Unreachable(t.to_string()).into()),
+    }
+}
+
+/// Error for a write answered with `404 Not Found`.
+fn missing_parent(url: &Url) -> anyhow::Error {
+    anyhow!("cannot write {url}: the parent folder or bucket does not exist")
+}
+
+/// Reads a response into a [`Fetched`] value.
+fn into_fetched(resp: ureq::Response) -> Result<Fetched> {
+    let etag = resp.header("ETag").map(str::to_string);
+    let mut body = Vec::new();
+    resp.into_reader()
+        .read_to_end(&mut body)
+        .with_context(|| "Failed to read remote vault")?;
+    Ok(Fetched { body, etag })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Round-trips a vault through a live server, checking conflict detection.
+    ///
+    /// Run against a local MinIO or WebDAV server with e.g.
+    /// `PASSMGR_TEST_REMOTE=s3+http://localhost:9000/test/vault.json cargo test -- --ignored`
+    /// (plus `AWS_ACCESS_KEY_ID`/`AWS_SECRET_ACCESS_KEY` or
+    /// `PASSMGR_WEBDAV_USER`/`PASSMGR_WEBDAV_PASSWORD`).
+    #[test]
+    #[ignore]
+    fn live_round_trip() {
+        let path = PathBuf::from(std::env::var("PASSMGR_TEST_REMOTE").expect("PASSMGR_TEST_REMOTE"));
+        let ff = |blob: &str| FileFormat {
+            version: 1,
+            salt: "c2FsdA==".to_string(),
+            blob: blob.to_string(),
+            parents: Vec::new(),
+        };
+
+        let etag = load(&path).unwrap().and_then(|(_, etag)| etag);
+        let etag = save(&path, &ff("Zmlyc3Q="), etag.as_deref()).unwrap();
+        assert_eq!(load(&path).unwrap().map(|(ff, _)| ff), Some(ff("Zmlyc3Q=")));
+
+        // Someone else writes behind our back: our next conditional save must fail.
+        let store = open(&path).unwrap();
+        let current = store.fetch().unwrap().unwrap().etag.unwrap();
+        store
+            .store(b"{\"version\":1,\"salt\":\"\",\"blob\":\"\"}", &Precondition::IfMatch(current))
+            .unwrap();
+        let err = save(&path, &ff("c2Vjb25k"), etag.as_deref()).unwrap_err();
+        assert!(err.is::<PreconditionFailed>());
+    }
+}
@@ -0,0 +1,175 @@
+//! Client for `passmgr-server`, the zero-knowledge sync server.
+//!
+//! A local vault is linked to an account on the server by a small state
+//! file next to it (`vault.json.server.json`), which holds the server URL,
+//! the access token and the revision and contents seen at the last sync.
+//! Only the encrypted [`FileFormat`] is ever sent; the master password and
+//! the derived key never leave the machine.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::model::FileFormat;
+use crate::perms;
+use super::Unreachable;
+
+/// Link between a local vault and an account on a sync server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerLink {
+    /// Base URL of the server (e.g. `https://passmgr.example.com`).
+    pub url: String,
+    /// Bearer token issued by `passmgr-server add-user`.
+    pub token: String,
+    /// Server revision at the last successful push or pull (`0` if none).
+    #[serde(default)]
+    pub revision: u64,
+    /// Vault contents at the last successful push or pull.
+    #[serde(default)]
+    pub base: Option<FileFormat>,
+}
+
+/// Result of [`ServerLink::push`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// The server stored the vault under this new revision.
+    Pushed(u64),
+    /// The server holds a newer revision than the one the push was based on.
+    Stale(u64),
+}
+
+impl ServerLink {
+    /// Returns the state file linking `vault` to a server.
+    pub fn path(vault: &Path) -> PathBuf {
+        let mut name = vault.file_name().unwrap_or_default().to_os_string();
+        name.push(".server.json");
+        vault.with_file_name(name)
+    }
+
+    /// Loads the link for `vault`, or `None` if it was never linked.
+    pub fn load(vault: &Path) -> Result<Option<Self>> {
+        let path = Self::path(vault);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let s = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let link = serde_json::from_str(&s)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(link))
+    }
+
+    /// Saves the link next to `vault`.
+    pub fn save(&self, vault: &Path) -> Result<()> {
+        let path = Self::path(vault);
+        let s = serde_json::to_string_pretty(self)
+            .with_context(|| "Failed to serialize server link")?;
+        // The link holds the access token, so keep it private like the vault.
+        perms::write_file(&path, s.as_bytes())
+    }
+
+    /// Records a successful push or pull.
+    pub fn synced(&mut self, revision: u64, ff: &FileFormat) {
+        self.revision = revision;
+        self.base = Some(ff.clone());
+    }
+
+    /// Returns `true` if the local vault changed since the last sync.
+    pub fn local_changed(&self, local: &FileFormat) -> bool {
+        self.base.as_ref().is_none_or(|b| b.blob != local.blob)
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/v1/vault", self.url.trim_end_matches('/'))
+    }
+
+    /// Fetches the vault stored on the server with its revision.
+    ///
+    /// Returns `Ok(None)` if nothing was pushed yet.
+    pub fn fetch(&self) -> Result<Option<(u64, FileFormat)>> {
+        let resp = ureq::get(&self.endpoint())
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call();
+
+        match resp {
+            Ok(resp) => {
+                let revision = revision_header(&resp)?;
+                let ff = serde_json::from_reader(resp.into_reader())
+                    .with_context(|| "Server returned an invalid vault")?;
+                Ok(Some((revision, ff)))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+
+    /// Uploads `ff`, provided the server is still at revision `base`.
+    pub fn push(&self, ff: &FileFormat, base: u64) -> Result<PushOutcome> {
+        let body = serde_json::to_vec(ff).with_context(|| "Failed to serialize FileFormat to JSON")?;
+        let resp = ureq::put(&self.endpoint())
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Content-Type", "application/json")
+            .set("X-Base-Revision", &base.to_string())
+            .send_bytes(&body);
+
+        match resp {
+            Ok(resp) => Ok(PushOutcome::Pushed(revision_header(&resp)?)),
+            Err(ureq::Error::Status(409, resp)) => Ok(PushOutcome::Stale(revision_header(&resp)?)),
+            Err(e) => Err(map_error(e)),
+        }
+    }
+}
+
+/// Reads the `X-Revision` header of a server response.
+fn revision_header(resp: &ureq::Response) -> Result<u64> {
+    resp.header("X-Revision")
+        .and_then(|r| r.parse().ok())
+        .ok_or_else(|| anyhow!("server response has no valid X-Revision header"))
+}
+
+/// Turns a ureq error into a readable message.
+fn map_error(e: ureq::Error) -> anyhow::Error {
+    match e {
+        ureq::Error::Status(401, _) => anyhow!("the server rejected the token; run 'remote login' again"),
+        ureq::Error::Status(code, resp) => {
+            let text = resp.into_string().unwrap_or_default();
+            anyhow!("server returned {code}: {}", text.trim())
+        }
+        ureq::Error::Transport(t) => Unreachable(t.to_string()).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiny_http::{Header, Response, Server};
+
+    /// Answers requests with `statuses` in turn, each carrying
+    /// `X-Revision: 7`, and returns a link to the server.
+    fn scripted_server(statuses: Vec<u16>) -> ServerLink {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            for (request, status) in server.incoming_requests().zip(statuses) {
+                let response = Response::from_string("{}")
+                    .with_status_code(status)
+                    .with_header(Header::from_bytes("X-Revision", "7").unwrap());
+                let _ = request.respond(response);
+            }
+        });
+        ServerLink { url, token: "secret".into(), revision: 0, base: None }
+    }
+
+    /// A push the server rejects as stale is a conflict for `remote sync`
+    /// to merge, not an error; a rejected token is an error.
+    #[test]
+    fn reports_stale_pushes_as_conflicts() {
+        let link = scripted_server(vec![200, 409, 401]);
+        let ff = FileFormat { version: 1, salt: "c2FsdA==".into(), blob: "dmF1bHQ=".into(), parents: Vec::new() };
+
+        assert_eq!(link.push(&ff, 0).unwrap(), PushOutcome::Pushed(7));
+        assert_eq!(link.push(&ff, 0).unwrap(), PushOutcome::Stale(7));
+        let err = link.push(&ff, 0).unwrap_err();
+        assert!(err.to_string().contains("rejected the token"), "{err}");
+    }
+}
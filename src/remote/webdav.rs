@@ -0,0 +1,63 @@
+//! WebDAV remote store (Nextcloud, ownCloud, Apache `mod_dav`, ...).
+//!
+//! Credentials are read from `PASSMGR_WEBDAV_USER` and
+//! `PASSMGR_WEBDAV_PASSWORD`, or from the user info part of the URL.
+
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use url::Url;
+use super::{into_fetched, missing_parent, send, Fetched, Precondition, RemoteStore};
+
+/// A vault stored as a single file on a WebDAV server.
+pub struct WebDav {
+    url: Url,
+    auth: Option<String>,
+}
+
+impl WebDav {
+    /// Creates a store for the file at `url`, taking credentials from the environment.
+    pub fn new(mut url: Url) -> Result<Self> {
+        let user = std::env::var("PASSMGR_WEBDAV_USER")
+            .ok()
+            .or_else(|| Some(url.username().to_string()).filter(|u| !u.is_empty()));
+        let password = std::env::var("PASSMGR_WEBDAV_PASSWORD")
+            .ok()
+            .or_else(|| url.password().map(str::to_string))
+            .unwrap_or_default();
+
+        // Never send the credentials as part of the URL itself
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+
+        let auth = user.map(|u| {
+            let token = general_purpose::STANDARD.encode(format!("{u}:{password}"));
+            format!("Basic {token}")
+        });
+        Ok(Self { url, auth })
+    }
+
+    /// Starts a request for the vault file with authentication applied.
+    fn request(&self, method: &str) -> ureq::Request {
+        let req = ureq::request_url(method, &self.url);
+        match &self.auth {
+            Some(auth) => req.set("Authorization", auth),
+            None => req,
+        }
+    }
+}
+
+impl RemoteStore for WebDav {
+    fn fetch(&self) -> Result<Option<Fetched>> {
+        send(self.request("GET"), None)?.map(into_fetched).transpose()
+    }
+
+    fn store(&self, body: &[u8], precondition: &Precondition) -> Result<Option<String>> {
+        let req = self.request("PUT").set("Content-Type", "application/json");
+        let req = match precondition {
+            Precondition::IfMatch(etag) => req.set("If-Match", etag),
+            Precondition::IfNoneMatch => req.set("If-None-Match", "*"),
+        };
+        let resp = send(req, Some(body))?.ok_or_else(|| missing_parent(&self.url))?;
+        Ok(resp.header("ETag").map(str::to_string))
+    }
+}
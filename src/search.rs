@@ -0,0 +1,209 @@
+//! Fuzzy search over entries.
+//!
+//! A query is a list of whitespace-separated terms, all of which must match.
+//! A plain term is looked for in the key, login, URLs and tags (and the
+//! notes, if asked for); a qualified term such as `login:alice`, `tag:prod`,
+//! `url:github`, `key:aws` or `notes:recovery` only in that value.
+//!
+//! Terms match fuzzily: exact matches rank above prefixes, prefixes above
+//! substrings, and substrings above scattered letters (`gthb` finds
+//! `github`). Matches in the key count more than in the login, URLs and
+//! tags, which count more than matches in the notes.
+
+use crate::model::{Entry, Vault};
+
+/// Where a search term is looked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Key, login, URLs, tags and, if enabled, notes.
+    Any,
+    /// `key:` (also `title:`)
+    Key,
+    /// `login:` (also `user:`)
+    Login,
+    /// `url:`
+    Url,
+    /// `tag:`
+    Tag,
+    /// `notes:` (also `note:`)
+    Notes,
+}
+
+/// One term of a [`Query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    pub scope: Scope,
+    pub text: String,
+}
+
+/// A parsed search query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub terms: Vec<Term>,
+    /// Whether plain terms also look in the notes.
+    pub notes: bool,
+}
+
+impl Query {
+    /// Parses a query such as `login:alice tag:prod aws`.
+    ///
+    /// A prefix that is not a known qualifier (as in `https://...`) is part
+    /// of a plain term.
+    pub fn parse(query: &str, notes: bool) -> Self {
+        let terms = query
+            .split_whitespace()
+            .map(|word| {
+                let scope = word.split_once(':').and_then(|(prefix, rest)| {
+                    let scope = match prefix.to_lowercase().as_str() {
+                        "key" | "title" => Scope::Key,
+                        "login" | "user" => Scope::Login,
+                        "url" => Scope::Url,
+                        "tag" => Scope::Tag,
+                        "notes" | "note" => Scope::Notes,
+                        _ => return None,
+                    };
+                    Some((scope, rest))
+                });
+                match scope {
+                    Some((scope, rest)) => Term { scope, text: rest.to_string() },
+                    None => Term { scope: Scope::Any, text: word.to_string() },
+                }
+            })
+            .filter(|t| !t.text.is_empty())
+            .collect();
+        Query { terms, notes }
+    }
+
+    /// Scores how well the entry `key` matches; `None` if some term does
+    /// not match at all.
+    pub fn score(&self, key: &str, entry: &Entry) -> Option<u32> {
+        self.terms.iter().map(|term| self.term_score(term, key, entry)).sum()
+    }
+
+    /// The best weighted score of `term` over the values it applies to.
+    fn term_score(&self, term: &Term, key: &str, entry: &Entry) -> Option<u32> {
+        let wants = |scope: Scope| term.scope == scope || term.scope == Scope::Any;
+        let mut values: Vec<(u32, &str)> = Vec::new();
+        if wants(Scope::Key) {
+            values.push((3, key));
+        }
+        if wants(Scope::Login) {
+            values.push((2, &entry.login));
+        }
+        if wants(Scope::Url) {
+            values.extend(entry.urls.iter().map(|u| (2, u.as_str())));
+        }
+        if wants(Scope::Tag) {
+            values.extend(entry.tags.iter().map(|t| (2, t.as_str())));
+        }
+        if term.scope == Scope::Notes || (term.scope == Scope::Any && self.notes) {
+            values.extend(entry.notes.as_deref().map(|n| (1, n)));
+        }
+        values
+            .into_iter()
+            .filter_map(|(weight, value)| fuzzy_score(&term.text, value).map(|s| s * weight))
+            .max()
+    }
+}
+
+/// Returns the keys of the entries matching `query`, best match first.
+pub fn search<'a>(vault: &'a Vault, query: &Query) -> Vec<(&'a String, u32)> {
+    let mut hits: Vec<(&String, u32)> = vault
+        .entries
+        .iter()
+        .filter_map(|(key, entry)| query.score(key, entry).map(|s| (key, s)))
+        .collect();
+    hits.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    hits
+}
+
+/// Scores how well `pattern` matches `text`, ignoring case.
+///
+/// Equal texts score 100, prefixes 80, substrings 60 (70 at the start of a
+/// word), and the letters of `pattern` appearing in order close together
+/// between 10 and 50. Returns `None` if `pattern` does not match.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    if text == pattern {
+        return Some(100);
+    }
+    if text.starts_with(&pattern) {
+        return Some(80);
+    }
+    if let Some(pos) = text.find(&pattern) {
+        let boundary = !text[..pos].chars().last().is_some_and(char::is_alphanumeric);
+        return Some(if boundary { 70 } else { 60 });
+    }
+
+    // The letters in order: consecutive letters and word starts earn a bonus
+    let text: Vec<char> = text.chars().collect();
+    let len = pattern.chars().count() as u32;
+    let (mut bonus, mut next, mut first, mut prev) = (0, 0, None, None);
+    for c in pattern.chars() {
+        let i = (next..text.len()).find(|&i| text[i] == c)?;
+        if prev.is_some_and(|p| p + 1 == i) {
+            bonus += 3;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            bonus += 2;
+        }
+        first.get_or_insert(i);
+        prev = Some(i);
+        next = i + 1;
+    }
+    // Letters spread too far apart are more likely chance than a match
+    let span = (prev? - first? + 1) as u32;
+    if span > 3 * len + 2 {
+        return None;
+    }
+    Some(10 + 40 * bonus / (5 * len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(login: &str, urls: &[&str], tags: &[&str], notes: Option<&str>) -> Entry {
+        let mut entry = Entry::new(login.into(), "pw".into(), notes.map(str::to_string));
+        entry.urls = urls.iter().map(|u| u.to_string()).collect();
+        entry.tags = tags.iter().map(|t| t.to_string()).collect();
+        entry
+    }
+
+    /// Closer matches score higher; unrelated text does not match.
+    #[test]
+    fn scores_fuzzy_matches() {
+        assert_eq!(fuzzy_score("GitHub", "github"), Some(100));
+        assert_eq!(fuzzy_score("git", "github.com"), Some(80));
+        assert_eq!(fuzzy_score("hub", "git-hub"), Some(70));
+        assert_eq!(fuzzy_score("hub", "github"), Some(60));
+        let scattered = fuzzy_score("gthb", "github").unwrap();
+        assert!((10..60).contains(&scattered));
+        assert_eq!(fuzzy_score("gthb", "gitlab"), None);
+        assert_eq!(fuzzy_score("ab", "a very long text before b"), None);
+    }
+
+    /// Qualified terms only look at their value; all terms must match.
+    #[test]
+    fn parses_and_ranks_queries() {
+        let query = Query::parse("login:alice tag:prod https://x", false);
+        assert_eq!(query.terms[0], Term { scope: Scope::Login, text: "alice".into() });
+        assert_eq!(query.terms[1], Term { scope: Scope::Tag, text: "prod".into() });
+        assert_eq!(query.terms[2], Term { scope: Scope::Any, text: "https://x".into() });
+
+        let mut vault = Vault::default();
+        vault.entries.insert("aws/prod".into(), entry("alice", &[], &["prod"], None));
+        vault.entries.insert("aws/dev".into(), entry("alice", &[], &["dev"], Some("prod account")));
+        vault.entries.insert("alice.dev".into(), entry("bob", &["https://alice.dev"], &[], None));
+
+        let keys = |q: &str, notes: bool| -> Vec<String> {
+            search(&vault, &Query::parse(q, notes)).into_iter().map(|(k, _)| k.clone()).collect()
+        };
+        assert_eq!(keys("login:alice tag:prod", false), ["aws/prod"]);
+        assert_eq!(keys("alice", false), ["alice.dev", "aws/dev", "aws/prod"]);
+        assert_eq!(keys("prod", false), ["aws/prod"]);
+        assert_eq!(keys("prod", true), ["aws/prod", "aws/dev"]);
+        assert_eq!(keys("notes:account", false), ["aws/dev"]);
+    }
+}
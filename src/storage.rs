@@ -1,20 +1,35 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
-use anyhow::{Result, Context};
-use crate::model::FileFormat;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{anyhow, Result, Context};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::blobs;
+use crate::crypto::{derive_key, generate_salt, encrypt_vault_with_key, decrypt_vault_with_key, KEY_LEN};
+use crate::dirvault;
+use crate::journal::{self, Pending};
+use crate::model::{AttachmentData, Entry, FileFormat, Vault};
+use crate::perms;
+use crate::remote::{self, server::ServerLink};
+use crate::usage;
+use crate::vcs::{self, Change};
 
 /// Loads a [`FileFormat`] structure from a JSON file.
 ///
-/// Returns `Ok(None)` if the file does not exist.
+/// Returns `Ok(None)` if the file does not exist. Remote vault URLs
+/// (see [`crate::remote`]) are fetched from their server instead.
 ///
 /// # Arguments
-/// * `path` — Path to the vault file (usually `vault.json`).
+/// * `path` — Path to the vault file (usually `vault.json`) or a remote vault URL.
 ///
 /// # Returns
 /// - `Ok(Some(FileFormat))` if the file was successfully read and parsed.
 /// - `Ok(None)` if the file does not exist.
-/// - `Err` if reading or parsing fails.
+/// - `Err` if reading or parsing fails, if the file is exposed to other users
+///   while `--strict-perms` is set (see [`crate::perms::check`]), or if `path` is a directory vault
+///   (see [`crate::dirvault`]), which has no single [`FileFormat`].
 ///
 /// # Example
 /// ```
@@ -23,11 +38,37 @@ use crate::model::FileFormat;
 ///     println!("Loaded version: {}", ff.version);
 /// }
 /// ```
-pub fn load_fileformat(path: &PathBuf) -> Result<Option<FileFormat>> {
+pub fn load_fileformat(path: &Path) -> Result<Option<FileFormat>> {
+    Ok(load_version(path)?.map(|loaded| loaded.ff))
+}
+
+/// A single-file vault as it was loaded, which a later save replaces
+/// (see [`save_over`]).
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    /// The vault file.
+    pub ff: FileFormat,
+    /// For remote vaults, the ETag of the version that was fetched.
+    pub etag: Option<String>,
+}
+
+/// Like [`load_fileformat`], but keeps the remote version the vault was
+/// fetched as, so that saving it back only replaces exactly that version.
+pub fn load_version(path: &Path) -> Result<Option<Loaded>> {
+    if remote::is_remote(path) {
+        return Ok(remote::load(path)?.map(|(ff, etag)| Loaded { ff, etag }));
+    }
+
     if !path.exists() {
         return Ok(None);
     }
+    reject_directory(path)?;
+    perms::check(path)?;
+    Ok(Some(Loaded { ff: read_fileformat(path)?, etag: None }))
+}
 
+/// Reads and parses a local vault file without any further checks.
+fn read_fileformat(path: &Path) -> Result<FileFormat> {
     let mut s = String::new();
     File::open(path)
         .with_context(|| format!("Failed to open file: {}", path.display()))?
@@ -37,7 +78,7 @@ pub fn load_fileformat(path: &PathBuf) -> Result<Option<FileFormat>> {
     let ff: FileFormat = serde_json::from_str(&s)
         .with_context(|| format!("Failed to parse JSON in {}", path.display()))?;
 
-    Ok(Some(ff))
+    Ok(ff)
 }
 
 /// Saves a [`FileFormat`] structure to a JSON file.
@@ -45,10 +86,30 @@ pub fn load_fileformat(path: &PathBuf) -> Result<Option<FileFormat>> {
 /// The file is written in a human-readable format using
 /// [`serde_json::to_string_pretty()`]. The function also calls
 /// [`File::sync_all()`] to ensure all data is flushed to disk.
+/// Vault and backup files are created with mode `0600` and the backup
+/// directory with `0700` (see [`crate::perms`]).
+///
+/// The identifier of the version being replaced is prepended to
+/// `ff.parents` (together with that version's own parents), so every
+/// file records where it came from; at most [`MAX_PARENTS`] are kept.
+///
+/// Before an existing vault is overwritten, its previous contents are
+/// copied into the backup directory (see [`backup_dir`]); only the
+/// newest [`MAX_BACKUPS`] copies are kept.
+///
+/// Remote vault URLs are written to their server instead. This only
+/// creates them; to replace a remote vault, use [`save_over`] with the
+/// version it was loaded as.
+///
+/// If the vault lives in a git repository with auto-commit enabled,
+/// the saved file is committed with a message built from `change`
+/// (see [`crate::vcs`]). A failed commit is reported as a warning and
+/// does not undo the save.
 ///
 /// # Arguments
 /// * `path` — Path to the file to write.
 /// * `ff` — Reference to the [`FileFormat`] structure to save.
+/// * `change` — Description of the change, used for the commit message.
 ///
 /// # Errors
 /// Returns an error if serialization, file creation, or writing fails.
@@ -59,15 +120,48 @@ pub fn load_fileformat(path: &PathBuf) -> Result<Option<FileFormat>> {
 ///     version: 1,
 ///     salt: "abcd".to_string(),
 ///     blob: "1234".to_string(),
+///     parents: Vec::new(),
 /// };
-/// save_fileformat(&PathBuf::from("vault.json"), &ff)?;
+/// save_fileformat(&PathBuf::from("vault.json"), &ff, &Change::vault("init vault"))?;
 /// ```
-pub fn save_fileformat(path: &PathBuf, ff: &FileFormat) -> Result<()> {
-    let serialized = serde_json::to_string_pretty(ff)
+pub fn save_fileformat(path: &Path, ff: &FileFormat, change: &Change) -> Result<()> {
+    save_over(path, ff, None, change).map(drop)
+}
+
+/// Saves a [`FileFormat`] like [`save_fileformat`], replacing the version
+/// `base` that was loaded before (see [`load_version`]), and returns the
+/// version that was saved.
+///
+/// The lineage of the new version is derived from `base`. A remote vault
+/// is only overwritten if `base` is still its current version, so a write
+/// by someone else in between fails with
+/// [`crate::remote::PreconditionFailed`] instead of being lost. Without a
+/// `base`, local files take the lineage from the file they overwrite and
+/// remote vaults are only created.
+pub fn save_over(path: &Path, ff: &FileFormat, base: Option<&Loaded>, change: &Change) -> Result<Loaded> {
+    let mut ff = ff.clone();
+    if remote::is_remote(path) {
+        ff.parents = lineage(base.map(|b| &b.ff), &ff.parents);
+        let etag = remote::save(path, &ff, base.and_then(|b| b.etag.as_deref()))?;
+        return Ok(Loaded { ff, etag });
+    }
+
+    reject_directory(path)?;
+    // The previous version was already checked when it was loaded
+    let previous = match base {
+        Some(base) => Some(base.ff.clone()),
+        None => read_fileformat(path).ok(),
+    };
+    ff.parents = lineage(previous.as_ref(), &ff.parents);
+
+    let serialized = serde_json::to_string_pretty(&ff)
         .with_context(|| "Failed to serialize FileFormat to JSON")?;
 
-    let mut f = File::create(path)
-        .with_context(|| format!("Failed to create file: {}", path.display()))?;
+    if path.exists() {
+        write_backup(path)?;
+    }
+
+    let mut f = perms::create_file(path)?;
 
     f.write_all(serialized.as_bytes())
         .with_context(|| format!("Failed to write data to file: {}", path.display()))?;
@@ -75,5 +169,473 @@ pub fn save_fileformat(path: &PathBuf, ff: &FileFormat) -> Result<()> {
     f.sync_all()
         .with_context(|| format!("Failed to sync file to disk: {}", path.display()))?;
 
+    if let Err(e) = vcs::auto_commit(path, change) {
+        eprintln!("⚠️  Vault saved, but auto-commit failed: {e}");
+    }
+
+    Ok(Loaded { ff, etag: None })
+}
+
+/// Fails for directory vaults, which have no single [`FileFormat`] to read or write.
+fn reject_directory(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        return Err(anyhow!(
+            "{} is a directory vault; this command only works on single-file vaults (see 'convert')",
+            path.display()
+        ));
+    }
     Ok(())
 }
+
+/// Returns `true` if a vault already exists at `path` (locally or remotely).
+pub fn vault_exists(path: &Path) -> Result<bool> {
+    if remote::is_remote(path) {
+        return Ok(remote::load(path)?.is_some());
+    }
+    Ok(path.exists())
+}
+
+/// How a local vault is laid out on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// A single encrypted `vault.json` file.
+    File,
+    /// A directory with one encrypted file per entry (see [`crate::dirvault`]).
+    Directory,
+}
+
+impl Layout {
+    /// Returns the layout of the vault at `path`; directories use [`Layout::Directory`].
+    pub fn of(path: &Path) -> Self {
+        if path.is_dir() {
+            Layout::Directory
+        } else {
+            Layout::File
+        }
+    }
+
+    /// Returns the other layout, the target of `convert`.
+    pub fn other(self) -> Self {
+        match self {
+            Layout::File => Layout::Directory,
+            Layout::Directory => Layout::File,
+        }
+    }
+}
+
+impl std::fmt::Display for Layout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Layout::File => "single file",
+            Layout::Directory => "directory",
+        })
+    }
+}
+
+/// An encrypted vault in either [`Layout`], read from disk but not yet decrypted.
+pub struct LockedVault {
+    path: PathBuf,
+    layout: Layout,
+    /// The vault file, or the manifest of a directory vault.
+    header: FileFormat,
+    /// The vault file as loaded, which saves replace (single-file vaults only).
+    base: Option<Loaded>,
+}
+
+/// A decrypted vault, remembering where and how to save it again.
+///
+/// Commands edit [`OpenVault::vault`] and call [`OpenVault::save`]; the
+/// derived key is kept, so saving does not ask for the password again.
+pub struct OpenVault {
+    /// The decrypted vault contents.
+    pub vault: Vault,
+    path: PathBuf,
+    layout: Layout,
+    header: FileFormat,
+    /// The vault file as last loaded or saved, which the next save replaces
+    /// (single-file vaults only; `None` until a new vault is first saved).
+    base: Option<Loaded>,
+    key: [u8; KEY_LEN],
+    /// Contents as last loaded or saved, so directory vaults only rewrite changed entries.
+    saved: Vault,
+}
+
+/// Reads the vault at `path` in whichever layout it uses.
+///
+/// Returns `Ok(None)` if there is no vault at `path`. Remote vault URLs
+/// always use [`Layout::File`].
+pub fn open_vault(path: &Path) -> Result<Option<LockedVault>> {
+    let layout = Layout::of(path);
+    let (header, base) = match layout {
+        Layout::File => match load_version(path)? {
+            Some(loaded) => (loaded.ff.clone(), Some(loaded)),
+            None => return Ok(None),
+        },
+        Layout::Directory => {
+            perms::check(path)?;
+            (dirvault::load_manifest(path)?, None)
+        }
+    };
+    Ok(Some(LockedVault {
+        path: path.to_path_buf(),
+        layout,
+        header,
+        base,
+    }))
+}
+
+impl LockedVault {
+    /// Returns the layout of this vault.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Derives the key from `master` and decrypts the vault.
+    ///
+    /// # Errors
+    /// Returns an error if the password is wrong or a file is corrupted.
+    pub fn unlock(self, master: &str) -> Result<OpenVault> {
+        let salt = general_purpose::STANDARD.decode(&self.header.salt)?;
+        let blob = general_purpose::STANDARD.decode(&self.header.blob)?;
+        let key = derive_key(master, &salt)?;
+
+        let mut vault = decrypt_vault_with_key(&blob, &key)?;
+        if self.layout == Layout::Directory {
+            vault.entries = dirvault::read_entries(&self.path, &key)?;
+            vault.assign_missing_ids();
+        }
+
+        Ok(OpenVault {
+            saved: vault.clone(),
+            vault,
+            path: self.path,
+            layout: self.layout,
+            header: self.header,
+            base: self.base,
+            key,
+        })
+    }
+}
+
+impl OpenVault {
+    /// Starts a new, empty vault at `path` with a fresh salt.
+    ///
+    /// Nothing is written until [`OpenVault::save`] is called.
+    pub fn create(path: &Path, layout: Layout, master: &str) -> Result<Self> {
+        let salt = generate_salt();
+        let key = derive_key(master, &salt)?;
+        Ok(OpenVault {
+            vault: Vault::default(),
+            path: path.to_path_buf(),
+            layout,
+            header: FileFormat {
+                version: 1,
+                salt: general_purpose::STANDARD.encode(salt),
+                blob: String::new(),
+                parents: Vec::new(),
+            },
+            base: None,
+            key,
+            saved: Vault::default(),
+        })
+    }
+
+    /// Encrypts and saves the vault in its layout.
+    ///
+    /// Single-file vaults go through [`save_fileformat`] (backups, lineage,
+    /// remote upload). Directory vaults only rewrite the entry files that
+    /// changed. Either way the change is auto-committed if enabled.
+    pub fn save(&mut self, change: &Change) -> Result<()> {
+        let saved = self.write_to(&self.path.clone(), self.layout, change)?;
+        if saved.is_some() {
+            self.base = saved;
+        }
+        self.saved = self.vault.clone();
+        Ok(())
+    }
+
+    /// Saves a copy of the vault to a new location `path` in the given layout,
+    /// keeping the salt (and so the master password).
+    /// Attachment blobs are copied along, before the vault itself is written.
+    pub fn save_as(&self, path: &Path, layout: Layout, change: &Change) -> Result<()> {
+        if !remote::is_remote(&self.path) && !remote::is_remote(path) {
+            blobs::copy_all(&self.path, self.layout, path, layout)?;
+        }
+        self.write_to(path, layout, change).map(drop)
+    }
+
+    /// Records that the entry `key` was used just now.
+    ///
+    /// The time goes to the local usage file (see [`crate::usage`]); the
+    /// vault itself is not written, committed or uploaded.
+    pub fn record_use(&self, key: &str) -> Result<()> {
+        match self.vault.entries.get(key) {
+            Some(entry) => usage::record(&self.path, &self.key, entry.id),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the rotation whose hook was started but never finished, if
+    /// any (see [`crate::journal`]).
+    pub fn pending_rotation(&self) -> Result<Option<Pending>> {
+        journal::read(&self.path, &self.key)
+    }
+
+    /// Records `pending` in the local journal while its hook runs; the vault
+    /// itself is not written.
+    pub fn begin_rotation(&self, pending: &Pending) -> Result<()> {
+        journal::write(&self.path, &self.key, pending)
+    }
+
+    /// Removes the journal once a rotation has finished, either way.
+    pub fn end_rotation(&self) -> Result<()> {
+        journal::clear(&self.path)
+    }
+
+    /// When `entry` was last used: the later of the time recorded in the
+    /// usage file and the one older versions stored in the vault.
+    pub fn last_used(&self, entry: &Entry) -> Option<DateTime<Utc>> {
+        let recorded = usage::load(&self.path, &self.key).get(&entry.id).copied();
+        recorded.max(entry.last_used_at)
+    }
+
+    /// Encrypts `data` into the sidecar blob store as the blob `id`.
+    ///
+    /// # Errors
+    /// Returns an error for remote vaults, which have no blob store, and for
+    /// vaults synced with `passmgr-server`, which only carries the vault.
+    pub fn write_blob(&self, id: &Uuid, data: &[u8]) -> Result<()> {
+        self.local_blobs()?;
+        if ServerLink::path(&self.path).exists() {
+            return Err(anyhow!(
+                "vaults synced with passmgr-server only hold attachments stored inside the vault (see 'config attachment-inline-limit')"
+            ));
+        }
+        blobs::write(&self.path, self.layout, &self.key, id, data)
+    }
+
+    /// Reads and decrypts the blob `id` from the sidecar blob store.
+    pub fn read_blob(&self, id: &Uuid) -> Result<Vec<u8>> {
+        self.local_blobs()?;
+        blobs::read(&self.path, self.layout, &self.key, id)
+    }
+
+    /// Deletes the blob `id` from the sidecar blob store.
+    pub fn remove_blob(&self, id: &Uuid) -> Result<()> {
+        self.local_blobs()?;
+        blobs::remove(&self.path, self.layout, id)
+    }
+
+    /// Deletes the sidecar blobs of `entry`'s attachments, once the entry
+    /// itself is gone for good.
+    pub fn remove_entry_blobs(&self, entry: &Entry) -> Result<()> {
+        for attachment in &entry.attachments {
+            if let AttachmentData::Blob(id) = &attachment.data {
+                self.remove_blob(id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn local_blobs(&self) -> Result<()> {
+        if remote::is_remote(&self.path) {
+            return Err(anyhow!(
+                "remote vaults only hold attachments stored inside the vault (see 'config attachment-inline-limit')"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the vault to `path` in `layout`, returning the saved version
+    /// of single-file vaults.
+    fn write_to(&self, path: &Path, layout: Layout, change: &Change) -> Result<Option<Loaded>> {
+        let same_place = path == self.path && layout == self.layout;
+        match layout {
+            Layout::File => {
+                let blob = encrypt_vault_with_key(&self.vault, &self.key)?;
+                let ff = FileFormat {
+                    version: self.header.version,
+                    salt: self.header.salt.clone(),
+                    blob: general_purpose::STANDARD.encode(&blob),
+                    parents: Vec::new(),
+                };
+                let base = if same_place { self.base.as_ref() } else { None };
+                save_over(path, &ff, base, change).map(Some)
+            }
+            Layout::Directory => {
+                if remote::is_remote(path) {
+                    return Err(anyhow!("remote vaults cannot use the directory layout"));
+                }
+                if same_place && path.is_dir() {
+                    dirvault::write(path, &self.header, &self.key, &self.saved, &self.vault, false)?;
+                } else {
+                    dirvault::create(path, &self.header, &self.key, &self.vault)?;
+                }
+                if let Err(e) = vcs::auto_commit(path, change) {
+                    eprintln!("⚠️  Vault saved, but auto-commit failed: {e}");
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Number of ancestor identifiers recorded in [`FileFormat::parents`].
+pub const MAX_PARENTS: usize = 32;
+
+/// Builds the parent list for a new version that replaces `previous`.
+///
+/// `extra` holds additional parents chosen by the caller (e.g. the other
+/// side of a merge). Duplicates are dropped and the list is truncated.
+fn lineage(previous: Option<&FileFormat>, extra: &[String]) -> Vec<String> {
+    let mut parents: Vec<String> = Vec::new();
+    let candidates = previous
+        .map(|p| p.id().to_string())
+        .into_iter()
+        .chain(extra.iter().cloned())
+        .chain(previous.into_iter().flat_map(|p| p.parents.iter().cloned()));
+
+    for id in candidates {
+        if !parents.contains(&id) {
+            parents.push(id);
+        }
+    }
+    parents.truncate(MAX_PARENTS);
+    parents
+}
+
+/// Number of previous vault versions kept in the backup directory.
+pub const MAX_BACKUPS: usize = 10;
+
+/// Returns the directory holding backups of the given vault.
+///
+/// For `vault.json` this is `vault.json.backups` next to the vault.
+pub fn backup_dir(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".backups");
+    path.with_file_name(name)
+}
+
+/// Lists the backups of a vault, newest first.
+///
+/// Returns an empty list if no backup has been written yet.
+pub fn list_backups(path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = backup_dir(path);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read backup directory: {}", dir.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+
+    // Backup names are millisecond timestamps, so a reverse sort is newest first.
+    backups.sort();
+    backups.reverse();
+    Ok(backups)
+}
+
+/// Copies the current vault file into its backup directory and prunes old copies.
+fn write_backup(path: &Path) -> Result<()> {
+    let dir = backup_dir(path);
+    perms::create_dir_all(&dir)?;
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let target = dir.join(format!("{millis:015}.json"));
+    perms::copy_file(path, &target)
+        .with_context(|| format!("Failed to back up vault to {}", target.display()))?;
+
+    for old in list_backups(path)?.into_iter().skip(MAX_BACKUPS) {
+        fs::remove_file(&old)
+            .with_context(|| format!("Failed to remove old backup: {}", old.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use tiny_http::{Header, Method, Response, Server};
+
+    /// Serves one file over WebDAV from memory, honouring `If-Match` and
+    /// `If-None-Match` like a real server, and returns its vault URL.
+    fn webdav_server() -> PathBuf {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("webdav+http://{}/vault.json", server.server_addr().to_ip().unwrap());
+        std::thread::spawn(move || {
+            let mut file: Option<(u32, Vec<u8>)> = None;
+            for mut request in server.incoming_requests() {
+                let header = |name: &'static str| {
+                    request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.to_string())
+                };
+                let etag = |n: u32| Header::from_bytes("ETag", format!("\"{n}\"")).unwrap();
+                let current = file.as_ref().map(|(n, _)| format!("\"{n}\""));
+                let response = match request.method() {
+                    Method::Get => match &file {
+                        Some((n, body)) => Response::from_data(body.clone()).with_header(etag(*n)),
+                        None => Response::from_data(Vec::new()).with_status_code(404),
+                    },
+                    Method::Put => {
+                        let allowed = match (header("If-Match"), header("If-None-Match")) {
+                            (Some(wanted), _) => current.as_ref() == Some(&wanted),
+                            (None, Some(_)) => current.is_none(),
+                            (None, None) => true,
+                        };
+                        if allowed {
+                            let mut body = Vec::new();
+                            request.as_reader().read_to_end(&mut body).unwrap();
+                            let n = file.as_ref().map_or(1, |(n, _)| n + 1);
+                            file = Some((n, body));
+                            Response::from_data(Vec::new()).with_status_code(201).with_header(etag(n))
+                        } else {
+                            Response::from_data(Vec::new()).with_status_code(412)
+                        }
+                    }
+                    _ => Response::from_data(Vec::new()).with_status_code(405),
+                };
+                let _ = request.respond(response);
+            }
+        });
+        PathBuf::from(url)
+    }
+
+    /// A save only replaces the remote version its vault was loaded as,
+    /// even when another process refreshed the shared cache in between.
+    #[test]
+    fn rejects_saves_over_a_newer_remote_version() {
+        let cache = TempDir::new().unwrap();
+        std::env::set_var("XDG_CACHE_HOME", cache.path());
+        let url = webdav_server();
+
+        let mut created = OpenVault::create(&url, Layout::File, "master").unwrap();
+        created.save(&Change::vault("init vault")).unwrap();
+
+        let mut ours = open_vault(&url).unwrap().unwrap().unlock("master").unwrap();
+        let mut theirs = open_vault(&url).unwrap().unwrap().unlock("master").unwrap();
+        theirs.vault.entries.insert("theirs".into(), Entry::new("b".into(), "2".into(), None));
+        theirs.save(&Change::entry("add", "theirs")).unwrap();
+        // Any load, e.g. a `get` in a third process, refreshes the cache
+        open_vault(&url).unwrap().unwrap();
+
+        ours.vault.entries.insert("ours".into(), Entry::new("a".into(), "1".into(), None));
+        let err = ours.save(&Change::entry("add", "ours")).unwrap_err();
+        assert!(err.is::<remote::PreconditionFailed>(), "{err:#}");
+
+        let stored = open_vault(&url).unwrap().unwrap().unlock("master").unwrap();
+        assert!(stored.vault.entries.contains_key("theirs"));
+        assert!(!stored.vault.entries.contains_key("ours"));
+
+        // Saving again continues from the version the last save stored
+        theirs.vault.entries.remove("theirs");
+        theirs.save(&Change::entry("remove", "theirs")).unwrap();
+        let latest = load_fileformat(&url).unwrap().unwrap();
+        assert_eq!(latest.parents.len(), 2);
+    }
+}
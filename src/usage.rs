@@ -0,0 +1,92 @@
+//! Local record of when entries were last used.
+//!
+//! Looking an entry up should not rewrite the vault: that would leave
+//! uncommitted changes in a git-tracked vault, upload the vault to its
+//! remote store and add a version to its lineage on every read. Times of use
+//! are therefore kept in a sidecar file of their own, encrypted with the
+//! vault key and keyed by entry id:
+//!
+//! ```text
+//! vault.json
+//! vault.json.usage          single-file layout
+//!
+//! vault/
+//! vault.usage               directory layout (next to, not in, the vault)
+//! ```
+//!
+//! Remote vaults keep it next to their local cache. The file is never
+//! backed up, committed or uploaded; losing it only loses the times of use.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::{
+    crypto::{decrypt_json_with_key, encrypt_json_with_key, KEY_LEN},
+    perms,
+    remote,
+};
+
+/// When each entry, by id, was last used.
+pub type Usage = BTreeMap<Uuid, DateTime<Utc>>;
+
+/// Returns the usage file of the vault at `path`.
+pub fn path(vault: &Path) -> PathBuf {
+    if remote::is_remote(vault) {
+        return remote::cache_sidecar(vault, "usage");
+    }
+    let mut name = vault.file_name().unwrap_or_default().to_os_string();
+    name.push(".usage");
+    vault.with_file_name(name)
+}
+
+/// Reads the usage file of the vault at `path`.
+///
+/// A missing or unreadable file (e.g. one written under an old master
+/// password) counts as empty, since times of use are only informational.
+pub fn load(vault: &Path, key: &[u8; KEY_LEN]) -> Usage {
+    fs::read(path(vault))
+        .ok()
+        .and_then(|blob| decrypt_json_with_key(&blob, key).ok())
+        .unwrap_or_default()
+}
+
+/// Records that the entry `id` was used just now.
+pub fn record(vault: &Path, key: &[u8; KEY_LEN], id: Uuid) -> Result<()> {
+    let mut usage = load(vault, key);
+    usage.insert(id, Utc::now());
+    let file = path(vault);
+    if let Some(dir) = file.parent().filter(|d| !d.as_os_str().is_empty()) {
+        perms::create_dir_all(dir)?;
+    }
+    perms::write_file(&file, &encrypt_json_with_key(&usage, key)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Usage files sit next to the vault, outside directory vaults.
+    #[test]
+    fn places_usage_next_to_the_vault() {
+        assert_eq!(path(Path::new("dir/vault.json")), PathBuf::from("dir/vault.json.usage"));
+        assert_eq!(path(Path::new("dir/vault/")), PathBuf::from("dir/vault.usage"));
+    }
+
+    /// Recorded times survive a reload; the wrong key reads as empty.
+    #[test]
+    fn records_and_reloads_times_of_use() {
+        let dir = TempDir::new().unwrap();
+        let vault = dir.path().join("vault.json");
+        let (key, other) = ([7u8; KEY_LEN], [8u8; KEY_LEN]);
+        let id = Uuid::new_v4();
+
+        assert!(load(&vault, &key).is_empty());
+        record(&vault, &key, id).unwrap();
+        assert!(load(&vault, &key).contains_key(&id));
+        assert!(load(&vault, &other).is_empty());
+    }
+}
@@ -0,0 +1,459 @@
+//! Git integration for rust-passmgr.
+//!
+//! Keeps the vault file under version control by shelling out to the
+//! `git` binary, so any remote git understands (including a local bare
+//! repository) can be used for history and sync.
+//!
+//! Auto-commit is opt-in and controlled by git config keys stored in the
+//! repository that contains the vault:
+//! - `passmgr.autocommit` — commit after every successful save.
+//! - `passmgr.commitNames` — include entry names in commit messages.
+//!
+//! The sidecar blob store of a single-file vault (see [`crate::blobs`]) is
+//! committed along with the vault file, so sync carries its attachments.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{anyhow, Context, Result};
+use crate::{blobs, storage::Layout};
+
+/// Describes a change to the vault, used to build commit messages.
+///
+/// The entry name is only written to the message when the repository
+/// opts in via `passmgr.commitNames`, so history does not leak which
+/// accounts the vault contains.
+#[derive(Debug, Clone, Copy)]
+pub struct Change<'a> {
+    /// Short verb describing the change (e.g. "add", "remove").
+    pub action: &'a str,
+    /// Entry name the change applies to, if any.
+    pub subject: Option<&'a str>,
+}
+
+impl<'a> Change<'a> {
+    /// Creates a change that touches a single entry.
+    pub fn entry(action: &'a str, key: &'a str) -> Self {
+        Self { action, subject: Some(key) }
+    }
+
+    /// Creates a change that applies to the vault as a whole.
+    ///
+    /// The action is used verbatim as the commit message.
+    pub fn vault(action: &'a str) -> Self {
+        Self { action, subject: None }
+    }
+
+    /// Renders the commit message for this change.
+    pub fn message(&self, with_names: bool) -> String {
+        match self.subject {
+            Some(key) if with_names => format!("{} {}", self.action, key),
+            Some(_) => format!("{} entry", self.action),
+            None => self.action.to_string(),
+        }
+    }
+}
+
+/// Runs `git` inside `dir` and returns its trimmed standard output.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .with_context(|| "Failed to run git (is it installed?)")?;
+
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        return Err(anyhow!("git {} failed: {}", args.join(" "), stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+}
+
+/// Returns the directory containing the vault file.
+fn vault_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Returns the vault file name as a path relative to [`vault_dir`].
+fn vault_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow!("invalid vault path: {}", path.display()))
+}
+
+/// Returns the paths committed for the vault, relative to [`vault_dir`]:
+/// the vault itself and, once it has one, its sidecar blob store.
+fn tracked_names(path: &Path) -> Result<Vec<String>> {
+    let mut names = vec![vault_name(path)?];
+    let store = blobs::dir(path, Layout::File);
+    if !path.is_dir() && store.is_dir() {
+        names.push(vault_name(&store)?);
+    }
+    Ok(names)
+}
+
+/// Returns `true` if the vault lives inside a git working tree.
+pub fn is_tracked_repo(path: &Path) -> bool {
+    git(&vault_dir(path), &["rev-parse", "--is-inside-work-tree"])
+        .map(|s| s == "true")
+        .unwrap_or(false)
+}
+
+/// Reads a boolean git config value, treating missing keys as `false`.
+fn config_flag(dir: &Path, key: &str) -> bool {
+    git(dir, &["config", "--bool", "--get", key])
+        .map(|s| s == "true")
+        .unwrap_or(false)
+}
+
+/// Initializes a git repository next to the vault and enables auto-commit.
+///
+/// If the vault already lives in a repository, only the config is updated.
+pub fn enable(path: &Path, with_names: bool) -> Result<()> {
+    let dir = vault_dir(path);
+    if !is_tracked_repo(path) {
+        git(&dir, &["init", "--quiet"])?;
+    }
+    git(&dir, &["config", "passmgr.autocommit", "true"])?;
+    git(&dir, &["config", "passmgr.commitNames", if with_names { "true" } else { "false" }])?;
+    Ok(())
+}
+
+/// Commits the vault file if auto-commit is enabled for its repository.
+///
+/// Does nothing when the vault is not in a git repository, when
+/// `passmgr.autocommit` is not set, when the file is unchanged, or while
+/// a merge is in progress (the merge commit is made by [`commit_merge`]).
+/// Only the vault and its blob store are committed; other staged changes
+/// are left alone.
+pub fn auto_commit(path: &Path, change: &Change) -> Result<()> {
+    if !is_tracked_repo(path) {
+        return Ok(());
+    }
+
+    let dir = vault_dir(path);
+    if !config_flag(&dir, "passmgr.autocommit") || merge_in_progress(&dir) {
+        return Ok(());
+    }
+
+    let names = tracked_names(path)?;
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    git(&dir, &[&["add", "--"], names.as_slice()].concat())?;
+
+    let unchanged = Command::new("git")
+        .arg("-C")
+        .arg(&dir)
+        .args(["diff", "--cached", "--quiet", "--"])
+        .args(&names)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if unchanged {
+        return Ok(());
+    }
+
+    let message = change.message(config_flag(&dir, "passmgr.commitNames"));
+    git(&dir, &[&["commit", "--quiet", "-m", &message, "--"], names.as_slice()].concat())?;
+    Ok(())
+}
+
+/// Returns the one-line history of the vault file, newest first.
+pub fn log(path: &Path, limit: Option<usize>) -> Result<Vec<String>> {
+    let dir = vault_dir(path);
+    let name = vault_name(path)?;
+    let limit = limit.map(|n| format!("-n{n}"));
+
+    let mut args = vec!["log", "--date=short", "--format=%h  %ad  %s"];
+    if let Some(l) = &limit {
+        args.push(l);
+    }
+    args.extend(["--", &name]);
+
+    let out = git(&dir, &args)?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Returns the hashes of the commits that touched the vault file, newest first.
+pub fn revisions(path: &Path, limit: usize) -> Result<Vec<String>> {
+    let dir = vault_dir(path);
+    let name = vault_name(path)?;
+    let limit = format!("-n{limit}");
+
+    let out = git(&dir, &["log", &limit, "--format=%H", "--", &name])?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Returns the raw contents of the vault file at the given revision.
+pub fn show(path: &Path, rev: &str) -> Result<String> {
+    let dir = vault_dir(path);
+    let name = vault_name(path)?;
+    git(&dir, &["show", &format!("{rev}:./{name}")])
+}
+
+/// Returns `true` while a `git merge` is waiting to be concluded.
+fn merge_in_progress(dir: &Path) -> bool {
+    git(dir, &["rev-parse", "-q", "--verify", "MERGE_HEAD"]).is_ok()
+}
+
+/// Outcome of [`sync`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// Local and remote histories were reconciled and pushed.
+    Synced,
+    /// Both sides changed the vault file. The merge is left in progress so
+    /// the caller can merge entries and conclude it with [`commit_merge`]
+    /// and [`push_merge`], or give up with [`abort_merge`].
+    Conflict,
+}
+
+/// The three versions of the vault file involved in a conflicted merge.
+#[derive(Debug)]
+pub struct ConflictVersions {
+    /// Version at the merge base, if the file existed there.
+    pub base: Option<String>,
+    /// Local version.
+    pub ours: Option<String>,
+    /// Incoming version from the remote.
+    pub theirs: Option<String>,
+    /// `true` if the local commit is at least as recent as the incoming one.
+    pub ours_newer: bool,
+}
+
+/// Pulls from `remote`, merges into the current branch and pushes back.
+///
+/// An empty remote (such as a freshly created bare repository) is
+/// populated by the push. If the merge conflicts on anything other than
+/// the vault file, it is aborted and an error is returned.
+pub fn sync(path: &Path, remote: &str) -> Result<SyncOutcome> {
+    if !is_tracked_repo(path) {
+        return Err(anyhow!("{} is not inside a git repository", path.display()));
+    }
+
+    let dir = vault_dir(path);
+    let branch = git(&dir, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+
+    let remote_has_branch = !git(&dir, &["ls-remote", "--heads", remote, &branch])?.is_empty();
+    if remote_has_branch {
+        git(&dir, &["fetch", "--quiet", remote, &branch])?;
+        let merged = git(&dir, &["merge", "--no-edit", "--quiet", "FETCH_HEAD"]);
+        if let Err(e) = merged {
+            let unmerged = git(&dir, &["diff", "--name-only", "--diff-filter=U", "--relative"])?;
+            let name = vault_name(path)?;
+            if unmerged.lines().any(|l| l != name) || !merge_in_progress(&dir) {
+                let _ = git(&dir, &["merge", "--abort"]);
+                return Err(e);
+            }
+            return Ok(SyncOutcome::Conflict);
+        }
+    }
+
+    push(&dir, remote, &branch)?;
+    Ok(SyncOutcome::Synced)
+}
+
+/// Pushes the current branch to `remote`.
+fn push(dir: &Path, remote: &str, branch: &str) -> Result<()> {
+    git(dir, &["push", "--quiet", remote, &format!("HEAD:{branch}")])?;
+    Ok(())
+}
+
+/// Reads the base, local and incoming versions of a conflicted vault file.
+pub fn conflict_versions(path: &Path) -> Result<ConflictVersions> {
+    let dir = vault_dir(path);
+    let name = vault_name(path)?;
+    let stage = |n: u8| git(&dir, &["show", &format!(":{n}:./{name}")]).ok();
+    let time = |rev: &str| -> u64 {
+        git(&dir, &["log", "-1", "--format=%ct", rev])
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .unwrap_or_default()
+    };
+
+    Ok(ConflictVersions {
+        base: stage(1),
+        ours: stage(2),
+        theirs: stage(3),
+        ours_newer: time("HEAD") >= time("MERGE_HEAD"),
+    })
+}
+
+/// Commits the resolved vault file, and its blob store, as the merge result.
+///
+/// Once this succeeds the merge is concluded and can no longer be aborted;
+/// publish it with [`push_merge`].
+pub fn commit_merge(path: &Path) -> Result<()> {
+    let dir = vault_dir(path);
+    let names = tracked_names(path)?;
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    git(&dir, &[&["add", "--"], names.as_slice()].concat())?;
+    git(&dir, &["commit", "--quiet", "--no-edit"])?;
+    Ok(())
+}
+
+/// Pushes the current branch, including a concluded merge, to `remote`.
+pub fn push_merge(path: &Path, remote: &str) -> Result<()> {
+    let dir = vault_dir(path);
+    let branch = git(&dir, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    push(&dir, remote, &branch)
+}
+
+/// Aborts an in-progress merge, restoring the pre-sync state.
+pub fn abort_merge(path: &Path) -> Result<()> {
+    git(&vault_dir(path), &["merge", "--abort"])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Sets a committer identity so commits work without a global config.
+    fn identify(dir: &Path) {
+        git(dir, &["config", "user.name", "passmgr"]).unwrap();
+        git(dir, &["config", "user.email", "passmgr@example.com"]).unwrap();
+    }
+
+    /// Writes `contents` to the vault and auto-commits it.
+    fn save(vault: &Path, contents: &str, action: &str) {
+        fs::write(vault, contents).unwrap();
+        auto_commit(vault, &Change::vault(action)).unwrap();
+    }
+
+    /// Sets up a bare remote and two clones of it that both hold `vault.json`.
+    fn two_clones(dir: &Path) -> (String, PathBuf, PathBuf) {
+        let remote = dir.join("remote.git");
+        git(dir, &["init", "--quiet", "--bare", "remote.git"]).unwrap();
+        let remote = remote.to_string_lossy().into_owned();
+
+        let ours = dir.join("ours").join("vault.json");
+        fs::create_dir_all(ours.parent().unwrap()).unwrap();
+        enable(&ours, false).unwrap();
+        identify(ours.parent().unwrap());
+        save(&ours, "base\n", "init vault");
+        assert_eq!(sync(&ours, &remote).unwrap(), SyncOutcome::Synced);
+
+        git(dir, &["clone", "--quiet", &remote, "theirs"]).unwrap();
+        let theirs = dir.join("theirs").join("vault.json");
+        enable(&theirs, false).unwrap();
+        identify(theirs.parent().unwrap());
+        (remote, ours, theirs)
+    }
+
+    /// Entry names only appear in messages when the repository opts in.
+    #[test]
+    fn renders_commit_messages() {
+        assert_eq!(Change::entry("add", "mail").message(true), "add mail");
+        assert_eq!(Change::entry("add", "mail").message(false), "add entry");
+        assert_eq!(Change::vault("merge vault").message(true), "merge vault");
+    }
+
+    /// Saves are only committed once auto-commit is enabled.
+    #[test]
+    fn commits_only_when_enabled() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let vault = dir.join("vault.json");
+        git(dir, &["init", "--quiet"]).unwrap();
+        identify(dir);
+
+        save(&vault, "one\n", "init vault");
+        assert!(log(&vault, None).is_err());
+
+        enable(&vault, true).unwrap();
+        save(&vault, "two\n", "add");
+        save(&vault, "two\n", "unchanged");
+        let history = log(&vault, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].ends_with("add"));
+        assert_eq!(show(&vault, "HEAD").unwrap(), "two");
+    }
+
+    /// The blob store of a single-file vault is committed with it and
+    /// reaches the other clone on sync.
+    #[test]
+    fn syncs_the_blob_store() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let (remote, ours, theirs) = two_clones(dir);
+
+        let store = blobs::dir(&ours, Layout::File);
+        fs::create_dir(&store).unwrap();
+        fs::write(store.join("a.blob"), "blob\n").unwrap();
+        save(&ours, "ours\n", "attach file");
+        fs::remove_file(store.join("a.blob")).unwrap();
+        fs::write(store.join("b.blob"), "blob\n").unwrap();
+        save(&ours, "ours\n", "attach file");
+        assert_eq!(git(ours.parent().unwrap(), &["rev-list", "--count", "HEAD"]).unwrap(), "3");
+        sync(&ours, &remote).unwrap();
+
+        sync(&theirs, &remote).unwrap();
+        let theirs_store = blobs::dir(&theirs, Layout::File);
+        assert!(!theirs_store.join("a.blob").exists());
+        assert_eq!(fs::read_to_string(theirs_store.join("b.blob")).unwrap(), "blob\n");
+    }
+
+    /// Changes on one side are pulled into the other.
+    #[test]
+    fn syncs_through_a_bare_remote() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let (remote, ours, theirs) = two_clones(dir);
+
+        save(&theirs, "theirs\n", "edit");
+        assert_eq!(sync(&theirs, &remote).unwrap(), SyncOutcome::Synced);
+        assert_eq!(sync(&ours, &remote).unwrap(), SyncOutcome::Synced);
+        assert_eq!(fs::read_to_string(&ours).unwrap(), "theirs\n");
+    }
+
+    /// A conflicted merge exposes all three versions and can be aborted.
+    #[test]
+    fn aborts_a_conflicted_merge() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let (remote, ours, theirs) = two_clones(dir);
+        save(&theirs, "theirs\n", "edit");
+        sync(&theirs, &remote).unwrap();
+        save(&ours, "ours\n", "edit");
+
+        assert_eq!(sync(&ours, &remote).unwrap(), SyncOutcome::Conflict);
+        let versions = conflict_versions(&ours).unwrap();
+        assert_eq!(versions.base.as_deref(), Some("base"));
+        assert_eq!(versions.ours.as_deref(), Some("ours"));
+        assert_eq!(versions.theirs.as_deref(), Some("theirs"));
+
+        abort_merge(&ours).unwrap();
+        assert!(!merge_in_progress(ours.parent().unwrap()));
+        assert_eq!(fs::read_to_string(&ours).unwrap(), "ours\n");
+    }
+
+    /// A committed merge that cannot be pushed stays committed, and a later
+    /// push publishes it.
+    #[test]
+    fn keeps_a_committed_merge_when_the_push_fails() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let (remote, ours, theirs) = two_clones(dir);
+        save(&theirs, "theirs\n", "edit");
+        sync(&theirs, &remote).unwrap();
+        save(&ours, "ours\n", "edit");
+        assert_eq!(sync(&ours, &remote).unwrap(), SyncOutcome::Conflict);
+
+        save(&ours, "merged\n", "merge vault");
+        commit_merge(&ours).unwrap();
+        let missing = dir.join("missing.git").to_string_lossy().into_owned();
+        assert!(push_merge(&ours, &missing).is_err());
+        assert!(!merge_in_progress(ours.parent().unwrap()));
+        assert!(abort_merge(&ours).is_err());
+        assert_eq!(show(&ours, "HEAD").unwrap(), "merged");
+
+        push_merge(&ours, &remote).unwrap();
+        assert_eq!(sync(&theirs, &remote).unwrap(), SyncOutcome::Synced);
+        assert_eq!(fs::read_to_string(&theirs).unwrap(), "merged\n");
+    }
+}
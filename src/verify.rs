@@ -0,0 +1,171 @@
+//! Integrity checks for vault files.
+//!
+//! The checks are split by what they need: [`check_header`] and
+//! [`check_blob`] only look at the stored file and run before any password
+//! is asked for, while [`check_vault`] validates the decrypted contents.
+//! Each returns a list of human-readable problems; an empty list means the
+//! part is intact.
+
+use std::collections::{BTreeMap, BTreeSet};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use crate::{
+    crypto::{NONCE_LEN, SALT_LEN},
+    model::{Entry, FileFormat},
+    storage::MAX_PARENTS,
+};
+
+/// Format versions this build can read.
+pub const KNOWN_VERSIONS: &[u8] = &[1];
+
+/// Length of the AES-GCM authentication tag appended to every ciphertext.
+const TAG_LEN: usize = 16;
+
+/// Checks the JSON structure and header fields of a stored [`FileFormat`].
+///
+/// Returns the parsed file (if the JSON matches the format at all) and
+/// the problems found: unknown version, invalid base64, wrong salt length,
+/// a blob too short to hold a nonce and tag, and malformed `parents`.
+pub fn check_header(text: &str) -> (Option<FileFormat>, Vec<String>) {
+    let ff: FileFormat = match serde_json::from_str(text) {
+        Ok(ff) => ff,
+        Err(e) if e.is_syntax() || e.is_eof() => {
+            return (None, vec![format!("not valid JSON ({e})")]);
+        }
+        Err(e) => return (None, vec![format!("JSON does not match the vault format ({e})")]),
+    };
+
+    let mut problems = Vec::new();
+    if !KNOWN_VERSIONS.contains(&ff.version) {
+        problems.push(format!("unknown format version {}", ff.version));
+    }
+
+    match general_purpose::STANDARD.decode(&ff.salt) {
+        Ok(salt) if salt.len() == SALT_LEN => {}
+        Ok(salt) => problems.push(format!("salt is {} bytes, expected {SALT_LEN}", salt.len())),
+        Err(e) => problems.push(format!("salt is not valid base64 ({e})")),
+    }
+
+    problems.extend(check_blob(&ff.blob));
+
+    let mut seen = BTreeSet::new();
+    for id in &ff.parents {
+        let valid = id.len() == 16
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/');
+        if !valid {
+            problems.push(format!("parent id {id:?} is malformed"));
+        } else if id == ff.id() {
+            problems.push(format!("parent id {id} is the vault's own id"));
+        } else if !seen.insert(id) {
+            problems.push(format!("parent id {id} is listed twice"));
+        }
+    }
+    if ff.parents.len() > MAX_PARENTS {
+        problems.push(format!("{} parent ids, at most {MAX_PARENTS} expected", ff.parents.len()));
+    }
+
+    (Some(ff), problems)
+}
+
+/// Checks that an encrypted blob is valid base64 and long enough for
+/// its nonce and authentication tag.
+pub fn check_blob(blob: &str) -> Vec<String> {
+    match general_purpose::STANDARD.decode(blob.trim()) {
+        Ok(bytes) if bytes.len() >= NONCE_LEN + TAG_LEN => Vec::new(),
+        Ok(bytes) => vec![format!(
+            "ciphertext is {} bytes, too short for a {NONCE_LEN}-byte nonce and {TAG_LEN}-byte tag",
+            bytes.len()
+        )],
+        Err(e) => vec![format!("ciphertext is not valid base64 ({e})")],
+    }
+}
+
+/// Validates the decrypted vault JSON against the [`crate::model::Vault`] schema.
+///
+/// Every entry is checked on its own, so a single broken entry is reported
+/// by name instead of failing the whole vault. Entry ids must be unique.
+pub fn check_vault(value: &Value) -> Vec<String> {
+    let Some(object) = value.as_object() else {
+        return vec!["decrypted data is not a JSON object".to_string()];
+    };
+    let Some(entries) = object.get("entries") else {
+        return vec!["decrypted vault has no `entries`".to_string()];
+    };
+    let Some(entries) = entries.as_object() else {
+        return vec!["`entries` is not a JSON object".to_string()];
+    };
+
+    let mut problems = Vec::new();
+    let mut ids: BTreeMap<&str, &str> = BTreeMap::new();
+    for (key, entry) in entries {
+        if key.is_empty() {
+            problems.push("an entry has an empty key".to_string());
+        }
+        problems.extend(check_entry(key, entry));
+        if let Some(id) = entry.get("id").and_then(Value::as_str) {
+            if let Some(other) = ids.insert(id, key) {
+                problems.push(format!("entries '{other}' and '{key}' share the id {id}"));
+            }
+        }
+    }
+    problems
+}
+
+/// Validates one decrypted entry against the [`Entry`] schema.
+pub fn check_entry(key: &str, value: &Value) -> Vec<String> {
+    match serde_json::from_value::<Entry>(value.clone()) {
+        Ok(_) => Vec::new(),
+        Err(e) => vec![format!("entry '{key}' is invalid ({e})")],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(salt_len: usize, blob_len: usize) -> String {
+        serde_json::json!({
+            "version": 1,
+            "salt": general_purpose::STANDARD.encode(vec![0u8; salt_len]),
+            "blob": general_purpose::STANDARD.encode(vec![0u8; blob_len]),
+        })
+        .to_string()
+    }
+
+    /// A well-formed header passes; truncation and bad lengths are reported.
+    #[test]
+    fn checks_header_fields() {
+        assert!(check_header(&header(SALT_LEN, 64)).1.is_empty());
+
+        let (ff, problems) = check_header("{\"version\": 1, \"salt\": \"");
+        assert!(ff.is_none());
+        assert!(problems[0].starts_with("not valid JSON"));
+
+        let (_, problems) = check_header(&header(8, 10));
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("salt is 8 bytes"));
+        assert!(problems[1].contains("too short"));
+
+        let (_, problems) = check_header("{\"version\": 1, \"salt\": \"\"}");
+        assert!(problems[0].contains("does not match"));
+    }
+
+    /// Broken entries are reported by key.
+    #[test]
+    fn checks_vault_schema() {
+        let good = serde_json::json!({"entries": {"a": {"login": "u", "password": "p", "notes": null}}});
+        assert!(check_vault(&good).is_empty());
+
+        let bad = serde_json::json!({"entries": {"a": {"login": "u"}}});
+        let problems = check_vault(&bad);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("entry 'a' is invalid"));
+
+        let id = "0b6f3c1e-2f4a-4c7e-9d1a-5e8b7c6d4f21";
+        let shared = serde_json::json!({"entries": {
+            "a": {"id": id, "login": "u", "password": "p", "notes": null},
+            "b": {"id": id, "login": "u", "password": "p", "notes": null},
+        }});
+        assert_eq!(check_vault(&shared), vec![format!("entries 'a' and 'b' share the id {id}")]);
+    }
+}